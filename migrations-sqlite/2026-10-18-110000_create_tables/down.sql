@@ -0,0 +1,5 @@
+DROP TABLE sync_states;
+DROP TABLE twitter_users;
+DROP TABLE deletions;
+DROP TABLE entities;
+DROP TABLE statuses;
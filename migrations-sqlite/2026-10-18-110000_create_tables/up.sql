@@ -0,0 +1,96 @@
+-- SQLite has neither enum types nor column comments: enums are text checked
+-- against their values, and the Postgres migrations document each column.
+-- Timestamps are RFC 3339 text in UTC, which sorts chronologically.
+
+CREATE TABLE statuses (
+  id INTEGER PRIMARY KEY,
+  text TEXT NOT NULL,
+  author_id INTEGER,
+  geolocation_lat REAL,
+  geolocation_lon REAL,
+  posted_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  fetched_via TEXT CHECK (fetched_via IN ('twitter archive')),
+  deleted_at TEXT,
+  is_repost BOOLEAN NOT NULL DEFAULT 0,
+  reposted_at TEXT,
+  is_marked BOOLEAN NOT NULL DEFAULT 0,
+  marked_at TEXT,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  source_id TEXT NOT NULL,
+  source_author TEXT NOT NULL,
+  source_app TEXT NOT NULL,
+  in_reply_to_status TEXT,
+  in_reply_to_user TEXT,
+  quoting_status TEXT,
+  public BOOLEAN NOT NULL DEFAULT 0,
+  CONSTRAINT statuses_source_id_uniq UNIQUE (source_id)
+);
+
+CREATE TABLE entities (
+  id INTEGER PRIMARY KEY,
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  status_id INTEGER NOT NULL REFERENCES statuses(id),
+  ordering INTEGER,
+  media_type TEXT NOT NULL CHECK (media_type IN ('photo', 'video', 'gif')),
+  source_id TEXT NOT NULL,
+  source_url TEXT NOT NULL,
+  original_status_source_id TEXT,
+  original_status_source_url TEXT,
+  blob_hash TEXT,
+  CONSTRAINT entities_source_id_uniq UNIQUE (source_id)
+);
+
+CREATE TABLE deletions (
+  id INTEGER PRIMARY KEY,
+  status_id INTEGER NOT NULL REFERENCES statuses(id),
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  not_before TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  executed_at TEXT,
+  sponsor TEXT NOT NULL
+);
+
+CREATE TABLE twitter_users (
+  id INTEGER PRIMARY KEY,
+  source_id TEXT NOT NULL,
+  screen_name TEXT NOT NULL,
+  name TEXT NOT NULL,
+  description TEXT,
+  location TEXT,
+  url TEXT,
+  is_verified BOOLEAN NOT NULL DEFAULT 0,
+  is_protected BOOLEAN NOT NULL DEFAULT 0,
+  is_coauthored BOOLEAN NOT NULL DEFAULT 0,
+  is_translator BOOLEAN NOT NULL DEFAULT 0,
+  statuses_count INTEGER NOT NULL DEFAULT 0,
+  following_count INTEGER NOT NULL DEFAULT 0,
+  followers_count INTEGER NOT NULL DEFAULT 0,
+  likes_count INTEGER NOT NULL DEFAULT 0,
+  listed_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  blocked_at TEXT,
+  muted_at TEXT,
+  missing BOOLEAN NOT NULL DEFAULT 0,
+  ui_language TEXT,
+  ui_timezone TEXT,
+  withheld_in TEXT,
+  withheld_scope TEXT,
+  CONSTRAINT twitter_users_source_id_uniq UNIQUE (source_id)
+);
+
+CREATE TABLE sync_states (
+  id INTEGER PRIMARY KEY,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  kind TEXT NOT NULL,
+  synced_to TEXT,
+  cursor TEXT,
+  pending_to TEXT,
+  done BOOLEAN NOT NULL DEFAULT 0,
+  last_run_at TEXT,
+  last_success_at TEXT,
+  last_error TEXT,
+  fetched_count INTEGER NOT NULL DEFAULT 0,
+  inserted_count INTEGER NOT NULL DEFAULT 0,
+  CONSTRAINT sync_states_source_kind_uniq UNIQUE (source, kind)
+);
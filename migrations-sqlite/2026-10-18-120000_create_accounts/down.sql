@@ -0,0 +1,31 @@
+CREATE TABLE sync_states_old (
+  id INTEGER PRIMARY KEY,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  kind TEXT NOT NULL,
+  synced_to TEXT,
+  cursor TEXT,
+  pending_to TEXT,
+  done BOOLEAN NOT NULL DEFAULT 0,
+  last_run_at TEXT,
+  last_success_at TEXT,
+  last_error TEXT,
+  fetched_count INTEGER NOT NULL DEFAULT 0,
+  inserted_count INTEGER NOT NULL DEFAULT 0,
+  CONSTRAINT sync_states_source_kind_uniq UNIQUE (source, kind)
+);
+
+-- Keep the oldest state of each pass, as there can only be one per source again.
+INSERT OR IGNORE INTO sync_states_old
+SELECT
+  id, source, kind, synced_to, cursor, pending_to, done, last_run_at,
+  last_success_at, last_error, fetched_count, inserted_count
+FROM sync_states
+ORDER BY id;
+
+DROP TABLE sync_states;
+ALTER TABLE sync_states_old RENAME TO sync_states;
+
+DROP INDEX statuses_account_id_idx;
+ALTER TABLE statuses DROP COLUMN account_id;
+
+DROP TABLE accounts;
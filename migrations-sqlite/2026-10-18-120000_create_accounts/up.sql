@@ -0,0 +1,44 @@
+CREATE TABLE accounts (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  source_id TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  CONSTRAINT accounts_name_uniq UNIQUE (name),
+  CONSTRAINT accounts_source_source_id_uniq UNIQUE (source, source_id)
+);
+
+-- SQLite can’t drop a column that’s part of a foreign key, so this one is a
+-- plain integer to keep the migration reversible.
+ALTER TABLE statuses ADD COLUMN account_id INTEGER;
+CREATE INDEX statuses_account_id_idx ON statuses (account_id);
+
+-- SQLite can’t change constraints in place, so the table is rebuilt.
+CREATE TABLE sync_states_new (
+  id INTEGER PRIMARY KEY,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  kind TEXT NOT NULL,
+  synced_to TEXT,
+  cursor TEXT,
+  pending_to TEXT,
+  done BOOLEAN NOT NULL DEFAULT 0,
+  last_run_at TEXT,
+  last_success_at TEXT,
+  last_error TEXT,
+  fetched_count INTEGER NOT NULL DEFAULT 0,
+  inserted_count INTEGER NOT NULL DEFAULT 0,
+  account_id INTEGER REFERENCES accounts(id),
+  CONSTRAINT sync_states_account_kind_uniq UNIQUE (account_id, kind)
+);
+
+INSERT INTO sync_states_new (
+  id, source, kind, synced_to, cursor, pending_to, done, last_run_at,
+  last_success_at, last_error, fetched_count, inserted_count
+)
+SELECT
+  id, source, kind, synced_to, cursor, pending_to, done, last_run_at,
+  last_success_at, last_error, fetched_count, inserted_count
+FROM sync_states;
+
+DROP TABLE sync_states;
+ALTER TABLE sync_states_new RENAME TO sync_states;
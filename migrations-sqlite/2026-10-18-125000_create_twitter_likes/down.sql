@@ -0,0 +1 @@
+DROP TABLE twitter_likes;
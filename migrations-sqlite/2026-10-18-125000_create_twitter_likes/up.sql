@@ -0,0 +1,19 @@
+CREATE TABLE twitter_likes (
+  id INTEGER PRIMARY KEY,
+  account_id INTEGER REFERENCES accounts(id),
+  status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  liked_at TEXT NOT NULL,
+  listed_at TEXT,
+  unliked_at TEXT,
+  CONSTRAINT twitter_likes_account_status_uniq UNIQUE (account_id, status_id)
+);
+
+CREATE INDEX twitter_likes_status_id_idx ON twitter_likes (status_id);
+
+-- Likes were only recorded on statuses, with the account they were fetched
+-- for if any. Those without one wait for `omelette-migrate-db
+-- --claim-rest-for`. The marks on statuses stay, as whether any account likes
+-- the tweet.
+INSERT INTO twitter_likes (account_id, status_id, liked_at)
+SELECT account_id, id, coalesce(marked_at, fetched_at) FROM statuses
+WHERE source = 'twitter' AND is_marked;
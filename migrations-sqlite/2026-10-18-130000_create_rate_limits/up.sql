@@ -0,0 +1,10 @@
+CREATE TABLE rate_limits (
+  id INTEGER PRIMARY KEY,
+  scope TEXT NOT NULL,
+  endpoint TEXT NOT NULL,
+  quota INTEGER NOT NULL,
+  remaining INTEGER NOT NULL,
+  reset_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  CONSTRAINT rate_limits_scope_endpoint_uniq UNIQUE (scope, endpoint)
+);
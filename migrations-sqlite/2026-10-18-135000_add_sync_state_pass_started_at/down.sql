@@ -0,0 +1 @@
+ALTER TABLE sync_states DROP COLUMN pass_started_at;
@@ -0,0 +1,8 @@
+ALTER TABLE twitter_users ADD COLUMN blocked_at TEXT;
+
+UPDATE twitter_users SET blocked_at = (
+  SELECT min(blocked_at) FROM twitter_blocks
+  WHERE user_id = twitter_users.id AND unblocked_at IS NULL
+);
+
+DROP TABLE twitter_blocks;
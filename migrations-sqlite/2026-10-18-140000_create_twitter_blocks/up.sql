@@ -0,0 +1,19 @@
+CREATE TABLE twitter_blocks (
+  id INTEGER PRIMARY KEY,
+  account_id INTEGER REFERENCES accounts(id),
+  user_id INTEGER NOT NULL REFERENCES twitter_users(id),
+  blocked_at TEXT NOT NULL,
+  listed_at TEXT,
+  unblocked_at TEXT,
+  CONSTRAINT twitter_blocks_account_user_uniq UNIQUE (account_id, user_id)
+);
+
+CREATE INDEX twitter_blocks_user_id_idx ON twitter_blocks (user_id);
+
+-- Blocks were recorded on users, without saying whose they were. They’re
+-- kept unassigned until `omelette-migrate-db --claim-rest-for` gives them to
+-- an account.
+INSERT INTO twitter_blocks (user_id, blocked_at)
+SELECT id, blocked_at FROM twitter_users WHERE blocked_at IS NOT NULL;
+
+ALTER TABLE twitter_users DROP COLUMN blocked_at;
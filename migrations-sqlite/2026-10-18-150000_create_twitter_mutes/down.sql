@@ -0,0 +1,8 @@
+ALTER TABLE twitter_users ADD COLUMN muted_at TEXT;
+
+UPDATE twitter_users SET muted_at = (
+  SELECT min(muted_at) FROM twitter_mutes
+  WHERE user_id = twitter_users.id AND unmuted_at IS NULL
+);
+
+DROP TABLE twitter_mutes;
@@ -0,0 +1,19 @@
+CREATE TABLE twitter_mutes (
+  id INTEGER PRIMARY KEY,
+  account_id INTEGER REFERENCES accounts(id),
+  user_id INTEGER NOT NULL REFERENCES twitter_users(id),
+  muted_at TEXT NOT NULL,
+  listed_at TEXT,
+  unmuted_at TEXT,
+  CONSTRAINT twitter_mutes_account_user_uniq UNIQUE (account_id, user_id)
+);
+
+CREATE INDEX twitter_mutes_user_id_idx ON twitter_mutes (user_id);
+
+-- Mutes were recorded on users, without saying whose they were. They’re
+-- kept unassigned until `omelette-migrate-db --claim-rest-for` gives them to
+-- an account.
+INSERT INTO twitter_mutes (user_id, muted_at)
+SELECT id, muted_at FROM twitter_users WHERE muted_at IS NOT NULL;
+
+ALTER TABLE twitter_users DROP COLUMN muted_at;
@@ -0,0 +1,18 @@
+CREATE TABLE graph_snapshots (
+  id INTEGER PRIMARY KEY,
+  account_id INTEGER NOT NULL REFERENCES accounts(id),
+  kind TEXT NOT NULL CHECK (kind IN ('followers', 'following')),
+  taken_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  complete BOOLEAN NOT NULL DEFAULT 0,
+  count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX graph_snapshots_account_kind_idx ON graph_snapshots (account_id, kind, taken_at);
+
+CREATE TABLE graph_members (
+  snapshot_id INTEGER NOT NULL REFERENCES graph_snapshots(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES twitter_users(id),
+  PRIMARY KEY (snapshot_id, user_id)
+);
+
+CREATE INDEX graph_members_user_id_idx ON graph_members (user_id);
@@ -0,0 +1,2 @@
+DROP INDEX statuses_author_id_idx;
+UPDATE statuses SET author_id = NULL;
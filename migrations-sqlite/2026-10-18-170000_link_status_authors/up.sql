@@ -0,0 +1,23 @@
+-- Authors were only recorded as `"Name" <@handle> (id)` in source_author. Store
+-- those not yet known as slim users, to be filled in by omelette-twitter-hydrate,
+-- then link each tweet to its author.
+--
+-- SQLite can’t add a foreign key to an existing column, and rebuilding the
+-- table would trip the foreign keys from entities and deletions, so author_id
+-- stays a plain integer here.
+INSERT OR IGNORE INTO twitter_users (source_id, screen_name, name)
+SELECT DISTINCT author, '~slim~', ''
+FROM (
+  SELECT rtrim(substr(source_author, instr(source_author, '> (') + 3), ')') AS author
+  FROM statuses
+  WHERE source = 'twitter' AND source_author LIKE '%> (%)'
+)
+WHERE author <> '' AND author NOT GLOB '*[^0-9]*';
+
+UPDATE statuses SET author_id = (
+  SELECT id FROM twitter_users
+  WHERE twitter_users.source_id = rtrim(substr(statuses.source_author, instr(statuses.source_author, '> (') + 3), ')')
+)
+WHERE source = 'twitter' AND author_id IS NULL AND source_author LIKE '%> (%)';
+
+CREATE INDEX statuses_author_id_idx ON statuses (author_id);
@@ -0,0 +1,5 @@
+ALTER TABLE entities DROP COLUMN duration_ms;
+ALTER TABLE entities DROP COLUMN height;
+ALTER TABLE entities DROP COLUMN width;
+ALTER TABLE entities DROP COLUMN byte_size;
+ALTER TABLE entities DROP COLUMN mime_type;
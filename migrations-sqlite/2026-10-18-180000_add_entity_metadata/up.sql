@@ -0,0 +1,5 @@
+ALTER TABLE entities ADD COLUMN mime_type TEXT;
+ALTER TABLE entities ADD COLUMN byte_size INTEGER;
+ALTER TABLE entities ADD COLUMN width INTEGER;
+ALTER TABLE entities ADD COLUMN height INTEGER;
+ALTER TABLE entities ADD COLUMN duration_ms INTEGER;
@@ -0,0 +1,4 @@
+ALTER TABLE entities DROP COLUMN lost_at;
+ALTER TABLE entities DROP COLUMN retry_at;
+ALTER TABLE entities DROP COLUMN download_error;
+ALTER TABLE entities DROP COLUMN download_failures;
@@ -0,0 +1,4 @@
+ALTER TABLE entities ADD COLUMN download_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE entities ADD COLUMN download_error TEXT;
+ALTER TABLE entities ADD COLUMN retry_at TEXT;
+ALTER TABLE entities ADD COLUMN lost_at TEXT;
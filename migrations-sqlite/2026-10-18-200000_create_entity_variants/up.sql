@@ -0,0 +1,14 @@
+CREATE TABLE entity_variants (
+  id INTEGER PRIMARY KEY,
+  entity_id INTEGER NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+  kind TEXT NOT NULL CHECK (kind IN ('video', 'poster')),
+  content_type TEXT,
+  bitrate INTEGER,
+  url TEXT NOT NULL,
+  blob_hash TEXT,
+  download_failures INTEGER NOT NULL DEFAULT 0,
+  download_error TEXT,
+  retry_at TEXT,
+  lost_at TEXT,
+  UNIQUE (entity_id, url)
+);
@@ -0,0 +1,36 @@
+CREATE TABLE hashtags (
+  id INTEGER PRIMARY KEY,
+  status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  text TEXT NOT NULL,
+  start_index INTEGER NOT NULL,
+  end_index INTEGER NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX hashtags_text_idx ON hashtags (text COLLATE NOCASE);
+
+CREATE TABLE mentions (
+  id INTEGER PRIMARY KEY,
+  status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES twitter_users(id),
+  screen_name TEXT NOT NULL,
+  start_index INTEGER NOT NULL,
+  end_index INTEGER NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX mentions_user_id_idx ON mentions (user_id);
+
+CREATE TABLE urls (
+  id INTEGER PRIMARY KEY,
+  status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  url TEXT NOT NULL,
+  expanded_url TEXT,
+  display_url TEXT NOT NULL,
+  domain TEXT,
+  start_index INTEGER NOT NULL,
+  end_index INTEGER NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX urls_domain_idx ON urls (domain);
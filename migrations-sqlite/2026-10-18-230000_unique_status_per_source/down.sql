@@ -0,0 +1,63 @@
+-- See up.sql for how the table is rebuilt.
+PRAGMA defer_foreign_keys = ON;
+
+CREATE TEMP TABLE statuses_copy AS SELECT * FROM statuses;
+CREATE TEMP TABLE twitter_likes_copy AS SELECT * FROM twitter_likes;
+CREATE TEMP TABLE hashtags_copy AS SELECT * FROM hashtags;
+CREATE TEMP TABLE mentions_copy AS SELECT * FROM mentions;
+CREATE TEMP TABLE urls_copy AS SELECT * FROM urls;
+
+DROP TABLE statuses;
+
+CREATE TABLE statuses (
+  id INTEGER PRIMARY KEY,
+  text TEXT NOT NULL,
+  author_id INTEGER,
+  geolocation_lat REAL,
+  geolocation_lon REAL,
+  posted_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  fetched_via TEXT CHECK (fetched_via IN ('twitter archive')),
+  deleted_at TEXT,
+  is_repost BOOLEAN NOT NULL DEFAULT 0,
+  reposted_at TEXT,
+  is_marked BOOLEAN NOT NULL DEFAULT 0,
+  marked_at TEXT,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  source_id TEXT NOT NULL,
+  source_author TEXT NOT NULL,
+  source_app TEXT NOT NULL,
+  in_reply_to_status TEXT,
+  in_reply_to_user TEXT,
+  quoting_status TEXT,
+  public BOOLEAN NOT NULL DEFAULT 0,
+  account_id INTEGER,
+  CONSTRAINT statuses_source_id_uniq UNIQUE (source_id)
+);
+
+INSERT INTO statuses (
+  id, text, author_id, geolocation_lat, geolocation_lon, posted_at,
+  fetched_at, fetched_via, deleted_at, is_repost, reposted_at, is_marked,
+  marked_at, source, source_id, source_author, source_app, in_reply_to_status,
+  in_reply_to_user, quoting_status, public, account_id
+)
+SELECT
+  id, text, author_id, geolocation_lat, geolocation_lon, posted_at,
+  fetched_at, fetched_via, deleted_at, is_repost, reposted_at, is_marked,
+  marked_at, source, source_id, source_author, source_app, in_reply_to_status,
+  in_reply_to_user, quoting_status, public, account_id
+FROM statuses_copy;
+
+INSERT INTO twitter_likes SELECT * FROM twitter_likes_copy;
+INSERT INTO hashtags SELECT * FROM hashtags_copy;
+INSERT INTO mentions SELECT * FROM mentions_copy;
+INSERT INTO urls SELECT * FROM urls_copy;
+
+CREATE INDEX statuses_account_id_idx ON statuses (account_id);
+CREATE INDEX statuses_author_id_idx ON statuses (author_id);
+
+DROP TABLE statuses_copy;
+DROP TABLE twitter_likes_copy;
+DROP TABLE hashtags_copy;
+DROP TABLE mentions_copy;
+DROP TABLE urls_copy;
@@ -0,0 +1,49 @@
+-- See up.sql for how the table is rebuilt.
+PRAGMA defer_foreign_keys = ON;
+
+CREATE TEMP TABLE entities_copy AS SELECT * FROM entities;
+CREATE TEMP TABLE entity_variants_copy AS SELECT * FROM entity_variants;
+
+DROP TABLE entities;
+
+CREATE TABLE entities (
+  id INTEGER PRIMARY KEY,
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  status_id INTEGER NOT NULL REFERENCES statuses(id),
+  ordering INTEGER,
+  media_type TEXT NOT NULL CHECK (media_type IN ('photo', 'video', 'gif')),
+  source_id TEXT NOT NULL,
+  source_url TEXT NOT NULL,
+  original_status_source_id TEXT,
+  original_status_source_url TEXT,
+  blob_hash TEXT,
+  mime_type TEXT,
+  byte_size INTEGER,
+  width INTEGER,
+  height INTEGER,
+  duration_ms INTEGER,
+  download_failures INTEGER NOT NULL DEFAULT 0,
+  download_error TEXT,
+  retry_at TEXT,
+  lost_at TEXT,
+  alt_text TEXT,
+  CONSTRAINT entities_source_id_uniq UNIQUE (source_id)
+);
+
+INSERT INTO entities (
+  id, fetched_at, status_id, ordering, media_type, source_id, source_url,
+  original_status_source_id, original_status_source_url, blob_hash, mime_type,
+  byte_size, width, height, duration_ms, download_failures, download_error,
+  retry_at, lost_at, alt_text
+)
+SELECT
+  id, fetched_at, status_id, ordering, media_type, source_id, source_url,
+  original_status_source_id, original_status_source_url, blob_hash, mime_type,
+  byte_size, width, height, duration_ms, download_failures, download_error,
+  retry_at, lost_at, alt_text
+FROM entities_copy;
+
+INSERT INTO entity_variants SELECT * FROM entity_variants_copy;
+
+DROP TABLE entities_copy;
+DROP TABLE entity_variants_copy;
@@ -0,0 +1,46 @@
+-- Media IDs are only unique within their source too, so entities say which
+-- one they’re from, as their status does.
+--
+-- SQLite can’t change constraints in place, so the table is rebuilt, as for
+-- statuses. Dropping it cascades to variants, so those are put back too.
+PRAGMA defer_foreign_keys = ON;
+
+CREATE TEMP TABLE entities_copy AS SELECT * FROM entities;
+CREATE TEMP TABLE entity_variants_copy AS SELECT * FROM entity_variants;
+
+DROP TABLE entities;
+
+CREATE TABLE entities (
+  id INTEGER PRIMARY KEY,
+  fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  status_id INTEGER NOT NULL REFERENCES statuses(id),
+  ordering INTEGER,
+  media_type TEXT NOT NULL CHECK (media_type IN ('photo', 'video', 'gif')),
+  source_id TEXT NOT NULL,
+  source_url TEXT NOT NULL,
+  original_status_source_id TEXT,
+  original_status_source_url TEXT,
+  blob_hash TEXT,
+  mime_type TEXT,
+  byte_size INTEGER,
+  width INTEGER,
+  height INTEGER,
+  duration_ms INTEGER,
+  download_failures INTEGER NOT NULL DEFAULT 0,
+  download_error TEXT,
+  retry_at TEXT,
+  lost_at TEXT,
+  alt_text TEXT,
+  source TEXT NOT NULL CHECK (source IN ('twitter', 'mastodon')),
+  CONSTRAINT entities_source_source_id_uniq UNIQUE (source, source_id)
+);
+
+-- Columns are in the same order, with the source last.
+INSERT INTO entities
+SELECT entities_copy.*, (SELECT source FROM statuses WHERE id = entities_copy.status_id)
+FROM entities_copy;
+
+INSERT INTO entity_variants SELECT * FROM entity_variants_copy;
+
+DROP TABLE entities_copy;
+DROP TABLE entity_variants_copy;
@@ -0,0 +1,29 @@
+CREATE TABLE sync_states (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  source source_t NOT NULL,
+  kind text NOT NULL,
+  synced_to text,
+  cursor text,
+  pending_to text,
+  done boolean DEFAULT FALSE NOT NULL,
+  last_run_at timestamp with time zone,
+  last_success_at timestamp with time zone,
+  last_error text,
+  fetched_count int NOT NULL DEFAULT 0,
+  inserted_count int NOT NULL DEFAULT 0
+);
+
+ALTER TABLE sync_states ADD CONSTRAINT sync_states_source_kind_uniq UNIQUE (source, kind);
+
+COMMENT ON COLUMN sync_states.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN sync_states.source IS 'Which service this sync state is for';
+COMMENT ON COLUMN sync_states.kind IS 'Which sync pass this is for, e.g. forward or backfill';
+COMMENT ON COLUMN sync_states.synced_to IS 'Opaque source ID up to which the pass has fully synced';
+COMMENT ON COLUMN sync_states.cursor IS 'Opaque source cursor from which an interrupted pass resumes';
+COMMENT ON COLUMN sync_states.pending_to IS 'Opaque source ID the pass in progress will have synced to once complete';
+COMMENT ON COLUMN sync_states.done IS 'Whether the pass has reached the end and has nothing more to do';
+COMMENT ON COLUMN sync_states.last_run_at IS 'When the pass last started';
+COMMENT ON COLUMN sync_states.last_success_at IS 'When the pass last completed';
+COMMENT ON COLUMN sync_states.last_error IS 'Error which interrupted the last run, if any';
+COMMENT ON COLUMN sync_states.fetched_count IS 'Items fetched during the last run';
+COMMENT ON COLUMN sync_states.inserted_count IS 'New items stored during the last run';
@@ -0,0 +1,2 @@
+DROP INDEX statuses_text_search_idx;
+ALTER TABLE statuses DROP COLUMN text_search;
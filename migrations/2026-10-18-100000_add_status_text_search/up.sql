@@ -0,0 +1,8 @@
+-- The 'simple' configuration doesn't stem or drop stop words, which works for
+-- statuses written in any language, at the cost of exact-word matching.
+ALTER TABLE statuses ADD COLUMN text_search tsvector
+  GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED;
+
+CREATE INDEX statuses_text_search_idx ON statuses USING GIN (text_search);
+
+COMMENT ON COLUMN statuses.text_search IS 'Full-text search vector of the text, maintained by Postgres';
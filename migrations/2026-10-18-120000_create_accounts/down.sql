@@ -0,0 +1,16 @@
+-- Keep the oldest state of each pass, as there can only be one per source again.
+DELETE FROM sync_states WHERE EXISTS (
+  SELECT 1 FROM sync_states older
+  WHERE older.source = sync_states.source
+    AND older.kind = sync_states.kind
+    AND older.id < sync_states.id
+);
+
+ALTER TABLE sync_states DROP CONSTRAINT sync_states_account_kind_uniq;
+ALTER TABLE sync_states ADD CONSTRAINT sync_states_source_kind_uniq UNIQUE (source, kind);
+ALTER TABLE sync_states DROP COLUMN account_id;
+
+DROP INDEX statuses_account_id_idx;
+ALTER TABLE statuses DROP COLUMN account_id;
+
+DROP TABLE accounts;
@@ -0,0 +1,25 @@
+CREATE TABLE accounts (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  name text NOT NULL,
+  source source_t NOT NULL,
+  source_id text NOT NULL,
+  created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE accounts ADD CONSTRAINT accounts_name_uniq UNIQUE (name);
+ALTER TABLE accounts ADD CONSTRAINT accounts_source_source_id_uniq UNIQUE (source, source_id);
+
+COMMENT ON COLUMN accounts.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN accounts.name IS 'Name given to the account in the config, used to pick it with --account';
+COMMENT ON COLUMN accounts.source IS 'Which service this account is on';
+COMMENT ON COLUMN accounts.source_id IS 'The opaque ID of the account on its source';
+COMMENT ON COLUMN accounts.created_at IS 'When Omelette first saw this account';
+
+ALTER TABLE statuses ADD COLUMN account_id int REFERENCES accounts(id);
+CREATE INDEX statuses_account_id_idx ON statuses (account_id);
+COMMENT ON COLUMN statuses.account_id IS 'Omelette reference to the account this status was fetched for (posted, reposted, or marked by it)';
+
+ALTER TABLE sync_states ADD COLUMN account_id int REFERENCES accounts(id);
+ALTER TABLE sync_states DROP CONSTRAINT sync_states_source_kind_uniq;
+ALTER TABLE sync_states ADD CONSTRAINT sync_states_account_kind_uniq UNIQUE (account_id, kind);
+COMMENT ON COLUMN sync_states.account_id IS 'Omelette reference to the account this sync state is for';
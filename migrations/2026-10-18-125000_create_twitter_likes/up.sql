@@ -0,0 +1,26 @@
+CREATE TABLE twitter_likes (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  account_id int REFERENCES accounts(id),
+  status_id int NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  liked_at timestamp with time zone NOT NULL,
+  listed_at timestamp with time zone,
+  unliked_at timestamp with time zone
+);
+
+ALTER TABLE twitter_likes ADD CONSTRAINT twitter_likes_account_status_uniq UNIQUE (account_id, status_id);
+CREATE INDEX twitter_likes_status_id_idx ON twitter_likes (status_id);
+
+COMMENT ON COLUMN twitter_likes.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN twitter_likes.account_id IS 'Omelette reference to the account that liked the tweet, null for likes from before accounts until claimed';
+COMMENT ON COLUMN twitter_likes.status_id IS 'Omelette reference to the liked tweet';
+COMMENT ON COLUMN twitter_likes.liked_at IS 'When the like was first seen';
+COMMENT ON COLUMN twitter_likes.listed_at IS 'When the tweet was last seen in the account’s likes list, to notice un-likes';
+COMMENT ON COLUMN twitter_likes.unliked_at IS 'When the tweet was noticed un-liked, null while the like stands';
+
+-- Likes were only recorded on statuses, with the account they were fetched
+-- for if any. Those without one wait for `omelette-migrate-db
+-- --claim-rest-for`. The marks on statuses stay, as whether any account likes
+-- the tweet.
+INSERT INTO twitter_likes (account_id, status_id, liked_at)
+SELECT account_id, id, coalesce(marked_at, fetched_at) FROM statuses
+WHERE source = 'twitter' AND is_marked;
@@ -0,0 +1,19 @@
+CREATE TABLE rate_limits (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  scope text NOT NULL,
+  endpoint text NOT NULL,
+  quota int NOT NULL,
+  remaining int NOT NULL,
+  reset_at timestamp with time zone NOT NULL,
+  updated_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE rate_limits ADD CONSTRAINT rate_limits_scope_endpoint_uniq UNIQUE (scope, endpoint);
+
+COMMENT ON COLUMN rate_limits.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN rate_limits.scope IS 'Whose budget this is, e.g. the Twitter user ID of the token';
+COMMENT ON COLUMN rate_limits.endpoint IS 'The API endpoint, e.g. statuses/lookup';
+COMMENT ON COLUMN rate_limits.quota IS 'How many calls the window allows';
+COMMENT ON COLUMN rate_limits.remaining IS 'How many calls are left in the window, decremented by omelette before each call';
+COMMENT ON COLUMN rate_limits.reset_at IS 'When the window ends and the budget is replenished';
+COMMENT ON COLUMN rate_limits.updated_at IS 'When the API last told us about this budget';
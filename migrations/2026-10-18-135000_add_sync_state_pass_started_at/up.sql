@@ -0,0 +1,2 @@
+ALTER TABLE sync_states ADD COLUMN pass_started_at timestamp with time zone;
+COMMENT ON COLUMN sync_states.pass_started_at IS 'When the pass in progress first started, kept when it is resumed';
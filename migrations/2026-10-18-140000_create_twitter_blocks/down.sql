@@ -0,0 +1,9 @@
+ALTER TABLE twitter_users ADD COLUMN blocked_at timestamp with time zone;
+COMMENT ON COLUMN twitter_users.blocked_at IS 'If pre-existing block, same as fetched_at, otherwise, when the user was blocked';
+
+UPDATE twitter_users SET blocked_at = (
+  SELECT min(blocked_at) FROM twitter_blocks
+  WHERE user_id = twitter_users.id AND unblocked_at IS NULL
+);
+
+DROP TABLE twitter_blocks;
@@ -0,0 +1,26 @@
+CREATE TABLE twitter_blocks (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  account_id int REFERENCES accounts(id),
+  user_id int NOT NULL REFERENCES twitter_users(id),
+  blocked_at timestamp with time zone NOT NULL,
+  listed_at timestamp with time zone,
+  unblocked_at timestamp with time zone
+);
+
+ALTER TABLE twitter_blocks ADD CONSTRAINT twitter_blocks_account_user_uniq UNIQUE (account_id, user_id);
+CREATE INDEX twitter_blocks_user_id_idx ON twitter_blocks (user_id);
+
+COMMENT ON COLUMN twitter_blocks.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN twitter_blocks.account_id IS 'Omelette reference to the account doing the blocking, null for blocks from before accounts until claimed';
+COMMENT ON COLUMN twitter_blocks.user_id IS 'Omelette reference to the blocked user';
+COMMENT ON COLUMN twitter_blocks.blocked_at IS 'If pre-existing block, when it was first fetched, otherwise, when the user was blocked';
+COMMENT ON COLUMN twitter_blocks.listed_at IS 'When the user was last seen in the account’s block list, to notice unblocks once the whole list has been fetched';
+COMMENT ON COLUMN twitter_blocks.unblocked_at IS 'When the user was noticed unblocked, null while the block stands';
+
+-- Blocks were recorded on users, without saying whose they were. They’re
+-- kept unassigned until `omelette-migrate-db --claim-rest-for` gives them to
+-- an account.
+INSERT INTO twitter_blocks (user_id, blocked_at)
+SELECT id, blocked_at FROM twitter_users WHERE blocked_at IS NOT NULL;
+
+ALTER TABLE twitter_users DROP COLUMN blocked_at;
@@ -0,0 +1,9 @@
+ALTER TABLE twitter_users ADD COLUMN muted_at timestamp with time zone;
+COMMENT ON COLUMN twitter_users.muted_at IS 'If pre-existing mute, same as fetched_at, otherwise, when the user was muted';
+
+UPDATE twitter_users SET muted_at = (
+  SELECT min(muted_at) FROM twitter_mutes
+  WHERE user_id = twitter_users.id AND unmuted_at IS NULL
+);
+
+DROP TABLE twitter_mutes;
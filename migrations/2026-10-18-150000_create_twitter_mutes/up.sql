@@ -0,0 +1,26 @@
+CREATE TABLE twitter_mutes (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  account_id int REFERENCES accounts(id),
+  user_id int NOT NULL REFERENCES twitter_users(id),
+  muted_at timestamp with time zone NOT NULL,
+  listed_at timestamp with time zone,
+  unmuted_at timestamp with time zone
+);
+
+ALTER TABLE twitter_mutes ADD CONSTRAINT twitter_mutes_account_user_uniq UNIQUE (account_id, user_id);
+CREATE INDEX twitter_mutes_user_id_idx ON twitter_mutes (user_id);
+
+COMMENT ON COLUMN twitter_mutes.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN twitter_mutes.account_id IS 'Omelette reference to the account doing the muting, null for mutes from before accounts until claimed';
+COMMENT ON COLUMN twitter_mutes.user_id IS 'Omelette reference to the muted user';
+COMMENT ON COLUMN twitter_mutes.muted_at IS 'If pre-existing mute, when it was first fetched, otherwise, when the user was muted';
+COMMENT ON COLUMN twitter_mutes.listed_at IS 'When the user was last seen in the account’s mute list, to notice unmutes once the whole list has been fetched';
+COMMENT ON COLUMN twitter_mutes.unmuted_at IS 'When the user was noticed unmuted, null while the mute stands';
+
+-- Mutes were recorded on users, without saying whose they were. They’re
+-- kept unassigned until `omelette-migrate-db --claim-rest-for` gives them to
+-- an account.
+INSERT INTO twitter_mutes (user_id, muted_at)
+SELECT id, muted_at FROM twitter_users WHERE muted_at IS NOT NULL;
+
+ALTER TABLE twitter_users DROP COLUMN muted_at;
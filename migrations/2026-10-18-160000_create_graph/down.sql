@@ -0,0 +1,2 @@
+DROP TABLE graph_members;
+DROP TABLE graph_snapshots;
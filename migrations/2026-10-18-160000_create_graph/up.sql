@@ -0,0 +1,28 @@
+CREATE TABLE graph_snapshots (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  account_id int NOT NULL REFERENCES accounts(id),
+  kind text NOT NULL CHECK (kind IN ('followers', 'following')),
+  taken_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  complete boolean DEFAULT false NOT NULL,
+  count int DEFAULT 0 NOT NULL
+);
+
+CREATE INDEX graph_snapshots_account_kind_idx ON graph_snapshots (account_id, kind, taken_at);
+
+COMMENT ON COLUMN graph_snapshots.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN graph_snapshots.account_id IS 'Omelette reference to the account whose graph this is';
+COMMENT ON COLUMN graph_snapshots.kind IS 'Either the users following the account (followers), or those it follows (following)';
+COMMENT ON COLUMN graph_snapshots.taken_at IS 'When the snapshot was started';
+COMMENT ON COLUMN graph_snapshots.complete IS 'Whether the whole list was fetched: incomplete snapshots are left out of diffs';
+COMMENT ON COLUMN graph_snapshots.count IS 'How many users are in the snapshot';
+
+CREATE TABLE graph_members (
+  snapshot_id int NOT NULL REFERENCES graph_snapshots(id) ON DELETE CASCADE,
+  user_id int NOT NULL REFERENCES twitter_users(id),
+  PRIMARY KEY (snapshot_id, user_id)
+);
+
+CREATE INDEX graph_members_user_id_idx ON graph_members (user_id);
+
+COMMENT ON COLUMN graph_members.snapshot_id IS 'Omelette reference to the snapshot';
+COMMENT ON COLUMN graph_members.user_id IS 'Omelette reference to a user in the snapshot';
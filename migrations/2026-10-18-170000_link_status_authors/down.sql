@@ -0,0 +1,5 @@
+COMMENT ON COLUMN statuses.author_id IS 'Omelette reference to this status’s author';
+
+DROP INDEX statuses_author_id_idx;
+ALTER TABLE statuses DROP CONSTRAINT statuses_author_id_fkey;
+UPDATE statuses SET author_id = NULL;
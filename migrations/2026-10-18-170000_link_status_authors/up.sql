@@ -0,0 +1,20 @@
+-- Authors were only recorded as `"Name" <@handle> (id)` in source_author. Store
+-- those not yet known as slim users, to be filled in by omelette-twitter-hydrate,
+-- then link each tweet to its author.
+INSERT INTO twitter_users (source_id, screen_name, name)
+SELECT DISTINCT substring(source_author from ' \((\d+)\)$'), '~slim~', ''
+FROM statuses
+WHERE source = 'twitter' AND source_author ~ ' \(\d+\)$'
+ON CONFLICT (source_id) DO NOTHING;
+
+UPDATE statuses SET author_id = twitter_users.id
+FROM twitter_users
+WHERE statuses.source = 'twitter'
+  AND statuses.author_id IS NULL
+  AND twitter_users.source_id = substring(statuses.source_author from ' \((\d+)\)$');
+
+ALTER TABLE statuses ADD CONSTRAINT statuses_author_id_fkey
+  FOREIGN KEY (author_id) REFERENCES twitter_users(id);
+CREATE INDEX statuses_author_id_idx ON statuses (author_id);
+
+COMMENT ON COLUMN statuses.author_id IS 'Omelette reference to this status’s author, for Twitter statuses';
@@ -0,0 +1,11 @@
+ALTER TABLE entities ADD COLUMN mime_type text;
+ALTER TABLE entities ADD COLUMN byte_size bigint;
+ALTER TABLE entities ADD COLUMN width int;
+ALTER TABLE entities ADD COLUMN height int;
+ALTER TABLE entities ADD COLUMN duration_ms int;
+
+COMMENT ON COLUMN entities.mime_type IS 'MIME type of the stored blob, as detected from its content';
+COMMENT ON COLUMN entities.byte_size IS 'Size of the stored blob in bytes';
+COMMENT ON COLUMN entities.width IS 'Width of the image or video in pixels, if it could be read';
+COMMENT ON COLUMN entities.height IS 'Height of the image or video in pixels, if it could be read';
+COMMENT ON COLUMN entities.duration_ms IS 'Duration of the video or gif in milliseconds, if it could be read';
@@ -0,0 +1,9 @@
+ALTER TABLE entities ADD COLUMN download_failures int DEFAULT 0 NOT NULL;
+ALTER TABLE entities ADD COLUMN download_error text;
+ALTER TABLE entities ADD COLUMN retry_at timestamp with time zone;
+ALTER TABLE entities ADD COLUMN lost_at timestamp with time zone;
+
+COMMENT ON COLUMN entities.download_failures IS 'How many times in a row downloading the media failed';
+COMMENT ON COLUMN entities.download_error IS 'Why the last download failed';
+COMMENT ON COLUMN entities.retry_at IS 'When to try downloading again, backing off after each failure';
+COMMENT ON COLUMN entities.lost_at IS 'When omelette gave up on downloading the media';
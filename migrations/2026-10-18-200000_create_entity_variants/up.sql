@@ -0,0 +1,26 @@
+CREATE TABLE entity_variants (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  entity_id int NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+  kind text NOT NULL CHECK (kind IN ('video', 'poster')),
+  content_type text,
+  bitrate int,
+  url text NOT NULL,
+  blob_hash text,
+  download_failures int DEFAULT 0 NOT NULL,
+  download_error text,
+  retry_at timestamp with time zone,
+  lost_at timestamp with time zone,
+  UNIQUE (entity_id, url)
+);
+
+COMMENT ON COLUMN entity_variants.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN entity_variants.entity_id IS 'Omelette reference to the entity this is a variant of';
+COMMENT ON COLUMN entity_variants.kind IS 'Either an encoding of the video (video), or the still image shown before it plays (poster)';
+COMMENT ON COLUMN entity_variants.content_type IS 'Content type as given by the source, if it gives one';
+COMMENT ON COLUMN entity_variants.bitrate IS 'Bitrate in bits per second as given by the source, for video variants';
+COMMENT ON COLUMN entity_variants.url IS 'URL to the variant on the source';
+COMMENT ON COLUMN entity_variants.blob_hash IS 'Hash of the downloaded variant in the blob store';
+COMMENT ON COLUMN entity_variants.download_failures IS 'How many times in a row downloading the variant failed';
+COMMENT ON COLUMN entity_variants.download_error IS 'Why the last download failed';
+COMMENT ON COLUMN entity_variants.retry_at IS 'When to try downloading again, backing off after each failure';
+COMMENT ON COLUMN entity_variants.lost_at IS 'When omelette gave up on downloading the variant';
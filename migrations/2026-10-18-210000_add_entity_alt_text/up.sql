@@ -0,0 +1,3 @@
+ALTER TABLE entities ADD COLUMN alt_text text;
+
+COMMENT ON COLUMN entities.alt_text IS 'Description of the media written by its author, for screen readers';
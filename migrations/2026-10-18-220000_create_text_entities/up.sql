@@ -0,0 +1,58 @@
+CREATE TABLE hashtags (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  status_id int NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  text text NOT NULL,
+  start_index int NOT NULL,
+  end_index int NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX hashtags_text_idx ON hashtags (lower(text));
+
+COMMENT ON COLUMN hashtags.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN hashtags.status_id IS 'Omelette reference to the status the hashtag is in';
+COMMENT ON COLUMN hashtags.text IS 'Hashtag without the #, as written';
+COMMENT ON COLUMN hashtags.start_index IS 'Where the hashtag starts in the text, in characters, as given by the source';
+COMMENT ON COLUMN hashtags.end_index IS 'Where the hashtag ends in the text, in characters, as given by the source';
+
+CREATE TABLE mentions (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  status_id int NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  user_id int NOT NULL REFERENCES twitter_users(id),
+  screen_name text NOT NULL,
+  start_index int NOT NULL,
+  end_index int NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX mentions_user_id_idx ON mentions (user_id);
+
+COMMENT ON COLUMN mentions.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN mentions.status_id IS 'Omelette reference to the status the mention is in';
+COMMENT ON COLUMN mentions.user_id IS 'Omelette reference to the user mentioned';
+COMMENT ON COLUMN mentions.screen_name IS 'Screen name of the user mentioned, at the time';
+COMMENT ON COLUMN mentions.start_index IS 'Where the mention starts in the text, in characters, as given by the source';
+COMMENT ON COLUMN mentions.end_index IS 'Where the mention ends in the text, in characters, as given by the source';
+
+CREATE TABLE urls (
+  id int GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  status_id int NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  url text NOT NULL,
+  expanded_url text,
+  display_url text NOT NULL,
+  domain text,
+  start_index int NOT NULL,
+  end_index int NOT NULL,
+  UNIQUE (status_id, start_index)
+);
+
+CREATE INDEX urls_domain_idx ON urls (domain);
+
+COMMENT ON COLUMN urls.id IS 'Omelette-internal ID';
+COMMENT ON COLUMN urls.status_id IS 'Omelette reference to the status the link is in';
+COMMENT ON COLUMN urls.url IS 'Link as it is in the text, shortened by the source (t.co)';
+COMMENT ON COLUMN urls.expanded_url IS 'Link as it was written';
+COMMENT ON COLUMN urls.display_url IS 'Link as shown by the source, possibly cut short';
+COMMENT ON COLUMN urls.domain IS 'Host of the expanded link, lowercased and without www.';
+COMMENT ON COLUMN urls.start_index IS 'Where the link starts in the text, in characters, as given by the source';
+COMMENT ON COLUMN urls.end_index IS 'Where the link ends in the text, in characters, as given by the source';
@@ -0,0 +1,2 @@
+ALTER TABLE statuses DROP CONSTRAINT statuses_source_source_id_uniq;
+ALTER TABLE statuses ADD CONSTRAINT statuses_source_id_uniq UNIQUE (source_id);
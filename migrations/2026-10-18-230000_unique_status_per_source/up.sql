@@ -0,0 +1,4 @@
+-- Status IDs are only unique within their source: a Mastodon status and a tweet
+-- can have the same one.
+ALTER TABLE statuses DROP CONSTRAINT statuses_source_id_uniq;
+ALTER TABLE statuses ADD CONSTRAINT statuses_source_source_id_uniq UNIQUE (source, source_id);
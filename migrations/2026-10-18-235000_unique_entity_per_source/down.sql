@@ -0,0 +1,3 @@
+ALTER TABLE entities DROP CONSTRAINT entities_source_source_id_uniq;
+ALTER TABLE entities ADD CONSTRAINT entities_source_id_uniq UNIQUE (source_id);
+ALTER TABLE entities DROP COLUMN source;
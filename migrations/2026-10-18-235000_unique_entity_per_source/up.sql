@@ -0,0 +1,9 @@
+-- Media IDs are only unique within their source too, so entities say which
+-- one they’re from, as their status does.
+ALTER TABLE entities ADD COLUMN source source_t;
+UPDATE entities SET source = statuses.source FROM statuses WHERE statuses.id = entities.status_id;
+ALTER TABLE entities ALTER COLUMN source SET NOT NULL;
+COMMENT ON COLUMN entities.source IS 'Which service this entity is from';
+
+ALTER TABLE entities DROP CONSTRAINT entities_source_id_uniq;
+ALTER TABLE entities ADD CONSTRAINT entities_source_source_id_uniq UNIQUE (source, source_id);
@@ -0,0 +1,202 @@
+//! The accounts omelette works on, as configured, and their rows in the
+//! database, which statuses and sync states belong to.
+
+use crate::config::{AccountConfig, Config};
+use crate::db::DbConnection;
+use crate::error::OmeletteError;
+use crate::inserts::NewAccount;
+use crate::models::Account;
+use crate::types::Source;
+use diesel::prelude::*;
+
+/// Finds or creates the database row for a configured account.
+///
+/// Accounts are matched by name, or by source ID if they were renamed in the
+/// config. A name can’t be moved to another account, as everything synced for
+/// it would then be misattributed.
+pub fn register(conn: &DbConnection, config: &AccountConfig) -> Result<Account, OmeletteError> {
+    use crate::schema::accounts::dsl::*;
+
+    let sid = config
+        .id
+        .clone()
+        .ok_or_else(|| OmeletteError::Config(format!("account {}: id is missing", config.name)))?;
+
+    let by_name: Option<Account> = accounts
+        .filter(name.eq(&config.name))
+        .first(conn)
+        .optional()?;
+
+    let account = match by_name {
+        Some(account) => {
+            if account.source != config.source || account.source_id != sid {
+                return Err(OmeletteError::Config(format!(
+                    "account {} is registered as {:?} {}, not {:?} {}",
+                    account.name, account.source, account.source_id, config.source, sid
+                )));
+            }
+
+            account
+        }
+        None => match find(conn, &config.source, &sid)? {
+            Some(account) => {
+                println!("-> Renaming account {} to {}", account.name, config.name);
+                diesel::update(accounts.find(account.id))
+                    .set(name.eq(&config.name))
+                    .execute(conn)?;
+
+                Account {
+                    name: config.name.clone(),
+                    ..account
+                }
+            }
+            None => {
+                diesel::insert_into(accounts)
+                    .values(&NewAccount {
+                        name: config.name.clone(),
+                        source: config.source.clone(),
+                        source_id: sid.clone(),
+                    })
+                    .execute(conn)?;
+
+                println!("-> Registered {:?} account {} as {}", config.source, sid, config.name);
+                accounts.filter(name.eq(&config.name)).first(conn)?
+            }
+        },
+    };
+
+    Ok(account)
+}
+
+/// Links statuses from before accounts (or from an import) to the account
+/// that wrote them, returning how many. See `omelette-migrate-db --claim`.
+///
+/// Tweets are matched by their author in `twitter_users`, or by their
+/// `source_author` if they haven’t been linked to one yet. There’s no table of
+/// Mastodon users, so toots only go by `source_author`.
+pub fn claim(conn: &DbConnection, account: &Account) -> Result<usize, OmeletteError> {
+    use crate::schema::statuses::dsl::*;
+
+    let written = source_author.like(format!("% ({})", account.source_id));
+    let unlinked = statuses
+        .filter(account_id.is_null())
+        .filter(source.eq(&account.source));
+
+    let author: Option<i32> = if account.source == Source::Twitter {
+        use crate::schema::twitter_users::dsl as users;
+        users::twitter_users
+            .select(users::id)
+            .filter(users::source_id.eq(&account.source_id))
+            .first(conn)
+            .optional()?
+    } else {
+        None
+    };
+
+    Ok(match author {
+        Some(author) => diesel::update(unlinked.filter(author_id.eq(author).or(written)))
+            .set(account_id.eq(account.id))
+            .execute(conn)?,
+        None => diesel::update(unlinked.filter(written))
+            .set(account_id.eq(account.id))
+            .execute(conn)?,
+    })
+}
+
+/// Links whatever is still unlinked on the account’s source to it: reposts,
+/// likes, sync states, and blocks and mutes, which can’t be told apart by
+/// author. Returns how many statuses that was.
+///
+/// Only for when it’s known they’re all the account’s, so run `claim` for
+/// every account first.
+pub fn claim_rest(conn: &DbConnection, account: &Account) -> Result<usize, OmeletteError> {
+    use crate::schema::statuses::dsl::*;
+
+    let claimed = diesel::update(
+        statuses
+            .filter(account_id.is_null())
+            .filter(source.eq(&account.source)),
+    )
+    .set(account_id.eq(account.id))
+    .execute(conn)?;
+
+    use crate::schema::sync_states::dsl as states;
+    diesel::update(
+        states::sync_states
+            .filter(states::account_id.is_null())
+            .filter(states::source.eq(&account.source)),
+    )
+    .set(states::account_id.eq(account.id))
+    .execute(conn)?;
+
+    // What the account has blocked, muted, or liked since is already its own.
+    macro_rules! claim_listed {
+        ($table:ident, $key:ident) => {{
+            use crate::schema::$table::dsl::*;
+            let listed: Vec<i32> = $table
+                .filter(account_id.eq(account.id))
+                .select($key)
+                .load(conn)?;
+            diesel::update($table.filter(account_id.is_null()).filter($key.ne_all(listed)))
+                .set(account_id.eq(account.id))
+                .execute(conn)?;
+        }};
+    }
+
+    if account.source == Source::Twitter {
+        claim_listed!(twitter_likes, status_id);
+        claim_listed!(twitter_blocks, user_id);
+        claim_listed!(twitter_mutes, user_id);
+    }
+
+    Ok(claimed)
+}
+
+/// Registers the configured accounts, or only the one named, skipping those
+/// without an ID.
+pub fn load(
+    conn: &DbConnection,
+    config: &Config,
+    only: &Option<String>,
+) -> Result<Vec<(AccountConfig, Account)>, OmeletteError> {
+    let configs: Vec<AccountConfig> = config
+        .accounts()
+        .into_iter()
+        .filter(|account| only.as_ref().map_or(true, |n| &account.name == n))
+        .collect();
+
+    if let Some(n) = only {
+        if configs.is_empty() {
+            return Err(OmeletteError::Config(format!("no account named {}", n)));
+        }
+    }
+
+    let mut loaded = Vec::with_capacity(configs.len());
+    for account in configs {
+        if account.id.is_none() {
+            println!("-- No ID for account {}, skipping", account.name);
+            continue;
+        }
+
+        let row = register(conn, &account)?;
+        loaded.push((account, row));
+    }
+
+    Ok(loaded)
+}
+
+pub fn find(conn: &DbConnection, src: &Source, sid: &str) -> Result<Option<Account>, OmeletteError> {
+    use crate::schema::accounts::dsl::*;
+
+    Ok(accounts
+        .filter(source.eq(src))
+        .filter(source_id.eq(sid))
+        .first(conn)
+        .optional()?)
+}
+
+pub fn all(conn: &DbConnection) -> Result<Vec<Account>, OmeletteError> {
+    use crate::schema::accounts::dsl::*;
+
+    Ok(accounts.order_by((source, name)).load(conn)?)
+}
@@ -0,0 +1,224 @@
+use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+/// New-format (2019+) Twitter archives are a zip of JS files, each of which
+/// assigns a JSON array to a global, like `window.YTD.tweet.part0 = [ ... ]`.
+/// This strips the assignment and parses the JSON.
+pub fn parse_js<T: DeserializeOwned>(content: &str) -> serde_json::Result<T> {
+    let start = content.find(|c| c == '[' || c == '{').unwrap_or(0);
+    serde_json::from_str(&content[start..])
+}
+
+/// Whether an archive entry is one of the (possibly multi-part) tweets files.
+///
+/// Depending on the archive's vintage this is `tweet.js` at the root, or
+/// `data/tweet.js` or `data/tweets.js`, with `-partN` variants for large
+/// archives.
+pub fn is_tweets_file(name: &str) -> bool {
+    let re = Regex::new(r"^(data/)?tweets?(-part\d+)?\.js$").unwrap();
+    re.is_match(name)
+}
+
+/// If an archive entry is a media file, returns the tweet ID and media key.
+///
+/// Media files are in `tweet_media/` (or `data/tweet_media/`, or
+/// `data/tweets_media/`) and named `<tweet_id>-<media_key>.<ext>`, where the
+/// media key is the basename of the media’s URL.
+pub fn media_file(name: &str) -> Option<(String, String)> {
+    let re = Regex::new(r"^(?:data/)?tweets?_media/(\d+)-([^/.]+)\.\w+$").unwrap();
+    re.captures(name).map(|cap| (cap[1].to_string(), cap[2].to_string()))
+}
+
+/// Whether an archive entry is the account details file.
+pub fn is_account_file(name: &str) -> bool {
+    name == "account.js" || name == "data/account.js"
+}
+
+/// Turns the archive’s HTML source anchor into our `Name <URL>` format.
+pub fn parse_source_app(html: &str) -> String {
+    let re = Regex::new("^<a href=\"([^\"]+)\".*>(.+)</a>$").unwrap();
+    re.captures(html)
+        .map(|cap| format!("{} <{}>", &cap[2], &cap[1]))
+        .unwrap_or("".into())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Wrapped<T> {
+    Account { account: T },
+    Tweet { tweet: T },
+    Bare(T),
+}
+
+impl<T> Wrapped<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Wrapped::Account { account } => account,
+            Wrapped::Tweet { tweet } => tweet,
+            Wrapped::Bare(inner) => inner,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub account_id: String,
+    pub username: String,
+    #[serde(default)]
+    pub account_display_name: String,
+    /// Not in every archive. When it’s missing, tweets are taken as private.
+    #[serde(default)]
+    pub protected: Option<bool>,
+}
+
+impl Account {
+    /// Same format as `NewStatus::from(&Tweet)` uses for the author.
+    pub fn source_author(&self) -> String {
+        format!(
+            "\"{}\" <@{}> ({})",
+            self.account_display_name, self.username, self.account_id
+        )
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tweet {
+    pub id_str: String,
+    pub full_text: String,
+    #[serde(deserialize_with = "twitter_date")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub source: String,
+    pub in_reply_to_status_id_str: Option<String>,
+    pub in_reply_to_user_id_str: Option<String>,
+    pub in_reply_to_screen_name: Option<String>,
+    pub quoted_status_id_str: Option<String>,
+    #[serde(default)]
+    pub favorited: bool,
+    pub coordinates: Option<Coordinates>,
+    #[serde(default)]
+    pub entities: Entities,
+    pub extended_entities: Option<ExtendedEntities>,
+}
+
+impl Tweet {
+    /// The archive doesn’t keep the original of retweets, only the `RT @user:`
+    /// text, so these can’t be stored fully and need hydrating.
+    pub fn is_retweet(&self) -> bool {
+        self.full_text.starts_with("RT @")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Coordinates {
+    /// Longitude then latitude, as strings.
+    pub coordinates: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Entities {
+    #[serde(default)]
+    pub hashtags: Vec<Hashtag>,
+    #[serde(default)]
+    pub user_mentions: Vec<UserMention>,
+    #[serde(default)]
+    pub urls: Vec<Url>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Hashtag {
+    pub text: String,
+    #[serde(deserialize_with = "indices")]
+    pub indices: (i32, i32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct UserMention {
+    pub id_str: String,
+    pub screen_name: String,
+    #[serde(deserialize_with = "indices")]
+    pub indices: (i32, i32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Url {
+    pub url: String,
+    pub expanded_url: Option<String>,
+    #[serde(default)]
+    pub display_url: String,
+    #[serde(deserialize_with = "indices")]
+    pub indices: (i32, i32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExtendedEntities {
+    #[serde(default)]
+    pub media: Vec<Media>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Media {
+    pub id_str: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+    pub media_url_https: String,
+    pub source_status_id_str: Option<String>,
+    pub video_info: Option<VideoInfo>,
+    pub ext_alt_text: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct VideoInfo {
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Variant {
+    pub bitrate: Option<String>,
+    pub content_type: String,
+    pub url: String,
+}
+
+impl Variant {
+    pub fn bitrate(&self) -> Option<i32> {
+        self.bitrate.as_ref().and_then(|b| b.parse().ok())
+    }
+}
+
+fn twitter_date<'de, D: Deserializer<'de>>(d: D) -> Result<DateTime<Utc>, D::Error> {
+    use serde::de::Error;
+
+    let s = String::deserialize(d)?;
+    Utc.datetime_from_str(&s, "%a %b %d %H:%M:%S %z %Y")
+        .map_err(Error::custom)
+}
+
+/// Start and end, which archives give as strings and the API as numbers.
+fn indices<'de, D: Deserializer<'de>>(d: D) -> Result<(i32, i32), D::Error> {
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Index {
+        Number(i32),
+        Text(String),
+    }
+
+    let parsed = Vec::<Index>::deserialize(d)?
+        .into_iter()
+        .map(|index| match index {
+            Index::Number(n) => Ok(n),
+            Index::Text(s) => s.parse().map_err(Error::custom),
+        })
+        .collect::<Result<Vec<i32>, D::Error>>()?;
+
+    if parsed.len() != 2 {
+        return Err(Error::custom(format!("expected start and end, got {} indices", parsed.len())));
+    }
+
+    Ok((parsed[0], parsed[1]))
+}
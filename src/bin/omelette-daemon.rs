@@ -0,0 +1,5 @@
+use omelette::cli::{self, daemon};
+
+fn main() {
+    cli::alias(daemon::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, export_site};
+
+fn main() {
+    cli::alias(export_site::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, export};
+
+fn main() {
+    cli::alias(export::run);
+}
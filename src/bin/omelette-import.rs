@@ -0,0 +1,5 @@
+use omelette::cli::{self, import};
+
+fn main() {
+    cli::alias(import::run);
+}
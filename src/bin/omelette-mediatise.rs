@@ -1,28 +1,5 @@
-use dotenv::dotenv;
-use std::path::PathBuf;
-use structopt::StructOpt;
-
-#[derive(StructOpt, Debug)]
-#[structopt()]
-struct Opt {
-    /// Read from .env in working directory
-    #[structopt(long = "dotenv")]
-    dotenv: bool,
-
-    /// Where the blob store is located
-    #[structopt(long = "store", default_value = "./omelette/store", parse(from_os_str))]
-    store: PathBuf,
-}
+use omelette::cli::{self, mediatise};
 
 fn main() {
-    let opt = Opt::from_args();
-
-    if cfg!(debug_assertions) || opt.dotenv {
-        println!("Loading .env");
-        dotenv().ok();
-    }
-
-    let db = omelette::connect();
-
-    omelette::store::sync(&db, &opt.store);
+    cli::alias(mediatise::run);
 }
@@ -0,0 +1,5 @@
+use omelette::cli::{self, policy};
+
+fn main() {
+    cli::alias(policy::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, search};
+
+fn main() {
+    cli::alias(search::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, twitter_events};
+
+fn main() {
+    cli::alias(twitter_events::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, twitter_graph};
+
+fn main() {
+    cli::alias(twitter_graph::run);
+}
@@ -0,0 +1,5 @@
+use omelette::cli::{self, twitter_mutes};
+
+fn main() {
+    cli::alias(twitter_mutes::run);
+}
@@ -0,0 +1,253 @@
+use chrono::{Duration, Utc};
+use crate::cli::Global;
+use crate::config::Config;
+use crate::inserts::NewDeletion;
+use crate::models::{Account, Entity, Status};
+use crate::types::Source;
+use crate::{DbConnection, OmeletteError};
+use diesel::prelude::*;
+use egg_mode_text::{entities, EntityKind};
+use regex::Regex;
+use std::collections::HashSet;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only clean up this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("cleanup").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    let accounts: Vec<Account> = crate::accounts::load(&db, config, &account)?
+        .into_iter()
+        .map(|(_, account)| account)
+        .filter(|account| account.source == Source::Twitter)
+        .collect();
+
+    if accounts.is_empty() {
+        return Err(OmeletteError::Config("no twitter account configured".into()));
+    }
+
+    for account in &accounts {
+        println!("\n=> Cleaning up {}", account.name);
+        cleanup_account(&db, account)?;
+    }
+
+    Ok(())
+}
+
+fn cleanup_account(db: &DbConnection, account: &Account) -> Result<(), OmeletteError> {
+    let twitter_uid: u64 = account.source_id.parse().map_err(|_| {
+        OmeletteError::Config(format!("account {}: id must be u64", account.name))
+    })?;
+
+    // Own tweets are those linked to the account’s user, or, for tweets not
+    // linked to any user yet, those whose source_author ends in its ID.
+    let author: Option<i32> = {
+        use crate::schema::twitter_users::dsl::*;
+        twitter_users
+            .select(id)
+            .filter(source_id.eq(&account.source_id))
+            .first(db)
+            .optional()?
+    };
+
+    // Statuses already waiting to be deleted, requested by an earlier run.
+    let pending: HashSet<i32> = {
+        use crate::schema::deletions::dsl::*;
+        deletions
+            .select(status_id)
+            .filter(executed_at.is_null())
+            .load::<i32>(db)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut requests: Vec<(Status, Option<Entity>)> = {
+        use crate::schema::entities;
+        use crate::schema::statuses::dsl::*;
+
+        statuses
+            .left_join(entities::table)
+            .filter(deleted_at.is_null())
+            .filter(entities::blob_hash.is_not_null().or(entities::id.is_null()))
+            .filter(
+                author_id
+                    .eq(author)
+                    .or(author_id.is_null().and(source_author.like(format!("% ({})", twitter_uid)))),
+            )
+            .filter(text.like("%#cleanup%"))
+            .load(db)?
+    };
+    requests.retain(|(status, _)| !pending.contains(&status.id));
+
+    if requests.is_empty() {
+        println!("=> No matching statuses, skip.");
+        return Ok(());
+    }
+
+    println!(
+        "=> Found {} maybe-matching statuses, looking closer...",
+        requests.len()
+    );
+
+    let re = Regex::new(r"#cleanup(?:\s+(\d+)(s|m|h|d))?").unwrap();
+
+    let now = Utc::now();
+    let mut matches: Vec<Vec<NewDeletion>> = Vec::new();
+    'requests: for (status, _) in requests {
+        if entities(&status.text).into_iter().any(|ent| {
+            ent.kind == EntityKind::Hashtag && ent.substr(&status.text) == "#cleanup"
+        }) {
+            let delay = match re.captures_iter(&status.text).next() {
+                None => {
+                    println!("!! No duration, default to 15m\n“{}”", status.text);
+                    900
+                }
+                Some(time) => {
+                    let multiplier = match time.get(1).map(|s| s.as_str()).unwrap_or("m") {
+                        "d" => 86400,
+                        "h" => 3600,
+                        "m" => 60,
+                        "s" | _ => 1,
+                    };
+
+                    match time
+                        .get(1)
+                        .map(|s| s.as_str())
+                        .unwrap_or("15")
+                        .parse::<u32>()
+                    {
+                        Ok(n) => n * multiplier,
+                        Err(err) => {
+                            println!(
+                                "!! Cannot parse duration, default to 15m: {:?}\n“{}”",
+                                err, status.text
+                            );
+                            900
+                        }
+                    }
+                }
+            };
+
+            let not_before = now + Duration::seconds(delay as i64);
+            let mut thread = vec![NewDeletion::from_status(&status, not_before)];
+            println!(
+                "-> Requesting deletion: {:?} {} (#{})\n“{}” — {}",
+                status.source, status.source_id, status.id, status.text, status.posted_at
+            );
+
+            let mut stat = status.clone();
+            loop {
+                match own_parent(db, author, twitter_uid, &stat)? {
+                    Threading::Stop => break,
+                    Threading::Abort => continue 'requests,
+                    Threading::Parent(s) => {
+                        stat = s;
+                        thread.push(NewDeletion::from_status(&stat, not_before));
+                        println!(
+                            "-> Requesting deletion: {:?} {} (#{})\n“{}” — {}",
+                            stat.source, stat.source_id, stat.id, stat.text, stat.posted_at
+                        );
+                    }
+                }
+            }
+
+            matches.push(thread);
+        }
+    }
+
+    if matches.is_empty() {
+        println!("=> No matching statuses, skip.");
+        return Ok(());
+    }
+
+    let matching = matches.len();
+    let deletes: Vec<NewDeletion> = matches
+        .into_iter()
+        .flatten()
+        .filter(|delete| !pending.contains(&delete.status_id))
+        .collect();
+    println!(
+        "=> Found {} matching statuses, requesting deletion for {} statuses",
+        matching,
+        deletes.len()
+    );
+
+    use crate::schema::deletions::dsl::deletions;
+    diesel::insert_into(deletions)
+        .values(&deletes)
+        .execute(db)?;
+
+    Ok(())
+}
+
+enum Threading {
+    Stop,
+    Abort,
+    Parent(Status),
+}
+
+fn own_parent(
+    db: &DbConnection,
+    author: Option<i32>,
+    twitter_uid: u64,
+    status: &Status,
+) -> Result<Threading, OmeletteError> {
+    use crate::schema::entities;
+    use crate::schema::statuses::dsl::*;
+
+    if status.in_reply_to_status.is_none() {
+        return Ok(Threading::Stop);
+    }
+
+    let parent_id = status.in_reply_to_status.clone().unwrap();
+    let requests: Vec<(Status, Option<Entity>)> = statuses
+        .left_join(entities::table)
+        .filter(deleted_at.is_null())
+        .filter(source.eq(&status.source))
+        .filter(
+            author_id
+                .eq(author)
+                .or(author_id.is_null().and(source_author.like(format!("% ({})", twitter_uid)))),
+        )
+        .filter(source_id.eq(parent_id))
+        .load(db)?;
+
+    if requests.is_empty() {
+        return Ok(Threading::Stop);
+    }
+
+    let mut s = None;
+    for (stat, ent) in &requests {
+        if s.is_none() {
+            s = Some(stat);
+        }
+
+        if let Some(entity) = ent {
+            if entity.blob_hash.is_none() {
+                println!(
+                    "~~ Status has thin entities, skipping thread: {:?} {} (#{})\n“{}” — {}",
+                    stat.source, stat.source_id, stat.id, stat.text, stat.posted_at
+                );
+
+                return Ok(Threading::Abort);
+            }
+        }
+    }
+
+    Ok(Threading::Parent(s.unwrap().clone()))
+}
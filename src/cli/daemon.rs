@@ -0,0 +1,93 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::daemon::{self, DaemonStatus, Job, JobConfig};
+use crate::OmeletteError;
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Where to write the state of each job [default: ./omelette/daemon.json]
+    #[structopt(long = "status-file", parse(from_os_str))]
+    pub status_file: Option<PathBuf>,
+
+    /// Show the state of each job from the status file instead of running
+    #[structopt(long = "status")]
+    pub status: bool,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("daemon");
+    let status_file = tool
+        .or("status-file", &opt.status_file)?
+        .unwrap_or_else(|| daemon::DEFAULT_STATUS_FILE.into());
+
+    if opt.status {
+        return print_status(&status_file);
+    }
+
+    let jobs = tool
+        .get::<BTreeMap<String, JobConfig>>("jobs")?
+        .unwrap_or_else(daemon::default_jobs);
+
+    if jobs.is_empty() {
+        return Err(OmeletteError::Config("no jobs for the daemon to run".into()));
+    }
+
+    let jobs = jobs
+        .into_iter()
+        .map(|(name, job)| Job::new(&name, job))
+        .collect::<Result<Vec<Job>, OmeletteError>>()?;
+
+    println!("=> Starting daemon with {} jobs", jobs.len());
+    for job in &jobs {
+        println!(
+            ":: {}\tevery {}s:\t{}",
+            job.name,
+            job.every().num_seconds(),
+            job.args.join(" ")
+        );
+    }
+
+    daemon::run(jobs, config.clone(), &status_file)
+}
+
+fn print_status(path: &Path) -> Result<(), OmeletteError> {
+    let status = DaemonStatus::load(path)?;
+    let fmt = |t: &Option<chrono::DateTime<chrono::Utc>>| {
+        t.map(|t| t.to_rfc3339()).unwrap_or("never".into())
+    };
+
+    println!(
+        "=> Daemon (pid {}) started at {}, last updated at {}",
+        status.pid,
+        status.started_at.to_rfc3339(),
+        status.updated_at.to_rfc3339()
+    );
+
+    for (name, job) in &status.jobs {
+        println!(
+            "\n=> {}{}: {}",
+            name,
+            if job.running { " (running)" } else { "" },
+            job.args.join(" ")
+        );
+        println!(":: Last run:\t\t{}", fmt(&job.last_started_at));
+        println!(":: Last success:\t{}", fmt(&job.last_success_at));
+        println!(":: Next run:\t\t{}", fmt(&job.next_run_at));
+        println!(":: Runs:\t\t{}", job.runs);
+        if let Some(ref err) = job.last_error {
+            println!("!! Last error ({} failures in a row): {}", job.failures, err);
+        }
+    }
+
+    Ok(())
+}
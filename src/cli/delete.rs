@@ -0,0 +1,58 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::sources::{all_available, run_deletes, ActionMode};
+use crate::OmeletteError;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Don’t actually perform deletes
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Ask before performing each delete
+    #[structopt(long = "interactive")]
+    pub interactive: bool,
+
+    /// Only delete statuses of this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("delete");
+    let dry_run = tool.flag("dry-run", opt.dry_run)?;
+    let interactive = tool.flag("interactive", opt.interactive)?;
+    let account = tool.or("account", &opt.account)?;
+
+    if interactive && dry_run {
+        return Err(OmeletteError::Config(
+            "cannot supply both --dry-run and --interactive".into(),
+        ));
+    }
+
+    let db = crate::connect()?;
+    let sources = all_available(&db, config, &account)?;
+
+    run_deletes(
+        &sources,
+        &db,
+        if dry_run {
+            ActionMode::DryRun
+        } else if interactive {
+            ActionMode::Interactive
+        } else {
+            ActionMode::Auto
+        },
+    )?;
+
+    Ok(())
+}
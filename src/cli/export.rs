@@ -0,0 +1,288 @@
+use blobstore::{BlobStore, Store};
+use chrono::Utc;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::dump::{self, DeletionRecord, EntityRecord, Manifest, VariantRecord};
+use crate::inserts::{NewStatus, NewTwitterUser};
+use crate::models::{Deletion, Entity, EntityVariant, Status, TwitterUser};
+use crate::types::Source;
+use crate::OmeletteError;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Folder to write the dump to [default: ./omelette/dump]
+    #[structopt(long = "out", parse(from_os_str))]
+    pub out: Option<PathBuf>,
+
+    /// Also copy media files from the blob store into the dump
+    #[structopt(long = "media")]
+    pub media: bool,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+}
+
+const BATCH: i64 = 1000;
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("export");
+    let dir = tool
+        .or("out", &opt.out)?
+        .unwrap_or_else(|| "./omelette/dump".into());
+    let media = tool.flag("media", opt.media)?;
+    let store = config.store(&tool, &opt.store)?;
+
+    let db = crate::connect()?;
+
+    // Remove any manifest first, so a dump interrupted while overwriting an
+    // older one doesn’t look complete.
+    let manifest_path = dir.join(dump::MANIFEST);
+    if manifest_path.exists() {
+        fs::remove_file(&manifest_path)?;
+    }
+
+    fs::create_dir_all(&dir)?;
+    if media {
+        fs::create_dir_all(dir.join("media"))?;
+    }
+
+    println!("=> Exporting to {}", dir.display());
+
+    let mut out = writer(&dir, dump::TWITTER_USERS)?;
+    let mut twitter_users_count = 0;
+    let mut last = 0;
+    loop {
+        use crate::schema::twitter_users::dsl::*;
+
+        let page: Vec<TwitterUser> = twitter_users
+            .filter(id.gt(last))
+            .order_by(id)
+            .limit(BATCH)
+            .load(&db)?;
+
+        if page.is_empty() {
+            break;
+        }
+
+        last = page.last().unwrap().id;
+        for user in &page {
+            line(&mut out, &NewTwitterUser::from(user))?;
+        }
+
+        twitter_users_count += page.len();
+    }
+    out.flush()?;
+    println!("-> {} twitter users", twitter_users_count);
+
+    let mut out = writer(&dir, dump::STATUSES)?;
+    let mut statuses_count = 0;
+    let mut last = 0;
+    loop {
+        use crate::schema::statuses::dsl::*;
+
+        let page: Vec<Status> = statuses
+            .filter(id.gt(last))
+            .order_by(id)
+            .limit(BATCH)
+            .load(&db)?;
+
+        if page.is_empty() {
+            break;
+        }
+
+        last = page.last().unwrap().id;
+        for status in &page {
+            line(&mut out, &NewStatus::from(status))?;
+        }
+
+        statuses_count += page.len();
+    }
+    out.flush()?;
+    println!("-> {} statuses", statuses_count);
+
+    let bs = BlobStore::new(store.to_string_lossy().into());
+    let mut out = writer(&dir, dump::ENTITIES)?;
+    let mut entities_count = 0;
+    let mut media_count = 0;
+    let mut last = 0;
+    loop {
+        use crate::schema::entities::dsl::*;
+        use crate::schema::statuses;
+
+        let page: Vec<(Entity, Source, String)> = entities
+            .inner_join(statuses::table)
+            .select((
+                crate::schema::entities::all_columns,
+                statuses::source,
+                statuses::source_id,
+            ))
+            .filter(id.gt(last))
+            .order_by(id)
+            .limit(BATCH)
+            .load(&db)?;
+
+        if page.is_empty() {
+            break;
+        }
+
+        last = page.last().unwrap().0.id;
+        for (entity, src, sid) in &page {
+            let mut record = EntityRecord::new(entity, src, sid);
+
+            if media {
+                if let Some(ref hash) = entity.blob_hash {
+                    record.media_path = copy_media(&bs, &dir, hash, &mut media_count)?;
+                }
+            }
+
+            line(&mut out, &record)?;
+        }
+
+        entities_count += page.len();
+    }
+    out.flush()?;
+    println!("-> {} entities ({} media files copied)", entities_count, media_count);
+
+    let mut out = writer(&dir, dump::ENTITY_VARIANTS)?;
+    let mut variants_count = 0;
+    let mut media_count = 0;
+    let mut last = 0;
+    loop {
+        use crate::schema::entity_variants::dsl::*;
+        use crate::schema::entities;
+
+        let page: Vec<(EntityVariant, Source, String)> = entity_variants
+            .inner_join(entities::table)
+            .select((
+                crate::schema::entity_variants::all_columns,
+                entities::source,
+                entities::source_id,
+            ))
+            .filter(id.gt(last))
+            .order_by(id)
+            .limit(BATCH)
+            .load(&db)?;
+
+        if page.is_empty() {
+            break;
+        }
+
+        last = page.last().unwrap().0.id;
+        for (variant, src, eid) in &page {
+            let mut record = VariantRecord::new(variant, src, eid);
+
+            if media {
+                if let Some(ref hash) = variant.blob_hash {
+                    record.media_path = copy_media(&bs, &dir, hash, &mut media_count)?;
+                }
+            }
+
+            line(&mut out, &record)?;
+        }
+
+        variants_count += page.len();
+    }
+    out.flush()?;
+    println!("-> {} entity variants ({} media files copied)", variants_count, media_count);
+
+    let mut out = writer(&dir, dump::DELETIONS)?;
+    let mut deletions_count = 0;
+    let mut last = 0;
+    loop {
+        use crate::schema::deletions::dsl::*;
+        use crate::schema::statuses;
+
+        let page: Vec<(Deletion, Source, String)> = deletions
+            .inner_join(statuses::table)
+            .select((
+                crate::schema::deletions::all_columns,
+                statuses::source,
+                statuses::source_id,
+            ))
+            .filter(id.gt(last))
+            .order_by(id)
+            .limit(BATCH)
+            .load(&db)?;
+
+        if page.is_empty() {
+            break;
+        }
+
+        last = page.last().unwrap().0.id;
+        for (deletion, src, sid) in &page {
+            line(&mut out, &DeletionRecord::new(deletion, src, sid))?;
+        }
+
+        deletions_count += page.len();
+    }
+    out.flush()?;
+    println!("-> {} deletions", deletions_count);
+
+    let manifest = Manifest {
+        format: dump::FORMAT,
+        omelette: env!("CARGO_PKG_VERSION").into(),
+        exported_at: Utc::now(),
+        statuses: statuses_count,
+        entities: entities_count,
+        entity_variants: variants_count,
+        deletions: deletions_count,
+        twitter_users: twitter_users_count,
+        media,
+    };
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+
+    println!("=> Export complete");
+    Ok(())
+}
+
+/// Copies a blob into the dump’s media folder, unless it’s there already from
+/// another record, and returns its path relative to the dump.
+fn copy_media(
+    bs: &BlobStore,
+    dir: &Path,
+    hash: &str,
+    copied: &mut usize,
+) -> Result<Option<String>, OmeletteError> {
+    let path = format!("media/{}", hash);
+    let file = dir.join(&path);
+    if file.exists() {
+        return Ok(Some(path));
+    }
+
+    match bs.get(hash) {
+        Err(err) => {
+            println!("!! Cannot read blob {} from store: {:?}", hash, err);
+            Ok(None)
+        }
+        Ok(mut blob) => {
+            io::copy(&mut blob, &mut File::create(&file)?)?;
+            *copied += 1;
+            Ok(Some(path))
+        }
+    }
+}
+
+fn writer(dir: &Path, name: &str) -> Result<BufWriter<File>, OmeletteError> {
+    Ok(BufWriter::new(File::create(dir.join(name))?))
+}
+
+fn line<T: Serialize>(out: &mut BufWriter<File>, record: &T) -> Result<(), OmeletteError> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
@@ -0,0 +1,437 @@
+use blobstore::{BlobStore, Store};
+use chrono::{Datelike, Utc};
+use crate::accounts;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::models::{Entity, Status};
+use crate::types::{MediaType, Source};
+use crate::OmeletteError;
+use diesel::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Where to write the site [default: ./omelette/site]
+    #[structopt(long = "out", parse(from_os_str))]
+    pub out: Option<PathBuf>,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+
+    /// Also export statuses that have been deleted from the service
+    #[structopt(long = "include-deleted")]
+    pub include_deleted: bool,
+
+    /// Also export statuses that aren’t public (protected, followers-only…)
+    #[structopt(long = "include-private")]
+    pub include_private: bool,
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 40em; margin: 2em auto; padding: 0 1em; }
+article { border-bottom: 1px solid #ddd; padding: 1em 0; }
+.meta { color: #666; font-size: 0.9em; }
+.media img, .media video { max-width: 100%; height: auto; margin-top: 0.5em; }
+.deleted { opacity: 0.6; }
+";
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("export-site");
+    let out = tool
+        .or("out", &opt.out)?
+        .unwrap_or_else(|| "./omelette/site".into());
+    let store = config.store(&tool, &opt.store)?;
+    let include_deleted = tool.flag("include-deleted", opt.include_deleted)?;
+    let include_private = tool.flag("include-private", opt.include_private)?;
+
+    let db = crate::connect()?;
+
+    let accounts = accounts::load(&db, config, &None)?;
+    if accounts.is_empty() {
+        return Err(OmeletteError::Config("no account with an id is configured".into()));
+    }
+
+    let mut found: Vec<Status> = Vec::new();
+    for (_, account) in &accounts {
+        use crate::schema::statuses::dsl::*;
+        use crate::schema::twitter_users;
+
+        let mut query = statuses
+            .left_join(twitter_users::table)
+            .select(crate::schema::statuses::all_columns)
+            .filter(source.eq(account.source.clone()))
+            .filter(is_repost.eq(true).or(source_author.like(format!("% ({})", account.source_id))))
+            .into_boxed();
+
+        if !include_deleted {
+            query = query.filter(deleted_at.is_null());
+        }
+        if !include_private {
+            // `public` is as of when the status was fetched: leave out
+            // authors that have gone protected since, ourselves included.
+            query = query.filter(public.eq(true)).filter(
+                twitter_users::id
+                    .is_null()
+                    .or(twitter_users::is_protected.eq(false)),
+            );
+        }
+
+        found.extend(query.load::<Status>(&db)?);
+    }
+
+    // Reposts come up once for each account on their source.
+    found.sort_by_key(|status| (status.posted_at, status.id));
+    found.dedup_by_key(|status| status.id);
+
+    if found.is_empty() {
+        println!("=> No statuses to export, skip.");
+        return Ok(());
+    }
+
+    println!("=> Exporting {} statuses to {}", found.len(), out.display());
+
+    let media = Entity::belonging_to(&found)
+        .load::<Entity>(&db)?
+        .grouped_by(&found);
+
+    let posters: HashMap<i32, String> = {
+        use crate::schema::entity_variants::dsl::*;
+        let ids: Vec<i32> = media.iter().flatten().map(|ent| ent.id).collect();
+        entity_variants
+            .select((entity_id, blob_hash))
+            .filter(entity_id.eq_any(ids))
+            .filter(kind.eq("poster"))
+            .filter(blob_hash.is_not_null())
+            .load::<(i32, Option<String>)>(&db)?
+            .into_iter()
+            .filter_map(|(eid, hash)| hash.map(|hash| (eid, hash)))
+            .collect()
+    };
+
+    fs::create_dir_all(out.join("status"))?;
+    fs::create_dir_all(out.join("media"))?;
+    fs::write(out.join("style.css"), STYLE)?;
+
+    let mut site = Site {
+        out: out.clone(),
+        store: BlobStore::new(store.to_string_lossy().into()),
+        pages: found
+            .iter()
+            .map(|status| ((status.source.clone(), status.source_id.clone()), page_name(status)))
+            .collect(),
+        replies: HashMap::new(),
+        posters,
+        stored: HashMap::new(),
+    };
+
+    for status in &found {
+        if let Some(ref parent) = status.in_reply_to_status {
+            site.replies
+                .entry((status.source.clone(), parent.clone()))
+                .or_insert_with(Vec::new)
+                .push(status);
+        }
+    }
+
+    let mut months: BTreeMap<(i32, u32), Vec<String>> = BTreeMap::new();
+    for (status, ents) in found.iter().zip(media.iter()) {
+        let article = site.article(status, ents, "..")?;
+        site.write_status(status, &article)?;
+
+        months
+            .entry((status.posted_at.year(), status.posted_at.month()))
+            .or_insert_with(Vec::new)
+            .push(site.article(status, ents, "../..")?);
+    }
+
+    let mut index = String::from("<h1>Archive</h1>\n<ul>\n");
+    for ((year, month), articles) in &months {
+        let dir = out.join(format!("{:04}/{:02}", year, month));
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("index.html"),
+            layout(
+                &format!("{:04}-{:02}", year, month),
+                "../..",
+                &format!(
+                    "<h1>{:04}-{:02}</h1>\n{}",
+                    year,
+                    month,
+                    articles.join("\n")
+                ),
+            ),
+        )?;
+
+        index.push_str(&format!(
+            "<li><a href=\"{y:04}/{m:02}/index.html\">{y:04}-{m:02}</a> ({n})</li>\n",
+            y = year,
+            m = month,
+            n = articles.len()
+        ));
+    }
+    index.push_str("</ul>\n");
+    fs::write(out.join("index.html"), layout("Archive", ".", &index))?;
+
+    println!(
+        "=> Wrote {} status pages over {} months, with {} media files",
+        found.len(),
+        months.len(),
+        site.stored.values().filter(|file| file.is_some()).count()
+    );
+
+    Ok(())
+}
+
+struct Site<'a> {
+    out: PathBuf,
+    store: BlobStore,
+    /// Page for each exported status, by source and source ID.
+    pages: HashMap<(Source, String), String>,
+    /// Exported replies to each status, by source and source ID.
+    replies: HashMap<(Source, String), Vec<&'a Status>>,
+    /// Blob hash of the poster of each video or gif, by entity ID, if it was
+    /// downloaded (see `omelette-mediatise --variants`).
+    posters: HashMap<i32, String>,
+    /// File in media/ for each blob hash, or None if it couldn’t be copied.
+    stored: HashMap<String, Option<String>>,
+}
+
+impl<'a> Site<'a> {
+    fn write_status(&self, status: &Status, article: &str) -> Result<(), OmeletteError> {
+        let mut body = format!(
+            "<p><a href=\"../{y:04}/{m:02}/index.html\">{y:04}-{m:02}</a></p>\n{}",
+            article,
+            y = status.posted_at.year(),
+            m = status.posted_at.month()
+        );
+
+        if let Some(replies) = self.replies.get(&(status.source.clone(), status.source_id.clone())) {
+            body.push_str("<h2>Replies</h2>\n<ul>\n");
+            for reply in replies {
+                body.push_str(&format!(
+                    "<li><a href=\"../status/{}\">{}</a></li>\n",
+                    page_name(reply),
+                    escape(&excerpt(&reply.text))
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+
+        fs::write(
+            self.out.join("status").join(page_name(status)),
+            layout(&excerpt(&status.text), "..", &body),
+        )?;
+
+        Ok(())
+    }
+
+    /// Renders a status, with links relative to `root`.
+    fn article(&mut self, status: &Status, ents: &[Entity], root: &str) -> Result<String, OmeletteError> {
+        let mut html = format!(
+            "<article{}>\n<p class=\"meta\"><a href=\"{}/status/{}\">{}</a>{}{}</p>\n",
+            if status.deleted_at.is_some() { " class=\"deleted\"" } else { "" },
+            root,
+            page_name(status),
+            status.posted_at.format("%Y-%m-%d %H:%M UTC"),
+            if status.is_repost {
+                format!(" — reposted from {}", escape(&status.source_author))
+            } else {
+                "".into()
+            },
+            if status.deleted_at.is_some() { " — deleted" } else { "" },
+        );
+
+        if let Some(ref parent) = status.in_reply_to_status {
+            html.push_str(&format!(
+                "<p class=\"meta\">In reply to {}</p>\n",
+                self.link(&status.source, parent, root)
+            ));
+        }
+
+        html.push_str(&format!(
+            "<p>{}</p>\n",
+            escape(&status.text).replace('\n', "<br>\n")
+        ));
+
+        if let Some(ref quoted) = status.quoting_status {
+            html.push_str(&format!(
+                "<p class=\"meta\">Quoting {}</p>\n",
+                self.link(&status.source, quoted, root)
+            ));
+        }
+
+        if !ents.is_empty() {
+            html.push_str("<div class=\"media\">\n");
+            for ent in ents {
+                html.push_str(&self.media(ent, root)?);
+            }
+            html.push_str("</div>\n");
+        }
+
+        html.push_str("</article>");
+        Ok(html)
+    }
+
+    /// Links to the exported page of a status if we have it, or to the
+    /// original otherwise.
+    fn link(&self, src: &Source, sid: &str, root: &str) -> String {
+        match self.pages.get(&(src.clone(), sid.to_string())) {
+            Some(page) => format!("<a href=\"{}/status/{}\">{}</a>", root, page, sid),
+            None => match src {
+                Source::Twitter => format!(
+                    "<a href=\"https://twitter.com/i/web/status/{}\">{}</a>",
+                    sid, sid
+                ),
+                Source::Mastodon => escape(sid),
+            },
+        }
+    }
+
+    fn media(&mut self, ent: &Entity, root: &str) -> Result<String, OmeletteError> {
+        let file = match ent.blob_hash {
+            Some(ref hash) => self.copy_blob(hash, &ent.mime_type)?,
+            None => None,
+        };
+
+        let poster = match self.posters.get(&ent.id).cloned() {
+            Some(hash) => self.copy_blob(&hash, &None)?,
+            None => None,
+        };
+
+        Ok(match (file, poster) {
+            // Without the video, its poster still shows what it was.
+            (None, Some(ref poster)) => format!(
+                "<a href=\"{}\"><img src=\"{}/media/{}\" alt=\"{:?}\"></a>\n",
+                escape(&ent.source_url),
+                root,
+                poster,
+                ent.media_type
+            ),
+            (None, None) => format!(
+                "<p><a href=\"{}\">{:?}</a></p>\n",
+                escape(&ent.source_url),
+                ent.media_type
+            ),
+            (Some(ref name), _) if ent.media_type == MediaType::Photo => format!(
+                "<img src=\"{}/media/{}\"{} alt=\"{}\">\n",
+                root,
+                name,
+                dimensions(ent),
+                escape(ent.alt_text.as_ref().map_or("", |alt| alt.as_str()))
+            ),
+            (Some(name), poster) => format!(
+                "<video src=\"{}/media/{}\"{}{}{} controls{}></video>\n",
+                root,
+                name,
+                dimensions(ent),
+                poster.map_or("".into(), |poster| format!(" poster=\"{}/media/{}\"", root, poster)),
+                ent.alt_text.as_ref().map_or("".into(), |alt| format!(" aria-label=\"{}\"", escape(alt))),
+                if ent.media_type == MediaType::Gif { " autoplay loop muted" } else { "" }
+            ),
+        })
+    }
+
+    /// Copies a blob into the site’s media folder, once. The file extension
+    /// is what the web server goes by for the content type, so it’s from the
+    /// type recorded when mediatising, or detected here if there’s none yet.
+    fn copy_blob(&mut self, hash: &str, mime: &Option<String>) -> Result<Option<String>, OmeletteError> {
+        if let Some(file) = self.stored.get(hash) {
+            return Ok(file.clone());
+        }
+
+        let file = match self.store.get(hash) {
+            Err(err) => {
+                println!("!! Cannot read blob {} from store: {:?}", hash, err);
+                None
+            }
+            Ok(content) => {
+                let mime = mime.clone().unwrap_or_else(|| tree_magic::from_u8(&content));
+                let name = format!("{}.{}", hash, extension(&mime));
+                let path = self.out.join("media").join(&name);
+                if !path.exists() {
+                    fs::write(&path, &content)?;
+                }
+
+                Some(name)
+            }
+        };
+
+        self.stored.insert(hash.into(), file.clone());
+        Ok(file)
+    }
+}
+
+fn page_name(status: &Status) -> String {
+    format!("{}-{}.html", format!("{:?}", status.source).to_lowercase(), status.source_id)
+}
+
+/// Lets browsers lay out the page before media loads.
+fn dimensions(ent: &Entity) -> String {
+    match (ent.width, ent.height) {
+        (Some(w), Some(h)) => format!(" width=\"{}\" height=\"{}\"", w, h),
+        _ => String::new(),
+    }
+}
+
+fn extension(mime: &str) -> &'static str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "video/mp4" => "mp4",
+        "video/quicktime" => "mov",
+        _ => "bin",
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("");
+    if line.chars().count() > 60 {
+        line.chars().take(59).chain(Some('…')).collect()
+    } else {
+        line.into()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn layout(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<link rel=\"stylesheet\" href=\"{}/style.css\">
+</head>
+<body>
+<nav><a href=\"{}/index.html\">Archive</a></nav>
+{}
+<footer class=\"meta\">Exported by omelette on {}</footer>
+</body>
+</html>
+",
+        escape(title),
+        root,
+        root,
+        body,
+        Utc::now().format("%Y-%m-%d")
+    )
+}
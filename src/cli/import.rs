@@ -0,0 +1,373 @@
+use blobstore::{BlobStore, Store};
+use chrono::{DateTime, Utc};
+use crate::cli::Global;
+use crate::config::Config;
+use crate::dump::{self, DeletionRecord, EntityRecord, Manifest, VariantRecord};
+use crate::inserts::{NewStatus, NewTwitterUser};
+use crate::sources::twitter::Twitter;
+use crate::types::Source;
+use crate::{insert_or_ignore, OmeletteError};
+use diesel::prelude::*;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Folder containing the dump
+    #[structopt(parse(from_os_str))]
+    pub dump: PathBuf,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+
+    /// Don’t put media files from the dump into the blob store
+    #[structopt(long = "no-media")]
+    pub no_media: bool,
+}
+
+const BATCH: usize = 1000;
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("import");
+    let no_media = tool.flag("no-media", opt.no_media)?;
+    let store = config.store(&tool, &opt.store)?;
+
+    let manifest: Manifest = match fs::read_to_string(opt.dump.join(dump::MANIFEST)) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => {
+            return Err(OmeletteError::Data(format!(
+                "no {} in {}, is the export complete?",
+                dump::MANIFEST,
+                opt.dump.display()
+            )))
+        }
+    };
+
+    if manifest.format > dump::FORMAT {
+        return Err(OmeletteError::Data(format!(
+            "dump format {} is newer than this omelette supports ({}), upgrade first",
+            manifest.format,
+            dump::FORMAT
+        )));
+    }
+
+    println!(
+        "=> Importing dump from omelette {} made {}",
+        manifest.omelette, manifest.exported_at
+    );
+
+    let db = crate::connect()?;
+
+    // Everything is inserted with insert_or_ignore!, so running the import
+    // again (or over a database that already has some of the data) keeps
+    // what’s there and only adds what’s missing.
+
+    let mut inserted = 0;
+    let total = batches(&opt.dump, dump::TWITTER_USERS, |batch: Vec<NewTwitterUser>| {
+        use crate::schema::twitter_users::dsl::*;
+        inserted += insert_or_ignore!(twitter_users, &batch, source_id).execute(&db)?;
+        Ok(())
+    })?;
+    println!("-> {} new twitter users (out of {})", inserted, total);
+
+    let mut inserted = 0;
+    let total = batches(&opt.dump, dump::STATUSES, |batch: Vec<NewStatus>| {
+        use crate::schema::statuses::dsl::*;
+        inserted += insert_or_ignore!(statuses, &batch, (source, source_id)).execute(&db)?;
+        Ok(())
+    })?;
+    println!("-> {} new statuses (out of {})", inserted, total);
+
+    let linked = Twitter::link_authors(&db)?;
+    if linked > 0 {
+        println!("-> Linked {} tweets to their authors", linked);
+    }
+
+    let status_ids: HashMap<(Source, String), i32> = {
+        use crate::schema::statuses::dsl::*;
+        statuses
+            .select((source, source_id, id))
+            .load::<(Source, String, i32)>(&db)?
+            .into_iter()
+            .map(|(src, sid, iid)| ((src, sid), iid))
+            .collect()
+    };
+
+    let bs = BlobStore::new(store.to_string_lossy().into());
+    let mut inserted = 0;
+    let mut stored = 0;
+    let total = batches(&opt.dump, dump::ENTITIES, |batch: Vec<EntityRecord>| {
+        use crate::schema::entities::dsl::*;
+
+        let mut inserts = Vec::with_capacity(batch.len());
+        let mut hashes = Vec::new();
+        for record in &batch {
+            let found =
+                find_by_source(&status_ids, &record.status_source, &record.status_source_id);
+            let (src, sid) = match found {
+                Some(found) => found,
+                None => {
+                    println!(
+                        "~~ Entity {} refers to unknown status {}, skipping",
+                        record.source_id, record.status_source_id
+                    );
+                    continue;
+                }
+            };
+
+            inserts.push(record.to_insert(sid, src.clone()));
+
+            let what = format!("entity {}", record.source_id);
+            let media = if no_media { None } else { record.media_path.as_ref() };
+            if let Some(hash) = media_hash(&bs, &opt.dump, media, &record.blob_hash, &what, &mut stored)
+            {
+                hashes.push((src, record.source_id.clone(), hash));
+            }
+        }
+
+        inserted += insert_or_ignore!(entities, &inserts, (source, source_id)).execute(&db)?;
+
+        for (src, sid, hash) in &hashes {
+            diesel::update(
+                entities
+                    .filter(source.eq(src))
+                    .filter(source_id.eq(sid))
+                    .filter(blob_hash.is_null()),
+            )
+            .set(blob_hash.eq(hash))
+            .execute(&db)?;
+        }
+
+        Ok(())
+    })?;
+    println!(
+        "-> {} new entities (out of {}), {} media files stored",
+        inserted, total, stored
+    );
+
+    let entity_ids: HashMap<(Source, String), i32> = {
+        use crate::schema::entities::dsl::*;
+        entities
+            .select((source, source_id, id))
+            .load::<(Source, String, i32)>(&db)?
+            .into_iter()
+            .map(|(src, sid, iid)| ((src, sid), iid))
+            .collect()
+    };
+
+    let mut inserted = 0;
+    let mut stored = 0;
+    let total = batches(&opt.dump, dump::ENTITY_VARIANTS, |batch: Vec<VariantRecord>| {
+        use crate::schema::entity_variants::dsl::*;
+
+        let mut inserts = Vec::with_capacity(batch.len());
+        let mut hashes = Vec::new();
+        for record in &batch {
+            let found =
+                find_by_source(&entity_ids, &record.entity_source, &record.entity_source_id);
+            let eid = match found {
+                Some((_, eid)) => eid,
+                None => {
+                    println!(
+                        "~~ Variant {} refers to unknown entity {}, skipping",
+                        record.url, record.entity_source_id
+                    );
+                    continue;
+                }
+            };
+
+            inserts.push(record.to_insert(eid));
+
+            let what = format!("variant {}", record.url);
+            let media = if no_media { None } else { record.media_path.as_ref() };
+            if let Some(hash) = media_hash(&bs, &opt.dump, media, &record.blob_hash, &what, &mut stored)
+            {
+                hashes.push((eid, record.url.clone(), hash));
+            }
+        }
+
+        inserted += insert_or_ignore!(entity_variants, &inserts, (entity_id, url)).execute(&db)?;
+
+        for (eid, vurl, hash) in &hashes {
+            diesel::update(
+                entity_variants
+                    .filter(entity_id.eq(eid))
+                    .filter(url.eq(vurl))
+                    .filter(blob_hash.is_null()),
+            )
+            .set(blob_hash.eq(hash))
+            .execute(&db)?;
+        }
+
+        Ok(())
+    })?;
+    println!(
+        "-> {} new entity variants (out of {}), {} media files stored",
+        inserted, total, stored
+    );
+
+    // Deletions have no natural key, so we skip those we already have for the
+    // same status at the same time.
+    let existing: HashSet<(i32, DateTime<Utc>)> = {
+        use crate::schema::deletions::dsl::*;
+        deletions
+            .select((status_id, created_at))
+            .load::<(i32, DateTime<Utc>)>(&db)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut inserted = 0;
+    let total = batches(&opt.dump, dump::DELETIONS, |batch: Vec<DeletionRecord>| {
+        use crate::schema::deletions::dsl::*;
+
+        let mut inserts = Vec::with_capacity(batch.len());
+        for record in &batch {
+            let found =
+                find_by_source(&status_ids, &record.status_source, &record.status_source_id);
+            let sid = match found {
+                Some((_, sid)) => sid,
+                None => {
+                    println!(
+                        "~~ Deletion refers to unknown status {}, skipping",
+                        record.status_source_id
+                    );
+                    continue;
+                }
+            };
+
+            if existing.contains(&(sid, record.created_at)) {
+                continue;
+            }
+
+            inserts.push((
+                status_id.eq(sid),
+                created_at.eq(record.created_at),
+                not_before.eq(record.not_before),
+                executed_at.eq(record.executed_at),
+                sponsor.eq(record.sponsor.clone()),
+            ));
+        }
+
+        inserted += diesel::insert_into(deletions)
+            .values(inserts)
+            .execute(&db)?;
+
+        Ok(())
+    })?;
+    println!("-> {} new deletions (out of {})", inserted, total);
+
+    println!("=> Import complete");
+    Ok(())
+}
+
+/// The source and internal ID of the status or entity a record refers to.
+/// Records from older dumps don’t say which source it’s on, so they only match
+/// if a single source has one with that ID.
+fn find_by_source(
+    ids: &HashMap<(Source, String), i32>,
+    src: &Option<Source>,
+    sid: &str,
+) -> Option<(Source, i32)> {
+    if let Some(src) = src {
+        return ids.get(&(src.clone(), sid.into())).map(|iid| (src.clone(), *iid));
+    }
+
+    let mut found = [Source::Twitter, Source::Mastodon]
+        .iter()
+        .filter_map(|src| ids.get(&(src.clone(), sid.into())).map(|iid| (src.clone(), *iid)));
+
+    match (found.next(), found.next()) {
+        (Some(found), None) => Some(found),
+        _ => None,
+    }
+}
+
+/// Reads a JSON Lines file from the dump and hands it over in batches,
+/// returning the number of records read.
+fn batches<T, F>(dir: &Path, name: &str, mut f: F) -> Result<usize, OmeletteError>
+where
+    T: DeserializeOwned,
+    F: FnMut(Vec<T>) -> Result<(), OmeletteError>,
+{
+    let path = dir.join(name);
+    if !path.exists() {
+        println!("~~ No {} in dump, skipping", name);
+        return Ok(0);
+    }
+
+    let mut total = 0;
+    let mut batch = Vec::with_capacity(BATCH);
+    for (n, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        batch.push(
+            serde_json::from_str(&line)
+                .map_err(|err| OmeletteError::Data(format!("{} line {}: {}", name, n + 1, err)))?,
+        );
+
+        if batch.len() == BATCH {
+            total += batch.len();
+            f(batch)?;
+            batch = Vec::with_capacity(BATCH);
+        }
+    }
+
+    if !batch.is_empty() {
+        total += batch.len();
+        f(batch)?;
+    }
+
+    Ok(total)
+}
+
+/// The hash to record for a record’s media: that of its file in the dump once
+/// stored, or the one recorded if there’s no file or it can’t be stored.
+fn media_hash(
+    bs: &BlobStore,
+    dir: &Path,
+    media_path: Option<&String>,
+    recorded: &Option<String>,
+    what: &str,
+    stored: &mut usize,
+) -> Option<String> {
+    let path = match media_path {
+        Some(path) => path,
+        None => return recorded.clone(),
+    };
+
+    match put_media(bs, &dir.join(path)) {
+        Ok(hash) => {
+            *stored += 1;
+            if recorded.as_ref().map_or(false, |h| *h != hash) {
+                println!("~~ Media for {} doesn’t match its recorded hash, using the file’s", what);
+            }
+            Some(hash)
+        }
+        Err(err) => {
+            println!("!! Cannot store media for {}: {}", what, err);
+            recorded.clone()
+        }
+    }
+}
+
+fn put_media(bs: &BlobStore, path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|err| format!("{}", err))?;
+    bs.put(&mut file).map_err(|err| format!("{:?}", err))
+}
@@ -0,0 +1,147 @@
+use chrono::Duration;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::store::{Downloads, Variants};
+use crate::OmeletteError;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+
+    /// How many downloads to run at once [default: 4]
+    #[structopt(long = "parallel")]
+    pub parallel: Option<usize>,
+
+    /// Give up on media after this many failed downloads in a row [default: 8]
+    #[structopt(long = "give-up-after")]
+    pub give_up_after: Option<i32>,
+
+    /// Which variants of videos and gifs to download: best, best-plus-poster,
+    /// or all [default: best]
+    #[structopt(long = "variants")]
+    pub variants: Option<Variants>,
+
+    /// Try again to download media that was given up on
+    #[structopt(long = "retry-lost")]
+    pub retry_lost: bool,
+
+    /// Check or clean the store instead of downloading
+    #[structopt(subcommand)]
+    pub action: Option<Action>,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Action {
+    /// Re-hash every blob entities refer to, and clear missing or corrupt ones
+    /// so they’re downloaded again
+    #[structopt(name = "verify")]
+    Verify(VerifyOpt),
+
+    /// Remove blobs no entity refers to
+    #[structopt(name = "gc")]
+    Gc(GcOpt),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct VerifyOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct GcOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Where the blob store is located [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+
+    /// Only list unreferenced blobs, don’t remove them
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    let global = match opt.action {
+        Some(Action::Verify(ref sub)) => &sub.global,
+        Some(Action::Gc(ref sub)) => &sub.global,
+        None => &opt.global,
+    };
+
+    // Options can be given before or after the subcommand, those after win.
+    let global = Global {
+        dotenv: opt.global.dotenv || global.dotenv,
+        config: global.config.clone().or_else(|| opt.global.config.clone()),
+    };
+
+    run_with(opt, &global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    match opt.action {
+        Some(Action::Verify(ref sub)) => verify(opt, sub, config),
+        Some(Action::Gc(ref sub)) => gc(opt, sub, config),
+        None => sync(opt, config),
+    }
+}
+
+/// The store can be given before or after the subcommand, after wins.
+fn store_path(opt: &Opt, config: &Config, store: &Option<PathBuf>) -> Result<PathBuf, OmeletteError> {
+    config.store(
+        &config.tool("mediatise"),
+        &store.clone().or_else(|| opt.store.clone()),
+    )
+}
+
+fn sync(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let store = store_path(opt, config, &opt.store)?;
+    let tool = config.tool("mediatise");
+    let defaults = Downloads::default();
+    let downloads = Downloads {
+        parallel: tool.or("parallel", &opt.parallel)?.unwrap_or(defaults.parallel),
+        give_up_after: tool
+            .or("give-up-after", &opt.give_up_after)?
+            .unwrap_or(defaults.give_up_after),
+        variants: tool.or("variants", &opt.variants)?.unwrap_or(defaults.variants),
+    };
+
+    let db = crate::connect()?;
+
+    if opt.retry_lost {
+        let n = crate::store::retry_lost(&db)?;
+        println!("=> Trying again for {} lost media", n);
+    }
+
+    crate::store::sync(&db, &store, downloads)?;
+    Ok(())
+}
+
+fn verify(opt: &Opt, sub: &VerifyOpt, config: &Config) -> Result<(), OmeletteError> {
+    let store = store_path(opt, config, &sub.store)?;
+
+    let db = crate::connect()?;
+    crate::store::verify(&db, &store)?;
+    Ok(())
+}
+
+fn gc(opt: &Opt, sub: &GcOpt, config: &Config) -> Result<(), OmeletteError> {
+    let store = store_path(opt, config, &sub.store)?;
+    let dry_run = config.tool("mediatise").flag("dry-run", sub.dry_run)?;
+
+    let db = crate::connect()?;
+    crate::store::gc(&db, &store, Duration::days(1), dry_run)?;
+    Ok(())
+}
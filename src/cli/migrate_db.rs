@@ -0,0 +1,59 @@
+use crate::accounts;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::OmeletteError;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Link statuses from before accounts, or from an import, to the account that wrote them
+    #[structopt(long = "claim")]
+    pub claim: bool,
+
+    /// Then link the remaining reposts, likes, sync progress, blocks, and mutes on its source to this account
+    #[structopt(long = "claim-rest-for", name = "ACCOUNT")]
+    pub claim_rest_for: Option<String>,
+}
+
+#[cfg(feature = "postgres")]
+embed_migrations!();
+
+#[cfg(feature = "sqlite")]
+embed_migrations!("migrations-sqlite");
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let db = crate::connect()?;
+    embedded_migrations::run_with_output(&db, &mut std::io::stdout())?;
+
+    println!("=> Database is ready");
+
+    if !opt.claim && opt.claim_rest_for.is_none() {
+        return Ok(());
+    }
+
+    let loaded = accounts::load(&db, config, &None)?;
+    for (_, account) in &loaded {
+        let claimed = accounts::claim(&db, account)?;
+        println!("-> Linked {} statuses to account {} by author", claimed, account.name);
+    }
+
+    if let Some(ref name) = opt.claim_rest_for {
+        let account = match loaded.iter().find(|(_, account)| &account.name == name) {
+            Some((_, account)) => account,
+            None => return Err(OmeletteError::Config(format!("no account named {}", name))),
+        };
+
+        let claimed = accounts::claim_rest(&db, account)?;
+        println!("-> Linked {} more statuses to account {}", claimed, account.name);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+//! The omelette tools, run either as `omelette <tool>` or through their own
+//! `omelette-<tool>` binaries.
+
+use crate::config::Config;
+use crate::error::OmeletteError;
+use dotenv::dotenv;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+pub mod cleanup;
+pub mod daemon;
+pub mod delete;
+pub mod export;
+pub mod export_site;
+pub mod import;
+pub mod mediatise;
+pub mod migrate_db;
+pub mod policy;
+pub mod search;
+pub mod sync;
+pub mod twitter_archive;
+pub mod twitter_blocks;
+pub mod twitter_events;
+pub mod twitter_graph;
+pub mod twitter_hydrate;
+pub mod twitter_mutes;
+
+/// Options every tool takes.
+#[derive(StructOpt, Debug, Default)]
+pub struct Global {
+    /// Read from .env in working directory
+    #[structopt(long = "dotenv")]
+    pub dotenv: bool,
+
+    /// Config file to read instead of ~/.config/omelette/config.toml
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+}
+
+impl Global {
+    /// Loads .env if asked, then the config file, which only fills in what the
+    /// environment doesn’t already have.
+    pub fn load(&self) -> Result<Config, OmeletteError> {
+        if cfg!(debug_assertions) || self.dotenv {
+            println!("Loading .env");
+            dotenv().ok();
+        }
+
+        let config = match self.config {
+            Some(ref path) => Config::load(path)?,
+            None => match Config::default_path() {
+                Some(ref path) if path.exists() => Config::load(path)?,
+                _ => Config::default(),
+            },
+        };
+
+        config.apply_env();
+        Ok(config)
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "omelette")]
+pub enum Command {
+    /// Fetch new statuses from all sources
+    #[structopt(name = "sync")]
+    Sync(sync::Opt),
+
+    /// Delete statuses that have been requested for deletion
+    #[structopt(name = "delete")]
+    Delete(delete::Opt),
+
+    /// Request deletion of threads marked with #cleanup
+    #[structopt(name = "cleanup")]
+    Cleanup(cleanup::Opt),
+
+    /// Request deletion of statuses matching a policy file
+    #[structopt(name = "policy")]
+    Policy(policy::Opt),
+
+    /// Download media into the blob store
+    #[structopt(name = "mediatise")]
+    Mediatise(mediatise::Opt),
+
+    /// Prepare or upgrade the database
+    #[structopt(name = "migrate-db")]
+    MigrateDb(migrate_db::Opt),
+
+    /// Search the archive by text
+    #[structopt(name = "search")]
+    Search(search::Opt),
+
+    /// Dump the database to JSON Lines files
+    #[structopt(name = "export")]
+    Export(export::Opt),
+
+    /// Load a dump into the database
+    #[structopt(name = "import")]
+    Import(import::Opt),
+
+    /// Write the archive out as a static website
+    #[structopt(name = "export-site")]
+    ExportSite(export_site::Opt),
+
+    /// Run other tools on intervals
+    #[structopt(name = "daemon")]
+    Daemon(daemon::Opt),
+
+    /// Twitter-specific tools
+    #[structopt(name = "twitter")]
+    Twitter(TwitterCommand),
+}
+
+#[derive(StructOpt, Debug)]
+pub enum TwitterCommand {
+    /// Import a Twitter archive
+    #[structopt(name = "archive")]
+    Archive(twitter_archive::Opt),
+
+    /// Fetch the IDs of blocked users
+    #[structopt(name = "blocks")]
+    Blocks(twitter_blocks::Opt),
+
+    /// Fetch the IDs of muted users
+    #[structopt(name = "mutes")]
+    Mutes(twitter_mutes::Opt),
+
+    /// Snapshot and compare followers and followed users
+    #[structopt(name = "graph")]
+    Graph(twitter_graph::Opt),
+
+    /// Fill in slim tweets and users
+    #[structopt(name = "hydrate")]
+    Hydrate(twitter_hydrate::Opt),
+
+    /// Receive account activity events
+    #[structopt(name = "events")]
+    Events(twitter_events::Opt),
+}
+
+impl Command {
+    pub fn run(&self) -> Result<(), OmeletteError> {
+        match self {
+            Command::Sync(opt) => sync::run(opt),
+            Command::Delete(opt) => delete::run(opt),
+            Command::Cleanup(opt) => cleanup::run(opt),
+            Command::Policy(opt) => policy::run(opt),
+            Command::Mediatise(opt) => mediatise::run(opt),
+            Command::MigrateDb(opt) => migrate_db::run(opt),
+            Command::Search(opt) => search::run(opt),
+            Command::Export(opt) => export::run(opt),
+            Command::Import(opt) => import::run(opt),
+            Command::ExportSite(opt) => export_site::run(opt),
+            Command::Daemon(opt) => daemon::run(opt),
+            Command::Twitter(TwitterCommand::Archive(opt)) => twitter_archive::run(opt),
+            Command::Twitter(TwitterCommand::Blocks(opt)) => twitter_blocks::run(opt),
+            Command::Twitter(TwitterCommand::Mutes(opt)) => twitter_mutes::run(opt),
+            Command::Twitter(TwitterCommand::Graph(opt)) => twitter_graph::run(opt),
+            Command::Twitter(TwitterCommand::Hydrate(opt)) => twitter_hydrate::run(opt),
+            Command::Twitter(TwitterCommand::Events(opt)) => twitter_events::run(opt),
+        }
+    }
+
+    /// Runs the tool with a config that’s already loaded, and its environment
+    /// applied, ignoring the tool’s own `--config` and `--dotenv`.
+    pub fn run_with(&self, config: &Config) -> Result<(), OmeletteError> {
+        match self {
+            Command::Sync(opt) => sync::run_with(opt, config),
+            Command::Delete(opt) => delete::run_with(opt, config),
+            Command::Cleanup(opt) => cleanup::run_with(opt, config),
+            Command::Policy(opt) => policy::run_with(opt, config),
+            Command::Mediatise(opt) => mediatise::run_with(opt, config),
+            Command::MigrateDb(opt) => migrate_db::run_with(opt, config),
+            Command::Search(opt) => search::run_with(opt, config),
+            Command::Export(opt) => export::run_with(opt, config),
+            Command::Import(opt) => import::run_with(opt, config),
+            Command::ExportSite(opt) => export_site::run_with(opt, config),
+            Command::Daemon(opt) => daemon::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Archive(opt)) => twitter_archive::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Blocks(opt)) => twitter_blocks::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Mutes(opt)) => twitter_mutes::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Graph(opt)) => twitter_graph::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Hydrate(opt)) => twitter_hydrate::run_with(opt, config),
+            Command::Twitter(TwitterCommand::Events(opt)) => twitter_events::run_with(opt, config),
+        }
+    }
+}
+
+/// Entry point of the `omelette` binary.
+pub fn main() {
+    if let Err(err) = Command::from_args().run() {
+        err.exit();
+    }
+}
+
+/// Entry point of the `omelette-<tool>` binaries, which are aliases for
+/// `omelette <tool>`.
+pub fn alias<O: StructOpt>(run: fn(&O) -> Result<(), OmeletteError>) {
+    if let Err(err) = run(&O::from_args()) {
+        err.exit();
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::Utc;
+use crate::accounts;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::inserts::NewDeletion;
+use crate::models::Status;
+use crate::policy::Policy;
+use crate::sources::mastodon::Mastodon;
+use crate::types::Source;
+use crate::OmeletteError;
+use diesel::prelude::*;
+use std::{collections::HashSet, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Show what would be requested, but don’t write anything
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Path to the policy file [default: omelette-policy.toml]
+    #[structopt(long = "policy", parse(from_os_str))]
+    pub policy: Option<PathBuf>,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("policy");
+    let dry_run = tool.flag("dry-run", opt.dry_run)?;
+    let path = tool
+        .or("policy", &opt.policy)?
+        .unwrap_or_else(|| "omelette-policy.toml".into());
+
+    let policy = Policy::load(&path)?;
+    let min_age = match policy.min_age() {
+        Some(age) => age,
+        None => {
+            println!("=> No rules in policy, skip.");
+            return Ok(());
+        }
+    };
+
+    let db = crate::connect()?;
+    let now = Utc::now();
+
+    // Only our own statuses are up for deletion: liked statuses from others
+    // are in the table too. Reposts are ours, whoever wrote the original.
+    let owners = owners(config);
+    if owners.is_empty() {
+        return Err(OmeletteError::Config("no account with an id is configured".into()));
+    }
+
+    let cutoff = now - min_age;
+    let mut candidates: Vec<Status> = Vec::new();
+    for (src, uid) in &owners {
+        use crate::schema::statuses::dsl::*;
+
+        candidates.extend(
+            statuses
+                .filter(source.eq(src.clone()))
+                .filter(deleted_at.is_null())
+                .filter(is_repost.eq(true).or(source_author.like(format!("% ({})", uid))))
+                .filter(posted_at.lt(cutoff).or(reposted_at.lt(cutoff)))
+                .order_by(posted_at)
+                .load::<Status>(&db)?,
+        );
+    }
+
+    // Reposts come up once for each account on their source.
+    candidates.sort_by_key(|status| (status.posted_at, status.id));
+    candidates.dedup_by_key(|status| status.id);
+
+    let pending: HashSet<i32> = {
+        use crate::schema::deletions::dsl::*;
+        deletions
+            .select(status_id)
+            .filter(executed_at.is_null())
+            .load::<i32>(&db)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut pinned = HashSet::new();
+    if policy.keep.pinned {
+        for (settings, account) in accounts::load(&db, config, &None)? {
+            if account.source != Source::Mastodon {
+                continue;
+            }
+
+            match Mastodon::load_unboxed(&settings, account) {
+                Ok(md) => pinned.extend(md.pinned_ids()?),
+                Err(err) => println!(
+                    "!! Cannot load account {}, not keeping its pinned statuses: {:?}",
+                    settings.name, err
+                ),
+            }
+        }
+    }
+
+    println!(
+        "=> Checking {} statuses against {} rules",
+        candidates.len(),
+        policy.rules.len()
+    );
+
+    let mut deletes = Vec::new();
+    let mut kept = 0;
+    for status in &candidates {
+        if pending.contains(&status.id) {
+            continue;
+        }
+
+        let rule = match policy.first_match(status, now) {
+            Some(rule) => rule,
+            None => continue,
+        };
+
+        if policy.keep.keeps(status, &pinned) {
+            kept += 1;
+            continue;
+        }
+
+        let not_before = rule.not_before(status, now);
+        println!(
+            "-> Requesting deletion ({}): {:?} {} (#{}) not before {}\n“{}” — {}",
+            rule.name,
+            status.source,
+            status.source_id,
+            status.id,
+            not_before,
+            status.text,
+            status.posted_at
+        );
+
+        deletes.push(NewDeletion::sponsored(status, not_before, &policy.sponsor));
+    }
+
+    if kept > 0 {
+        println!(":: Kept {} statuses by keep-list", kept);
+    }
+
+    if deletes.is_empty() {
+        println!("=> No matching statuses, skip.");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("=> Would request deletion for {} statuses", deletes.len());
+        return Ok(());
+    }
+
+    println!("=> Requesting deletion for {} statuses", deletes.len());
+
+    use crate::schema::deletions::dsl::*;
+    diesel::insert_into(deletions)
+        .values(&deletes)
+        .execute(&db)?;
+
+    Ok(())
+}
+
+/// The source ID of each configured account, to tell our own statuses apart
+/// from others’ (e.g. liked) ones.
+///
+/// Own statuses have a `source_author` ending in ` (<id>)`, except reposts.
+fn owners(config: &Config) -> Vec<(Source, String)> {
+    let mut owners = Vec::new();
+    for account in config.accounts() {
+        match account.id {
+            Some(uid) => owners.push((account.source, uid)),
+            None => println!("-- No ID for account {}, skipping its statuses", account.name),
+        }
+    }
+
+    owners
+}
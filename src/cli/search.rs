@@ -0,0 +1,291 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::cli::Global;
+use crate::config::Config;
+use crate::models::{lower, Entity, Status, Url};
+use crate::types::Source;
+use crate::OmeletteError;
+use diesel::dsl::{not, sql};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float4, Text};
+use serde::Serialize;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Search terms: words, "quoted phrases", `or`, and -excluded words
+    pub query: Option<String>,
+
+    /// Only statuses posted on or after this date (YYYY-MM-DD or RFC 3339)
+    #[structopt(long = "since", parse(try_from_str = "parse_date"))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only statuses posted before this date (YYYY-MM-DD or RFC 3339)
+    #[structopt(long = "until", parse(try_from_str = "parse_date"))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only statuses from this source (twitter or mastodon)
+    #[structopt(long = "source")]
+    pub source: Option<Source>,
+
+    /// Only reposts (true) or only non-reposts (false)
+    #[structopt(long = "is-repost")]
+    pub is_repost: Option<bool>,
+
+    /// Only liked/favourited (true) or only non-liked (false) statuses
+    #[structopt(long = "is-marked")]
+    pub is_marked: Option<bool>,
+
+    /// Only public (true) or only non-public (false) statuses
+    #[structopt(long = "public")]
+    pub public: Option<bool>,
+
+    /// Only statuses with (true) or without (false) media
+    #[structopt(long = "has-media")]
+    pub has_media: Option<bool>,
+
+    /// Only statuses linking to this domain or its subdomains (e.g. example.com)
+    #[structopt(long = "domain")]
+    pub domain: Option<String>,
+
+    /// Only statuses with this hashtag, with or without the #
+    #[structopt(long = "hashtag")]
+    pub hashtag: Option<String>,
+
+    /// Only statuses mentioning this user, by screen name with or without the @
+    #[structopt(long = "mention")]
+    pub mention: Option<String>,
+
+    /// Include deleted statuses
+    #[structopt(long = "deleted")]
+    pub deleted: bool,
+
+    /// Maximum number of results [default: 50]
+    #[structopt(long = "limit")]
+    pub limit: Option<i64>,
+
+    /// Output results as JSON, one array
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+        .map_err(|err| format!("{}: {}", s, err))
+}
+
+#[derive(Debug, Serialize)]
+struct Hit {
+    id: i32,
+    source: String,
+    source_id: String,
+    author: String,
+    posted_at: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
+    is_repost: bool,
+    is_marked: bool,
+    public: bool,
+    text: String,
+    media: Vec<String>,
+    links: Vec<String>,
+}
+
+impl Hit {
+    fn new(status: Status, entities: Vec<Entity>, links: Vec<Url>) -> Self {
+        Self {
+            id: status.id,
+            source: format!("{:?}", status.source).to_lowercase(),
+            source_id: status.source_id,
+            author: status.source_author,
+            posted_at: status.posted_at,
+            deleted_at: status.deleted_at,
+            is_repost: status.is_repost,
+            is_marked: status.is_marked,
+            public: status.public,
+            text: status.text,
+            media: entities.into_iter().map(|ent| ent.source_url).collect(),
+            links: links
+                .into_iter()
+                .map(|link| link.expanded_url.unwrap_or(link.url))
+                .collect(),
+        }
+    }
+
+    fn flags(&self) -> String {
+        format!(
+            "{}{}{}{}{}",
+            if self.is_repost { 'R' } else { '-' },
+            if self.is_marked { 'M' } else { '-' },
+            if self.public { 'P' } else { '-' },
+            if self.media.is_empty() { '-' } else { 'E' },
+            if self.deleted_at.is_some() { 'D' } else { '-' },
+        )
+    }
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let limit = config.tool("search").or("limit", &opt.limit)?.unwrap_or(50);
+
+    if cfg!(feature = "sqlite") {
+        return Err(OmeletteError::Config(
+            "search relies on Postgres full-text search, and isn’t available with SQLite".into(),
+        ));
+    }
+
+    let db = crate::connect()?;
+
+    let found: Vec<Status> = {
+        use crate::schema::{entities, hashtags, mentions, urls};
+        use crate::schema::statuses::dsl::*;
+
+        let mut query = statuses.into_boxed();
+
+        // The text_search column is generated by Postgres and kept out of the
+        // schema (see schema.patch), so it's queried with raw SQL.
+        if let Some(ref terms) = opt.query {
+            query = query
+                .filter(
+                    sql::<Bool>("text_search @@ websearch_to_tsquery('simple', ")
+                        .bind::<Text, _>(terms.clone())
+                        .sql(")"),
+                )
+                .order_by(
+                    sql::<Float4>("ts_rank(text_search, websearch_to_tsquery('simple', ")
+                        .bind::<Text, _>(terms.clone())
+                        .sql(")) DESC"),
+                );
+        }
+
+        query = query.then_order_by(posted_at.desc());
+
+        if !opt.deleted {
+            query = query.filter(deleted_at.is_null());
+        }
+        if let Some(date) = opt.since {
+            query = query.filter(posted_at.ge(date));
+        }
+        if let Some(date) = opt.until {
+            query = query.filter(posted_at.lt(date));
+        }
+        if let Some(ref src) = opt.source {
+            query = query.filter(source.eq(src.clone()));
+        }
+        if let Some(flag) = opt.is_repost {
+            query = query.filter(is_repost.eq(flag));
+        }
+        if let Some(flag) = opt.is_marked {
+            query = query.filter(is_marked.eq(flag));
+        }
+        if let Some(flag) = opt.public {
+            query = query.filter(public.eq(flag));
+        }
+        match opt.has_media {
+            Some(true) => {
+                query = query.filter(id.eq_any(entities::table.select(entities::status_id)))
+            }
+            Some(false) => {
+                query = query.filter(not(id.eq_any(entities::table.select(entities::status_id))))
+            }
+            None => {}
+        }
+        // Stored the same way, see `NewUrl`.
+        if let Some(ref d) = opt.domain {
+            let d = d.to_lowercase();
+            let d = d.trim_start_matches("www.");
+            query = query.filter(
+                id.eq_any(
+                    urls::table
+                        .select(urls::status_id)
+                        .filter(urls::domain.eq(d).or(urls::domain.like(format!("%.{}", d)))),
+                ),
+            );
+        }
+        if let Some(ref tag) = opt.hashtag {
+            let tag = tag.trim_start_matches('#').to_lowercase();
+            query = query.filter(
+                id.eq_any(
+                    hashtags::table
+                        .select(hashtags::status_id)
+                        .filter(lower(hashtags::text).eq(tag)),
+                ),
+            );
+        }
+        if let Some(ref name) = opt.mention {
+            let name = name.trim_start_matches('@').to_lowercase();
+            query = query.filter(
+                id.eq_any(
+                    mentions::table
+                        .select(mentions::status_id)
+                        .filter(lower(mentions::screen_name).eq(name)),
+                ),
+            );
+        }
+
+        query.limit(limit).load(&db)?
+    };
+
+    let media = Entity::belonging_to(&found)
+        .load::<Entity>(&db)?
+        .grouped_by(&found);
+
+    let links = Url::belonging_to(&found)
+        .order_by(crate::schema::urls::start_index)
+        .load::<Url>(&db)?
+        .grouped_by(&found);
+
+    let hits: Vec<Hit> = found
+        .into_iter()
+        .zip(media)
+        .zip(links)
+        .map(|((status, ents), links)| Hit::new(status, ents, links))
+        .collect();
+
+    if opt.json {
+        let json = serde_json::to_string_pretty(&hits)
+            .map_err(|err| OmeletteError::Data(format!("cannot serialise results: {}", err)))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("=> No matching statuses.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<10} {:<8} {:<20} {:<5} {}",
+        "posted", "source", "id", "flags", "text"
+    );
+    for hit in &hits {
+        let line = hit.text.lines().next().unwrap_or("");
+        let text: String = if line.chars().count() > 80 {
+            line.chars().take(79).chain(Some('…')).collect()
+        } else {
+            line.into()
+        };
+
+        println!(
+            "{:<10} {:<8} {:<20} {:<5} {}",
+            hit.posted_at.format("%Y-%m-%d"),
+            hit.source,
+            hit.source_id,
+            hit.flags(),
+            text
+        );
+    }
+
+    println!("=> {} statuses", hits.len());
+    Ok(())
+}
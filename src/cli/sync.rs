@@ -0,0 +1,95 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::sources::{all_available, state};
+use crate::{DbConnection, OmeletteError};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only sync this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Show the state of each sync pass instead of syncing
+    #[structopt(long = "status")]
+    pub status: bool,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("sync").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    if opt.status {
+        return print_status(&db);
+    }
+
+    let sources = all_available(&db, config, &account)?;
+
+    let mut successes = 0;
+    let mut failure = None;
+    for (name, source) in &sources {
+        println!("\n=> Syncing {} ({:?})", name, source.account().source);
+
+        match source.sync(&db) {
+            Ok(()) => successes += 1,
+            Err(err) => {
+                println!("!! Error syncing {}: {}", name, err);
+                failure = failure.or(Some(err));
+            }
+        }
+    }
+
+    println!("\n=> Synced {} accounts.", successes);
+
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn print_status(db: &DbConnection) -> Result<(), OmeletteError> {
+    let states = state::all(db)?;
+    if states.is_empty() {
+        println!("=> Nothing has been synced yet.");
+        return Ok(());
+    }
+
+    for (st, account) in &states {
+        let fmt = |t: &Option<chrono::DateTime<chrono::Utc>>| {
+            t.map(|t| t.to_rfc3339()).unwrap_or("never".into())
+        };
+
+        match account {
+            Some(account) => println!("\n=> {} ({:?}) {}", account.name, st.source, st.kind),
+            None => println!("\n=> {:?} {} (no account)", st.source, st.kind),
+        }
+        println!(":: Last run:\t\t{}", fmt(&st.last_run_at));
+        println!(":: Last success:\t{}", fmt(&st.last_success_at));
+        println!(
+            ":: Synced to:\t\t{}{}",
+            st.synced_to.clone().unwrap_or("-".into()),
+            if st.done { " (done)" } else { "" }
+        );
+        if let Some(ref cursor) = st.cursor {
+            println!(":: Interrupted at:\t{}", cursor);
+        }
+        println!(
+            ":: Last run counts:\t{} fetched, {} new",
+            st.fetched_count, st.inserted_count
+        );
+        if let Some(ref err) = st.last_error {
+            println!("!! Last error: {}", err);
+        }
+    }
+
+    Ok(())
+}
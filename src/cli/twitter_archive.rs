@@ -0,0 +1,458 @@
+use crate::archive::{self, Account, Tweet, Wrapped};
+use crate::cli::Global;
+use crate::config::Config;
+use crate::{DbConnection, OmeletteError};
+use diesel::prelude::*;
+use std::{fs::File, io::Read, path::{Path, PathBuf}};
+use structopt::StructOpt;
+use zip::{read::ZipArchive, result::ZipError};
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Archive file. Either a CSV or a ZIP (old format with a tweets.csv, or new format)
+    #[structopt(name = "FILE", parse(from_os_str))]
+    pub file: Option<PathBuf>,
+
+    /// Where the blob store is located (for media in new format archives) [default: ./omelette/store]
+    #[structopt(long = "store", parse(from_os_str))]
+    pub store: Option<PathBuf>,
+
+    /// Don’t import media files from new format archives
+    #[structopt(long = "no-media")]
+    pub no_media: bool,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    use std::ffi::OsStr;
+    use tree_magic::match_filepath;
+
+    let tool = config.tool("twitter-archive");
+    let no_media = tool.flag("no-media", opt.no_media)?;
+    let store = config.store(&tool, &opt.store)?;
+
+    let db = crate::connect()?;
+
+    let path = match opt.file {
+        Some(ref path) => path,
+        None => return Err(OmeletteError::Config("missing path to archive file".into())),
+    };
+    let ext_csv = path.extension() == Some(OsStr::new("csv"));
+    let is_csv = match_filepath("text/csv", path);
+    let is_zip = match_filepath("application/zip", path);
+
+    if !is_zip && !is_csv && !ext_csv {
+        return Err(OmeletteError::Data("file is neither a zip nor a csv".into()));
+    }
+
+    let file = File::open(path)?;
+
+    if is_zip {
+        let mut archive = ZipArchive::new(file).map_err(bad_zip)?;
+        let is_old_format = archive.by_name("tweets.csv").is_ok();
+
+        if is_old_format {
+            let entry = archive.by_name("tweets.csv").map_err(bad_zip)?;
+            slim_load(&db, csv::Reader::from_reader(entry))?;
+        } else {
+            full_load(&db, &mut archive)?;
+            if !no_media {
+                media_load(&db, &mut archive, &store)?;
+            }
+        }
+    } else {
+        slim_load(&db, csv::Reader::from_reader(file))?;
+    }
+
+    Ok(())
+}
+
+fn bad_zip(err: ZipError) -> OmeletteError {
+    OmeletteError::Data(format!("cannot read zip: {}", err))
+}
+
+fn slim_load<R: Read>(
+    conn: &DbConnection,
+    csv_reader: csv::Reader<R>,
+) -> Result<Vec<i32>, OmeletteError> {
+    use chrono::{TimeZone, Utc};
+    use crate::db::insert_new_statuses;
+    use crate::inserts::NewStatus;
+    use crate::types::{IntermediarySource, Source};
+    use regex::Regex;
+
+    let source_app_re = Regex::new("^<a href=\"([^\"]+)\".*>(.+)</a>$").unwrap();
+
+    let mut ids = Vec::new();
+    let mut bag = Vec::with_capacity(1000);
+    let mut batch = 0;
+
+    println!("\n=> Loading from archive in batches of 1000");
+    let mut csv_reader = csv_reader;
+    for record in csv_reader.records() {
+        let record = record.map_err(|err| OmeletteError::Data(format!("cannot parse CSV: {}", err)))?;
+
+        let tweet_id = record[0].into();
+        let in_reply_to_status_id = record[1].into();
+
+        // Without the reply user name, can’t really write out the usual format.
+        // So we'll get to that field (2) during another pass.
+
+        let timestamp = Utc
+            .datetime_from_str(&record[3], "%Y-%m-%d %H:%M:%S %z")
+            .map_err(|err| OmeletteError::Data(format!("cannot parse date {:?}: {}", &record[3], err)))?;
+        let app = source_app_re
+            .captures(&record[4])
+            .map(|cap| format!("{} <{}>", &cap[2], &cap[1]))
+            .unwrap_or("".into());
+        let content = record[5].into();
+
+        // The archive format confuses retweets and quoted tweets, so we can't
+        // trust the retweet-related fields in there (6-8), and we'll get the
+        // entities (field 9) in a latter pass.
+
+        let status = NewStatus {
+            text: content,
+            author_id: None,
+            geolocation_lat: None,
+            geolocation_lon: None,
+            posted_at: timestamp,
+            fetched_at: Utc::now(),
+            fetched_via: Some(IntermediarySource::TwitterArchive),
+            deleted_at: None,
+            is_repost: false,
+            reposted_at: None,
+            is_marked: false,
+            marked_at: None,
+            source: Source::Twitter,
+            source_id: tweet_id,
+            source_author: crate::SLIM_MARK.into(),
+            source_app: app,
+            in_reply_to_status: Some(in_reply_to_status_id),
+            in_reply_to_user: None,
+            quoting_status: None,
+            // Not in the CSV: private until hydrated.
+            public: false,
+            account_id: None,
+        };
+
+        bag.push(status);
+        if bag.len() >= 1000 {
+            batch += 1;
+            print!("-> Saving batch {}... ", batch);
+
+            let results = insert_new_statuses(conn, &bag)?;
+
+            ids.extend(results.iter().map(|status| status.id));
+            bag.truncate(0);
+            println!("done. {} new tweets loaded so far", ids.len());
+        }
+    }
+
+    // store whatever remains
+    if !bag.is_empty() {
+        print!("-> Saving last batch ({} entries)... ", bag.len());
+        let results = insert_new_statuses(conn, &bag)?;
+
+        ids.extend(results.iter().map(|status| status.id));
+        println!("done.");
+    }
+
+    println!(
+        "=> {} entries processed and {} new tweets stored.",
+        batch * 1000 + bag.len(),
+        ids.len()
+    );
+    Ok(ids)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String, OmeletteError> {
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .map_err(bad_zip)?
+        .read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn full_load(conn: &DbConnection, archive: &mut ZipArchive<File>) -> Result<Vec<i32>, OmeletteError> {
+    let mut names: Vec<String> = (0..archive.len())
+        .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
+        .collect();
+    names.sort();
+
+    let mut account: Account = match names.iter().find(|n| archive::is_account_file(n)) {
+        None => {
+            return Err(OmeletteError::Data(
+                "file is not a twitter archive (no account.js)".into(),
+            ))
+        }
+        Some(name) => {
+            let accounts: Vec<Wrapped<Account>> = archive::parse_js(&read_entry(archive, name)?)?;
+            accounts
+                .into_iter()
+                .next()
+                .ok_or_else(|| OmeletteError::Data("no account in account.js".into()))?
+                .into_inner()
+        }
+    };
+
+    let parts: Vec<&String> = names.iter().filter(|n| archive::is_tweets_file(n)).collect();
+    if parts.is_empty() {
+        return Err(OmeletteError::Data("file is not a twitter archive (no tweets)".into()));
+    }
+
+    println!(
+        "\n=> Loading @{}’s tweets from {} archive file(s) in batches of 1000",
+        account.username,
+        parts.len()
+    );
+
+    // Link the tweets to the account if it’s registered, otherwise that’s for
+    // `omelette-migrate-db --claim` to do once it is.
+    let owner = crate::accounts::find(conn, &crate::types::Source::Twitter, &account.account_id)?
+        .map(|acc| acc.id);
+
+    // The archive’s own tweets are all by its account. It’s stored by ID only
+    // if not known yet, for hydrate to fill in.
+    let author = archive_author(conn, &account)?;
+
+    // Older archives don’t say whether the account is protected, but a sync
+    // may have told us already.
+    if account.protected.is_none() {
+        account.protected = archive_protected(conn, &account)?;
+    }
+    if account.protected != Some(false) {
+        println!("~~ Account may be protected, storing its tweets as private");
+    }
+
+    let mut ids = Vec::new();
+    let mut total = 0;
+    let mut batch = 0;
+    for part in parts {
+        println!("-> Reading {}", part);
+        let tweets: Vec<Tweet> = archive::parse_js::<Vec<Wrapped<Tweet>>>(&read_entry(archive, part)?)?
+            .into_iter()
+            .map(|w| w.into_inner())
+            .collect();
+
+        for chunk in tweets.chunks(1000) {
+            batch += 1;
+            print!("-> Saving batch {}... ", batch);
+
+            let mut results = full_batch(conn, &account, owner, author, chunk)?;
+            total += chunk.len();
+            ids.append(&mut results);
+            println!("done. {} tweets loaded so far", ids.len());
+        }
+    }
+
+    println!(
+        "=> {} entries processed and {} tweets stored.",
+        total,
+        ids.len()
+    );
+    Ok(ids)
+}
+
+fn archive_author(conn: &DbConnection, account: &Account) -> Result<Option<i32>, OmeletteError> {
+    use crate::inserts::NewTwitterUserID;
+    use crate::schema::twitter_users::dsl::*;
+
+    let uid: u64 = match account.account_id.parse() {
+        Ok(uid) => uid,
+        Err(_) => return Ok(None),
+    };
+    let user: NewTwitterUserID = uid.into();
+    crate::insert_or_ignore!(twitter_users, &user, source_id).execute(conn)?;
+
+    Ok(twitter_users
+        .select(id)
+        .filter(source_id.eq(&account.account_id))
+        .first(conn)
+        .optional()?)
+}
+
+fn archive_protected(conn: &DbConnection, account: &Account) -> Result<Option<bool>, OmeletteError> {
+    use crate::schema::twitter_users::dsl::*;
+
+    Ok(twitter_users
+        .select(is_protected)
+        .filter(source_id.eq(&account.account_id))
+        .filter(screen_name.ne(crate::SLIM_MARK))
+        .first(conn)
+        .optional()?)
+}
+
+fn full_batch(
+    conn: &DbConnection,
+    account: &Account,
+    owner: Option<i32>,
+    author: Option<i32>,
+    tweets: &[Tweet],
+) -> Result<Vec<i32>, OmeletteError> {
+    use crate::inserts::{NewEntity, NewEntityVariant, NewStatus, TextEntities};
+    use crate::types::Source;
+    use std::collections::HashMap;
+
+    let bag: Vec<NewStatus> = tweets
+        .iter()
+        .map(|tweet| NewStatus {
+            account_id: owner,
+            // Retweets are by someone else, and linked when hydrated.
+            author_id: if tweet.is_retweet() { None } else { author },
+            ..NewStatus::from_archive(tweet, account)
+        })
+        .collect();
+
+    let mut entitybag: HashMap<String, Vec<NewEntity>> = HashMap::new();
+    let mut variantbag = HashMap::new();
+    let mut textbag = HashMap::new();
+    for tweet in tweets {
+        if let Some(ref ents) = tweet.extended_entities {
+            entitybag.insert(tweet.id_str.clone(), NewEntity::from_archive(ents));
+            variantbag.extend(NewEntityVariant::from_archive(ents));
+        }
+
+        // Retweets get theirs when hydrated into the original.
+        if !tweet.is_retweet() {
+            textbag.insert(tweet.id_str.clone(), TextEntities::from_archive(tweet));
+        }
+    }
+
+    // Save statuses and entities together so an interruption doesn’t leave
+    // statuses without their media.
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+        use crate::schema::statuses::dsl::*;
+
+        let mut stored: Vec<(i32, String)> = crate::db::insert_new_statuses(conn, &bag)?
+            .into_iter()
+            .map(|status| (status.id, status.source_id))
+            .collect();
+
+        // Tweets already there from an old-format import or a partial sync
+        // are slim: fill them in, but leave any fuller copy alone.
+        let existing: Vec<(i32, String)> = statuses
+            .select((id, source_id))
+            .filter(source.eq(Source::Twitter))
+            .filter(source_id.eq_any(bag.iter().map(|s| s.source_id.clone())))
+            .filter(source_author.eq(crate::slim()))
+            .load(conn)?;
+
+        for (sid, ssource_id) in existing {
+            if stored.iter().any(|(i, _)| *i == sid) {
+                continue;
+            }
+
+            if let Some(insert) = bag.iter().find(|s| s.source_id == ssource_id) {
+                diesel::update(statuses.find(sid)).set(insert).execute(conn)?;
+                stored.push((sid, ssource_id));
+            }
+        }
+
+        let mut entitysack = Vec::with_capacity(entitybag.len() * 4);
+        for (sid, ssource_id) in &stored {
+            if let Some(ents) = entitybag.get(ssource_id) {
+                for ent in ents {
+                    let mut ent = ent.clone();
+                    ent.status_id = *sid;
+                    entitysack.push(ent);
+                }
+            }
+        }
+
+        {
+            use crate::schema::entities::dsl::*;
+            crate::insert_or_ignore!(entities, &entitysack, (source, source_id)).execute(conn)?;
+        }
+        crate::db::insert_variants(conn, &Source::Twitter, &variantbag)?;
+        crate::db::insert_text_entities(conn, &textbag)?;
+
+        Ok(stored.into_iter().map(|(sid, _)| sid).collect())
+    })?)
+}
+
+fn media_load(
+    conn: &DbConnection,
+    archive: &mut ZipArchive<File>,
+    store: &Path,
+) -> Result<(), OmeletteError> {
+    use blobstore::{BlobStore, Store};
+    use crate::models::Entity;
+    use crate::types::Source;
+
+    let files: Vec<(String, String, String)> = (0..archive.len())
+        .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
+        .filter_map(|name| archive::media_file(&name).map(|(tid, key)| (name, tid, key)))
+        .collect();
+
+    if files.is_empty() {
+        println!("=> No media in archive, skip.");
+        return Ok(());
+    }
+
+    println!("\n=> Importing {} media files from archive", files.len());
+
+    let bs = BlobStore::new(store.to_string_lossy().into());
+
+    let mut successes = 0;
+    for (name, tweet_id, key) in &files {
+        let candidates: Vec<Entity> = {
+            use crate::schema::entities::dsl::*;
+            use crate::schema::statuses;
+
+            entities
+                .inner_join(statuses::table)
+                .select(crate::schema::entities::all_columns)
+                .filter(statuses::source.eq(Source::Twitter))
+                .filter(statuses::source_id.eq(tweet_id))
+                .filter(blob_hash.is_null())
+                .load(conn)?
+        };
+
+        // The key is the basename of the media URL, but fall back to the only
+        // entity of the tweet if nothing matches (e.g. for videos).
+        let entity = candidates
+            .iter()
+            .find(|e| e.source_url.contains(key.as_str()))
+            .or(if candidates.len() == 1 {
+                candidates.first()
+            } else {
+                None
+            });
+
+        let entity = match entity {
+            Some(e) => e,
+            None => continue,
+        };
+
+        let mut file = archive.by_name(name).map_err(bad_zip)?;
+        match bs.put(&mut file) {
+            Err(err) => println!("!! Error storing {}: {:?}", name, err),
+            Ok(hash) => {
+                use crate::schema::entities::dsl::*;
+                match diesel::update(entities.find(entity.id))
+                    .set(blob_hash.eq(&hash))
+                    .execute(conn)
+                {
+                    Err(err) => println!("!! Error recording {}: {:?}", name, err),
+                    Ok(_) => successes += 1,
+                }
+            }
+        }
+    }
+
+    println!(
+        "=> Stored {} (out of {}) media files from archive",
+        successes,
+        files.len()
+    );
+    Ok(())
+}
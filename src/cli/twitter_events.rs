@@ -0,0 +1,882 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::sources::twitter::Twitter;
+use crate::types::Source;
+use crate::{DbConnection, OmeletteError};
+use diesel::prelude::*;
+use egg_mode::{tweet::Tweet, user::TwitterUser as EggUser};
+use futures::future;
+use hmac::{Hmac, Mac};
+use hyper::rt::{self, Future, Stream};
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Account whose app the webhook is registered with [default: the only one]
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Address to listen on [default: 0.0.0.0:8080]
+    #[structopt(long = "listen")]
+    pub listen: Option<SocketAddr>,
+
+    /// Don’t check the signature of incoming events (for testing with recorded payloads)
+    #[structopt(long = "no-verify")]
+    pub no_verify: bool,
+
+    /// Tool to run once Twitter has checked connectivity (first CRC challenge)
+    #[structopt(long = "on-boot", name = "BOOT TOOL")]
+    pub on_boot: Option<String>,
+
+    /// Tool to run when a tweet is created
+    #[structopt(long = "on-create", name = "CREATE TOOL")]
+    pub on_create: Option<String>,
+
+    /// Tool to run when a tweet is deleted
+    #[structopt(long = "on-delete", name = "DELETE TOOL")]
+    pub on_delete: Option<String>,
+
+    /// Tool to run when a tweet is liked
+    #[structopt(long = "on-favorite", name = "FAVORITE TOOL")]
+    pub on_favorite: Option<String>,
+
+    /// Tool to run when a user is blocked
+    #[structopt(long = "on-block", name = "BLOCK TOOL")]
+    pub on_block: Option<String>,
+
+    /// Tool to run when a user is unblocked
+    #[structopt(long = "on-unblock", name = "UNBLOCK TOOL")]
+    pub on_unblock: Option<String>,
+
+    /// Tool to run when a user is muted
+    #[structopt(long = "on-mute", name = "MUTE TOOL")]
+    pub on_mute: Option<String>,
+
+    /// Tool to run when a user is unmuted
+    #[structopt(long = "on-unmute", name = "UNMUTE TOOL")]
+    pub on_unmute: Option<String>,
+}
+
+struct State {
+    db: Mutex<DbConnection>,
+    /// Keyed with the app’s consumer secret.
+    key: Hmac<Sha256>,
+    verify: bool,
+    hooks: HashMap<&'static str, String>,
+    booted: AtomicBool,
+}
+
+type ResponseFuture = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let tool = config.tool("twitter-events");
+    let account = tool.or("account", &opt.account)?;
+    let listen = tool
+        .or("listen", &opt.listen)?
+        .unwrap_or_else(|| ([0, 0, 0, 0], 8080).into());
+
+    let mut hooks = HashMap::new();
+    for (event, key, tool_opt) in vec![
+        ("boot", "on-boot", &opt.on_boot),
+        ("create", "on-create", &opt.on_create),
+        ("delete", "on-delete", &opt.on_delete),
+        ("favorite", "on-favorite", &opt.on_favorite),
+        ("block", "on-block", &opt.on_block),
+        ("unblock", "on-unblock", &opt.on_unblock),
+        ("mute", "on-mute", &opt.on_mute),
+        ("unmute", "on-unmute", &opt.on_unmute),
+    ] {
+        if let Some(hook) = tool.or(key, tool_opt)? {
+            hooks.insert(event, hook);
+        }
+    }
+
+    let db = crate::connect()?;
+    let key = hmac_key(&app_secret(&db, config, &account)?)?;
+
+    let state = Arc::new(State {
+        db: Mutex::new(db),
+        key,
+        verify: !tool.flag("no-verify", opt.no_verify)?,
+        hooks,
+        booted: AtomicBool::new(false),
+    });
+
+    let new_service = move || {
+        let state = state.clone();
+        service_fn(move |req| handle(state.clone(), req))
+    };
+
+    let server = Server::bind(&listen)
+        .serve(new_service)
+        .map_err(|err| println!("!! Server error: {:?}", err));
+
+    println!("=> Listening for account activity events on http://{}", listen);
+    println!("-- Register the public URL for this server as a webhook on your account to start receiving events.");
+    rt::run(server);
+    Ok(())
+}
+
+/// Events are signed with the secret of the app the webhook belongs to, which
+/// all the accounts subscribed to it share.
+fn app_secret(
+    conn: &DbConnection,
+    config: &crate::config::Config,
+    only: &Option<String>,
+) -> Result<String, OmeletteError> {
+    let mut secrets: Vec<String> = crate::accounts::load(conn, config, only)?
+        .into_iter()
+        .filter(|(account, _)| account.source == Source::Twitter)
+        .filter_map(|(account, _)| account.consumer_secret)
+        .collect();
+    secrets.sort();
+    secrets.dedup();
+
+    match secrets.len() {
+        0 => Err(OmeletteError::Config("no twitter account with a consumer_secret".into())),
+        1 => Ok(secrets.remove(0)),
+        _ => Err(OmeletteError::Config(
+            "twitter accounts are on different apps, pick one with --account".into(),
+        )),
+    }
+}
+
+fn handle(state: Arc<State>, req: Request<Body>) -> ResponseFuture {
+    match *req.method() {
+        Method::GET => Box::new(future::ok(crc(&state, &req))),
+        Method::POST => {
+            let signature = req
+                .headers()
+                .get("x-twitter-webhooks-signature")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+
+            Box::new(req.into_body().concat2().map(move |body| {
+                if state.verify && !verify(&state.key, &body, &signature) {
+                    println!("!! Bad signature on event, ignoring");
+                    return respond(StatusCode::FORBIDDEN, Body::empty());
+                }
+
+                match serde_json::from_slice::<Value>(&body) {
+                    Err(err) => {
+                        println!("!! Cannot parse event: {:?}", err);
+                        respond(StatusCode::BAD_REQUEST, Body::empty())
+                    }
+                    Ok(payload) => {
+                        // Twitter retries events that fail, and storing them
+                        // again is harmless, so errors are only reported.
+                        let events = match state.db.lock() {
+                            Ok(db) => process(&db, &payload),
+                            Err(_) => Err(OmeletteError::Data("DB lock poisoned".into())),
+                        };
+
+                        let events = match events {
+                            Ok(events) => events,
+                            Err(err) => {
+                                println!("!! Cannot store event: {}", err);
+                                return respond(StatusCode::INTERNAL_SERVER_ERROR, Body::empty());
+                            }
+                        };
+
+                        for (event, data) in events {
+                            run_hook(&state, event, &data);
+                        }
+
+                        respond(StatusCode::OK, Body::empty())
+                    }
+                }
+            }))
+        }
+        _ => Box::new(future::ok(respond(StatusCode::METHOD_NOT_ALLOWED, Body::empty()))),
+    }
+}
+
+fn respond(status: StatusCode, body: Body) -> Response<Body> {
+    let mut resp = Response::new(body);
+    *resp.status_mut() = status;
+    resp
+}
+
+fn hmac_key(secret: &str) -> Result<Hmac<Sha256>, OmeletteError> {
+    Hmac::<Sha256>::new_varkey(secret.as_bytes())
+        .map_err(|_| OmeletteError::Config("consumer_secret can’t be used as an HMAC key".into()))
+}
+
+fn hmac(key: &Hmac<Sha256>, data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = key.clone();
+    mac.input(data);
+    mac
+}
+
+fn verify(key: &Hmac<Sha256>, body: &[u8], signature: &Option<String>) -> bool {
+    match signature
+        .as_ref()
+        .and_then(|s| base64::decode(s.trim_start_matches("sha256=")).ok())
+    {
+        Some(given) => hmac(key, body).verify(&given).is_ok(),
+        None => false,
+    }
+}
+
+/// Answers the Challenge-Response Check Twitter does on registration and then
+/// periodically, which is how we know the webhook is reachable.
+fn crc(state: &State, req: &Request<Body>) -> Response<Body> {
+    let token = req.uri().query().and_then(|q| {
+        url::form_urlencoded::parse(q.as_bytes())
+            .find(|(k, _)| k == "crc_token")
+            .map(|(_, v)| v.into_owned())
+    });
+
+    let token = match token {
+        Some(t) => t,
+        None => return respond(StatusCode::BAD_REQUEST, Body::empty()),
+    };
+
+    println!("-> Answering CRC challenge");
+    let response = json!({
+        "response_token": format!("sha256={}", base64::encode(&hmac(&state.key, token.as_bytes()).result().code()))
+    });
+
+    if !state.booted.swap(true, Ordering::SeqCst) {
+        println!("=> Connectivity checked by Twitter");
+        run_hook(state, "boot", &json!({}));
+    }
+
+    let mut resp = respond(StatusCode::OK, Body::from(response.to_string()));
+    resp.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+fn run_hook(state: &State, event: &str, data: &Value) {
+    let tool = match state.hooks.get(event) {
+        Some(tool) => tool,
+        None => return,
+    };
+
+    println!("-> Running {} for {} event", tool, event);
+    let child = Command::new(tool)
+        .env("OMELETTE_STREAM_EVENT", event)
+        .stdin(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(c) => c,
+        Err(err) => {
+            println!("!! Cannot run {}: {:?}", tool, err);
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(err) = stdin.write_all(data.to_string().as_bytes()) {
+            println!("!! Cannot pipe event to {}: {:?}", tool, err);
+        }
+    }
+
+    // Don’t hold up the response to Twitter, but don’t leave zombies around.
+    let tool = tool.clone();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => println!("!! {} exited with {}", tool, status),
+        Err(err) => println!("!! Cannot wait on {}: {:?}", tool, err),
+        _ => {}
+    });
+}
+
+/// Stores what we can from the payload and returns the events to hook on.
+fn process(
+    conn: &DbConnection,
+    payload: &Value,
+) -> Result<Vec<(&'static str, Value)>, OmeletteError> {
+    let for_user = payload["for_user_id"].as_str().unwrap_or("").to_string();
+    let mut events = Vec::new();
+
+    // Events are delivered for every subscribed user, the tweets they bring
+    // belong to that account if it’s one we know.
+    let owner = crate::accounts::find(conn, &Source::Twitter, &for_user)?.map(|account| account.id);
+
+    if let Some(tweets) = payload["tweet_create_events"].as_array() {
+        for data in tweets {
+            match serde_json::from_value::<Tweet>(data.clone()) {
+                Err(err) => println!("!! Cannot parse tweet: {:?}", err),
+                Ok(tweet) => {
+                    // Mentions and replies from others come through too, but
+                    // like sync we only keep our own timeline.
+                    if tweet.user.as_ref().map(|u| format!("{}", u.id)) == Some(for_user.clone()) {
+                        store_tweet(conn, &tweet, owner, false)?;
+                    }
+                    events.push(("create", data.clone()));
+                }
+            }
+        }
+    }
+
+    if let Some(favs) = payload["favorite_events"].as_array() {
+        for data in favs {
+            if data["user"]["id_str"].as_str() != Some(&for_user) {
+                continue;
+            }
+
+            match serde_json::from_value::<Tweet>(data["favorited_status"].clone()) {
+                Err(err) => println!("!! Cannot parse liked tweet: {:?}", err),
+                Ok(tweet) => {
+                    store_tweet(conn, &tweet, owner, true)?;
+                    events.push(("favorite", data.clone()));
+                }
+            }
+        }
+    }
+
+    if let Some(deletes) = payload["tweet_delete_events"].as_array() {
+        for data in deletes {
+            if let Some(sid) = data["status"]["id"].as_str() {
+                use crate::schema::statuses::dsl::*;
+                println!("-> Tweet {} was deleted", sid);
+
+                // Only the account’s own copy: the same tweet may have been
+                // liked from another account, where it’s still there.
+                if let Some(owner) = owner {
+                    diesel::update(
+                        statuses
+                            .filter(source.eq(Source::Twitter))
+                            .filter(account_id.eq(owner))
+                            .filter(source_id.eq(sid))
+                            .filter(deleted_at.is_null()),
+                    )
+                    .set(deleted_at.eq(chrono::Utc::now()))
+                    .execute(conn)?;
+                }
+                events.push(("delete", data.clone()));
+            }
+        }
+    }
+
+    for (key, on, off) in &[("block_events", "block", "unblock"), ("mute_events", "mute", "unmute")] {
+        if let Some(list) = payload[*key].as_array() {
+            for data in list {
+                let event = match data["type"].as_str() {
+                    Some(t) if t == *on => *on,
+                    Some(t) if t == *off => *off,
+                    _ => continue,
+                };
+
+                match serde_json::from_value::<EggUser>(data["target"].clone()) {
+                    Err(err) => println!("!! Cannot parse {} target: {:?}", event, err),
+                    Ok(user) => {
+                        store_user_flag(conn, &user, event, owner)?;
+                        events.push((event, data.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+fn store_tweet(
+    conn: &DbConnection,
+    tweet: &Tweet,
+    owner: Option<i32>,
+    liked: bool,
+) -> Result<(), OmeletteError> {
+    use chrono::Utc;
+    use crate::inserts::{NewEntity, NewEntityVariant, NewStatus, TextEntities};
+    use crate::schema::statuses::dsl::*;
+
+    let mut insert: NewStatus = tweet.into();
+    insert.account_id = owner;
+    insert.author_id = Twitter::store_authors(conn, Some(tweet))?
+        .get(&insert.source_id)
+        .cloned();
+    if liked {
+        insert.is_marked = true;
+        insert.marked_at = Some(Utc::now());
+    }
+
+    println!("-> Storing tweet {}", insert.source_id);
+
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let stored = crate::db::insert_new_statuses(conn, &[insert.clone()])?;
+
+        let sid = match stored.get(0) {
+            Some(status) => status.id,
+            None => {
+                if liked {
+                    diesel::update(
+                        statuses
+                            .filter(source.eq(Source::Twitter))
+                            .filter(source_id.eq(&insert.source_id)),
+                    )
+                        .set((is_marked.eq(true), marked_at.eq(insert.marked_at)))
+                        .execute(conn)?;
+                }
+                return Ok(());
+            }
+        };
+
+        if let Some(ref ents) = tweet.extended_entities {
+            let mut entitybag = NewEntity::from_extended(ents);
+            for ent in &mut entitybag {
+                ent.status_id = sid;
+            }
+
+            {
+                use crate::schema::entities::dsl::*;
+                crate::insert_or_ignore!(entities, &entitybag, (source, source_id))
+                    .execute(conn)?;
+            }
+            let variantbag = NewEntityVariant::from_extended(ents);
+            crate::db::insert_variants(conn, &Source::Twitter, &variantbag)?;
+        }
+
+        let otweet = tweet.retweeted_status.as_ref().map(|t| &**t).unwrap_or(tweet);
+        let mut textbag = HashMap::new();
+        textbag.insert(insert.source_id.clone(), TextEntities::from(otweet));
+        crate::db::insert_text_entities(conn, &textbag)?;
+
+        Ok(())
+    })?;
+
+    if let (true, Some(owner)) = (liked, owner) {
+        let sid: i32 = statuses
+            .select(id)
+            .filter(source.eq(Source::Twitter))
+            .filter(source_id.eq(&insert.source_id))
+            .first(conn)?;
+        Twitter::store_liked(conn, owner, &[sid])?;
+    }
+
+    Ok(())
+}
+
+/// Blocks and mutes are the account’s own, so they’re only recorded when it’s
+/// one we know.
+fn store_user_flag(
+    conn: &DbConnection,
+    user: &EggUser,
+    event: &str,
+    owner: Option<i32>,
+) -> Result<(), OmeletteError> {
+    use chrono::{DateTime, Utc};
+    use crate::inserts::{NewTwitterBlock, NewTwitterMute, NewTwitterUser};
+
+    println!("-> Event {} on user @{} ({})", event, user.screen_name, user.id);
+
+    let owner = match owner {
+        Some(owner) => owner,
+        None => return Ok(()),
+    };
+
+    let insert: NewTwitterUser = user.into();
+
+    // Un-blocks and un-mutes are kept, with when they happened, until the
+    // user is blocked or muted again.
+    macro_rules! flag {
+        ($table:ident, $row:ident, $flag:ident, $cleared:ident, $uid:expr) => {{
+            use crate::schema::$table::dsl::*;
+            let now = Utc::now();
+            let row = $row {
+                account_id: Some(owner),
+                user_id: $uid,
+                $flag: now,
+                listed_at: None,
+            };
+            crate::insert_or_ignore!($table, &row, (account_id, user_id)).execute(conn)?;
+            diesel::update(
+                $table
+                    .filter(account_id.eq(owner))
+                    .filter(user_id.eq($uid))
+                    .filter($cleared.is_not_null()),
+            )
+            .set(($flag.eq(now), $cleared.eq(None::<DateTime<Utc>>)))
+            .execute(conn)?;
+        }};
+    }
+
+    macro_rules! unflag {
+        ($table:ident, $cleared:ident, $uid:expr) => {{
+            use crate::schema::$table::dsl::*;
+            diesel::update(
+                $table
+                    .filter(account_id.eq(owner))
+                    .filter(user_id.eq($uid))
+                    .filter($cleared.is_null()),
+            )
+            .set($cleared.eq(Utc::now()))
+            .execute(conn)?;
+        }};
+    }
+
+    // Insert the user if we don’t know them yet, then set the flag either way.
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let uid: i32 = {
+            use crate::schema::twitter_users::dsl::*;
+            crate::insert_or_ignore!(twitter_users, &insert, source_id).execute(conn)?;
+            twitter_users
+                .filter(source_id.eq(&insert.source_id))
+                .select(id)
+                .first(conn)?
+        };
+
+        match event {
+            "block" => flag!(twitter_blocks, NewTwitterBlock, blocked_at, unblocked_at, uid),
+            "unblock" => unflag!(twitter_blocks, unblocked_at, uid),
+            "mute" => flag!(twitter_mutes, NewTwitterMute, muted_at, unmuted_at, uid),
+            _ => unflag!(twitter_mutes, unmuted_at, uid),
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AccountConfig;
+    use crate::db::test_connection;
+    use crate::inserts::{NewStatus, NewTwitterBlock};
+    use crate::models::{Status, TwitterBlock, TwitterLike, TwitterMute, TwitterUser};
+
+    const SECRET: &str = "consumer-secret";
+    const OWNER: &str = "1100000000000000001";
+    const OTHER: &str = "1100000000000000002";
+
+    const TWEET_CREATE: &str = include_str!("../../tests/fixtures/twitter-events/tweet_create.json");
+    const FAVORITE: &str = include_str!("../../tests/fixtures/twitter-events/favorite.json");
+    const DELETE: &str = include_str!("../../tests/fixtures/twitter-events/delete.json");
+    const BLOCK: &str = include_str!("../../tests/fixtures/twitter-events/block.json");
+    const UNBLOCK: &str = include_str!("../../tests/fixtures/twitter-events/unblock.json");
+    const MUTE: &str = include_str!("../../tests/fixtures/twitter-events/mute.json");
+    const UNMUTE: &str = include_str!("../../tests/fixtures/twitter-events/unmute.json");
+
+    fn state() -> Arc<State> {
+        let conn = test_connection();
+        crate::accounts::register(
+            &conn,
+            &AccountConfig {
+                name: "owner".into(),
+                source: Source::Twitter,
+                id: Some(OWNER.into()),
+                consumer_key: None,
+                consumer_secret: Some(SECRET.into()),
+                access_token_key: None,
+                access_token_secret: None,
+                instance: None,
+                access_token: None,
+            },
+        )
+        .unwrap();
+
+        Arc::new(State {
+            db: Mutex::new(conn),
+            key: hmac_key(SECRET).unwrap(),
+            verify: true,
+            hooks: HashMap::new(),
+            booted: AtomicBool::new(false),
+        })
+    }
+
+    fn post_signed(state: &Arc<State>, secret: &str, payload: &str) -> StatusCode {
+        let key = hmac_key(secret).unwrap();
+        let signature = base64::encode(&hmac(&key, payload.as_bytes()).result().code());
+        let req = Request::post("/")
+            .header("x-twitter-webhooks-signature", format!("sha256={}", signature))
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+
+        handle(state.clone(), req).wait().unwrap().status()
+    }
+
+    fn post(state: &Arc<State>, payload: &str) -> StatusCode {
+        post_signed(state, SECRET, payload)
+    }
+
+    fn tweet(state: &State, sid: &str) -> Option<Status> {
+        use crate::schema::statuses::dsl::*;
+
+        let db = state.db.lock().unwrap();
+        statuses
+            .filter(source.eq(Source::Twitter))
+            .filter(source_id.eq(sid))
+            .first(&*db)
+            .optional()
+            .unwrap()
+    }
+
+    fn user(state: &State, sid: &str) -> TwitterUser {
+        use crate::schema::twitter_users::dsl::*;
+
+        let db = state.db.lock().unwrap();
+        twitter_users.filter(source_id.eq(sid)).first(&*db).unwrap()
+    }
+
+    fn owner_id(state: &State) -> i32 {
+        let db = state.db.lock().unwrap();
+        crate::accounts::find(&db, &Source::Twitter, OWNER)
+            .unwrap()
+            .unwrap()
+            .id
+    }
+
+    fn other_id(state: &State) -> i32 {
+        let db = state.db.lock().unwrap();
+        crate::accounts::register(
+            &db,
+            &AccountConfig {
+                name: "other".into(),
+                source: Source::Twitter,
+                id: Some("1100000000000000009".into()),
+                consumer_key: None,
+                consumer_secret: Some(SECRET.into()),
+                access_token_key: None,
+                access_token_secret: None,
+                instance: None,
+                access_token: None,
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn like(state: &State, account: i32, sid: &str) -> TwitterLike {
+        use crate::schema::twitter_likes::dsl::*;
+
+        let liked = tweet(state, sid).unwrap().id;
+        let db = state.db.lock().unwrap();
+        twitter_likes
+            .filter(account_id.eq(account))
+            .filter(status_id.eq(liked))
+            .first(&*db)
+            .unwrap()
+    }
+
+    fn block(state: &State, account: i32, sid: &str) -> Option<TwitterBlock> {
+        use crate::schema::twitter_blocks::dsl::*;
+
+        let uid = user(state, sid).id;
+        let db = state.db.lock().unwrap();
+        twitter_blocks
+            .filter(account_id.eq(account))
+            .filter(user_id.eq(uid))
+            .first(&*db)
+            .optional()
+            .unwrap()
+    }
+
+    fn mute(state: &State, account: i32, sid: &str) -> Option<TwitterMute> {
+        use crate::schema::twitter_mutes::dsl::*;
+
+        let uid = user(state, sid).id;
+        let db = state.db.lock().unwrap();
+        twitter_mutes
+            .filter(account_id.eq(account))
+            .filter(user_id.eq(uid))
+            .first(&*db)
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn answers_crc_challenges() {
+        let state = state();
+        let req = Request::get("/?crc_token=challenge").body(Body::empty()).unwrap();
+        let resp = handle(state.clone(), req).wait().unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = resp.into_body().concat2().wait().unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["response_token"],
+            "sha256=2RUZDVKjSpEV/C/r9ivMsVZJ4DFPAawjJFQQzY+6ba4="
+        );
+        assert!(state.booted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn ignores_badly_signed_events() {
+        let state = state();
+        assert_eq!(post_signed(&state, "another-secret", TWEET_CREATE), StatusCode::FORBIDDEN);
+        assert!(tweet(&state, "1141600000000000001").is_none());
+    }
+
+    #[test]
+    fn stores_own_tweets_only() {
+        let state = state();
+        assert_eq!(post(&state, TWEET_CREATE), StatusCode::OK);
+
+        let own = tweet(&state, "1141600000000000001").unwrap();
+        assert_eq!(own.account_id, Some(owner_id(&state)));
+        assert_eq!(own.author_id, Some(user(&state, OWNER).id));
+        assert_eq!(own.text, "Scrambled, with chives. #breakfast");
+
+        // A mention of the account, by someone else.
+        assert!(tweet(&state, "1141600000000000003").is_none());
+    }
+
+    #[test]
+    fn stores_likes() {
+        let state = state();
+        assert_eq!(post(&state, FAVORITE), StatusCode::OK);
+
+        let liked = tweet(&state, "1141600000000000002").unwrap();
+        assert!(liked.is_marked);
+        assert!(liked.marked_at.is_some());
+        assert_eq!(liked.account_id, Some(owner_id(&state)));
+        assert_eq!(liked.author_id, Some(user(&state, OTHER).id));
+        assert!(like(&state, owner_id(&state), "1141600000000000002").unliked_at.is_none());
+    }
+
+    #[test]
+    fn keeps_likes_per_account() {
+        let state = state();
+        assert_eq!(post(&state, FAVORITE), StatusCode::OK);
+
+        let owner = owner_id(&state);
+        let other = other_id(&state);
+        let liked = tweet(&state, "1141600000000000002").unwrap().id;
+        {
+            let db = state.db.lock().unwrap();
+            assert_eq!(Twitter::store_liked(&db, other, &[liked]).unwrap(), 1);
+        }
+
+        let owners = like(&state, owner, "1141600000000000002").id;
+        {
+            let db = state.db.lock().unwrap();
+            Twitter::store_unliked(&db, &[owners]).unwrap();
+        }
+        assert!(like(&state, owner, "1141600000000000002").unliked_at.is_some());
+        assert!(tweet(&state, "1141600000000000002").unwrap().is_marked);
+
+        let others = like(&state, other, "1141600000000000002").id;
+        {
+            let db = state.db.lock().unwrap();
+            Twitter::store_unliked(&db, &[others]).unwrap();
+        }
+        assert!(!tweet(&state, "1141600000000000002").unwrap().is_marked);
+
+        // Liking again starts afresh.
+        assert_eq!(post(&state, FAVORITE), StatusCode::OK);
+        assert!(like(&state, owner, "1141600000000000002").unliked_at.is_none());
+        assert!(tweet(&state, "1141600000000000002").unwrap().is_marked);
+    }
+
+    #[test]
+    fn deletes_the_accounts_tweet_only() {
+        let state = state();
+        assert_eq!(post(&state, TWEET_CREATE), StatusCode::OK);
+
+        // A toot that happens to have the same ID.
+        let payload: Value = serde_json::from_str(TWEET_CREATE).unwrap();
+        let created: Tweet = serde_json::from_value(payload["tweet_create_events"][0].clone()).unwrap();
+        {
+            let db = state.db.lock().unwrap();
+            crate::db::insert_new_statuses(
+                &db,
+                &[NewStatus {
+                    source: Source::Mastodon,
+                    ..(&created).into()
+                }],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(post(&state, DELETE), StatusCode::OK);
+        assert!(tweet(&state, "1141600000000000001").unwrap().deleted_at.is_some());
+
+        use crate::schema::statuses::dsl::*;
+        let db = state.db.lock().unwrap();
+        let toot: Status = statuses
+            .filter(source.eq(Source::Mastodon))
+            .filter(source_id.eq("1141600000000000001"))
+            .first(&*db)
+            .unwrap();
+        assert!(toot.deleted_at.is_none());
+    }
+
+    #[test]
+    fn flags_blocked_and_muted_users() {
+        let state = state();
+
+        let owner = owner_id(&state);
+
+        assert_eq!(post(&state, BLOCK), StatusCode::OK);
+        assert!(block(&state, owner, OTHER).unwrap().unblocked_at.is_none());
+
+        assert_eq!(post(&state, MUTE), StatusCode::OK);
+        assert!(mute(&state, owner, OTHER).unwrap().unmuted_at.is_none());
+
+        assert_eq!(post(&state, UNBLOCK), StatusCode::OK);
+        assert!(block(&state, owner, OTHER).unwrap().unblocked_at.is_some());
+        assert!(mute(&state, owner, OTHER).unwrap().unmuted_at.is_none());
+
+        assert_eq!(post(&state, UNMUTE), StatusCode::OK);
+        assert!(mute(&state, owner, OTHER).unwrap().unmuted_at.is_some());
+
+        assert_eq!(post(&state, BLOCK), StatusCode::OK);
+        assert!(block(&state, owner, OTHER).unwrap().unblocked_at.is_none());
+    }
+
+    #[test]
+    fn unblocks_for_the_account_only() {
+        let state = state();
+        assert_eq!(post(&state, BLOCK), StatusCode::OK);
+
+        let other = other_id(&state);
+
+        let blocked = user(&state, OTHER).id;
+        {
+            use crate::schema::twitter_blocks::dsl::*;
+            let db = state.db.lock().unwrap();
+            diesel::insert_into(twitter_blocks)
+                .values(&NewTwitterBlock {
+                    account_id: Some(other),
+                    user_id: blocked,
+                    blocked_at: chrono::Utc::now(),
+                    listed_at: None,
+                })
+                .execute(&*db)
+                .unwrap();
+        }
+
+        assert_eq!(post(&state, UNBLOCK), StatusCode::OK);
+        assert!(block(&state, owner_id(&state), OTHER).unwrap().unblocked_at.is_some());
+        assert!(block(&state, other, OTHER).unwrap().unblocked_at.is_none());
+    }
+
+    #[test]
+    fn fails_without_panicking_on_a_poisoned_lock() {
+        let state = state();
+
+        let poisoner = state.clone();
+        let _ = std::thread::spawn(move || {
+            let _db = poisoner.db.lock().unwrap();
+            panic!("poisoning the lock");
+        })
+        .join();
+
+        assert_eq!(post(&state, TWEET_CREATE), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,409 @@
+use chrono::Utc;
+use crate::cli::Global;
+use crate::config::Config;
+use crate::models::{GraphSnapshot, TwitterUser};
+use crate::policy::parse_duration;
+use crate::sources::twitter::{GraphKind, Twitter};
+use crate::{DbConnection, OmeletteError};
+use diesel::prelude::*;
+use std::collections::BTreeSet;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub enum Opt {
+    /// Record who follows the accounts and whom they follow
+    #[structopt(name = "snapshot")]
+    Snapshot(SnapshotOpt),
+
+    /// List the snapshots taken
+    #[structopt(name = "list")]
+    List(ListOpt),
+
+    /// Report who was added to or removed from the graph between two snapshots
+    #[structopt(name = "diff")]
+    Diff(DiffOpt),
+
+    /// Show when a user followed or was followed, and stopped
+    #[structopt(name = "history")]
+    History(HistoryOpt),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct SnapshotOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only snapshot this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Only snapshot followers or following [default: both]
+    #[structopt(long = "kind")]
+    pub kind: Option<GraphKind>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ListOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only list snapshots of this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Only list snapshots of followers or following [default: both]
+    #[structopt(long = "kind")]
+    pub kind: Option<GraphKind>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct DiffOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only diff this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Only diff followers or following [default: both]
+    #[structopt(long = "kind")]
+    pub kind: Option<GraphKind>,
+
+    /// Compare against the last snapshot from at least this long ago (e.g. 7d)
+    /// instead of the one before the latest
+    #[structopt(long = "since")]
+    pub since: Option<String>,
+
+    /// Snapshot to compare from [default: the one before TO]
+    pub from: Option<i32>,
+
+    /// Snapshot to compare to [default: the latest]
+    pub to: Option<i32>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct HistoryOpt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only look at this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Only look at followers or following [default: both]
+    #[structopt(long = "kind")]
+    pub kind: Option<GraphKind>,
+
+    /// The user, by screen name or numeric ID
+    pub user: String,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    let global = match opt {
+        Opt::Snapshot(opt) => &opt.global,
+        Opt::List(opt) => &opt.global,
+        Opt::Diff(opt) => &opt.global,
+        Opt::History(opt) => &opt.global,
+    };
+
+    run_with(opt, &global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    match opt {
+        Opt::Snapshot(opt) => snapshot(opt, config),
+        Opt::List(opt) => list(opt, config),
+        Opt::Diff(opt) => diff(opt, config),
+        Opt::History(opt) => history(opt, config),
+    }
+}
+
+fn kinds(kind: &Option<GraphKind>) -> Vec<GraphKind> {
+    match kind {
+        Some(kind) => vec![*kind],
+        None => GraphKind::all().to_vec(),
+    }
+}
+
+fn snapshot(opt: &SnapshotOpt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-graph").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    println!("-- Twitter allows 15 pages of 5000 IDs per 15 minutes, so large graphs can take a while.");
+    for tw in Twitter::all(&db, config, &account)? {
+        for kind in kinds(&opt.kind) {
+            println!("\n=> Snapshotting {} of {}", kind.name(), tw.account.name);
+            tw.snapshot_graph(&db, kind)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn list(opt: &ListOpt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-graph").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    for tw in Twitter::all(&db, config, &account)? {
+        for kind in kinds(&opt.kind) {
+            use crate::schema::graph_snapshots::dsl as snaps;
+            let all: Vec<GraphSnapshot> = snaps::graph_snapshots
+                .filter(snaps::account_id.eq(tw.account.id))
+                .filter(snaps::kind.eq(kind.name()))
+                .order_by(snaps::taken_at.asc())
+                .load(&db)?;
+
+            println!("\n=> {} of {}: {} snapshots", kind.name(), tw.account.name, all.len());
+            for snap in all {
+                println!(
+                    ":: #{}\t{}\t{} users{}",
+                    snap.id,
+                    snap.taken_at.to_rfc3339(),
+                    snap.count,
+                    if snap.complete { "" } else { " (incomplete)" }
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn diff(opt: &DiffOpt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-graph").or("account", &opt.account)?;
+    let since = match opt.since {
+        Some(ref s) => Some(
+            parse_duration(s).ok_or_else(|| OmeletteError::Config(format!("bad duration {:?}", s)))?,
+        ),
+        None => None,
+    };
+
+    let db = crate::connect()?;
+
+    // Snapshots given by ID say which account and kind to look at.
+    if let Some(from) = opt.from {
+        let from = find(&db, from)?;
+        let to = match opt.to {
+            Some(to) => find(&db, to)?,
+            None => match complete_snapshots(&db, from.account_id, &from.kind)?.pop() {
+                Some(to) => to,
+                None => return Err(OmeletteError::Data(format!("no complete snapshot to compare #{} to", from.id))),
+            },
+        };
+
+        if from.account_id != to.account_id || from.kind != to.kind {
+            return Err(OmeletteError::Config(format!(
+                "snapshots #{} and #{} aren’t of the same account and kind",
+                from.id, to.id
+            )));
+        }
+
+        let name = account_name(&db, from.account_id)?;
+        return print_diff(&db, &name, &from, &to);
+    }
+
+    for tw in Twitter::all(&db, config, &account)? {
+        for kind in kinds(&opt.kind) {
+            let mut snaps = complete_snapshots(&db, tw.account.id, kind.name())?;
+            let to = match snaps.pop() {
+                Some(to) => to,
+                None => {
+                    println!("\n-- No complete snapshot of {} of {} yet", kind.name(), tw.account.name);
+                    continue;
+                }
+            };
+
+            let from = match since {
+                Some(since) => {
+                    let before = Utc::now() - since;
+                    snaps
+                        .iter()
+                        .rev()
+                        .find(|snap| snap.taken_at <= before)
+                        .or_else(|| snaps.first())
+                        .cloned()
+                }
+                None => snaps.pop(),
+            };
+
+            match from {
+                Some(from) => print_diff(&db, &tw.account.name, &from, &to)?,
+                None => println!(
+                    "\n-- Only one complete snapshot of {} of {}, nothing to compare",
+                    kind.name(), tw.account.name
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_diff(
+    conn: &DbConnection,
+    account: &str,
+    from: &GraphSnapshot,
+    to: &GraphSnapshot,
+) -> Result<(), OmeletteError> {
+    let before = members(conn, from.id)?;
+    let after = members(conn, to.id)?;
+
+    let added: Vec<i32> = after.difference(&before).cloned().collect();
+    let removed: Vec<i32> = before.difference(&after).cloned().collect();
+
+    println!(
+        "\n=> {} of {}: #{} ({}) to #{} ({})",
+        from.kind,
+        account,
+        from.id,
+        from.taken_at.to_rfc3339(),
+        to.id,
+        to.taken_at.to_rfc3339()
+    );
+    println!(":: {} users then, {} now", before.len(), after.len());
+
+    println!("-> {} added", added.len());
+    for user in users(conn, &added)? {
+        println!("   + {}", describe(&user));
+    }
+
+    println!("-> {} removed", removed.len());
+    for user in users(conn, &removed)? {
+        println!("   - {}", describe(&user));
+    }
+
+    Ok(())
+}
+
+fn history(opt: &HistoryOpt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-graph").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    let user: TwitterUser = {
+        use crate::schema::twitter_users::dsl::*;
+        let needle = opt.user.trim_start_matches('@');
+        let query = if needle.parse::<u64>().is_ok() {
+            twitter_users.filter(source_id.eq(needle)).into_boxed()
+        } else {
+            twitter_users.filter(screen_name.eq(needle)).into_boxed()
+        };
+
+        query
+            .first(&db)
+            .optional()?
+            .ok_or_else(|| OmeletteError::Data(format!("no user {} in the database", opt.user)))?
+    };
+
+    println!("=> History of {}", describe(&user));
+
+    for tw in Twitter::all(&db, config, &account)? {
+        for kind in kinds(&opt.kind) {
+            let snaps = complete_snapshots(&db, tw.account.id, kind.name())?;
+            let seen: BTreeSet<i32> = {
+                use crate::schema::graph_members::dsl::*;
+                graph_members
+                    .filter(user_id.eq(user.id))
+                    .filter(snapshot_id.eq_any(snaps.iter().map(|s| s.id)))
+                    .select(snapshot_id)
+                    .load::<i32>(&db)?
+                    .into_iter()
+                    .collect()
+            };
+
+            println!("\n-> In {} of {} ({} snapshots)", kind.name(), tw.account.name, snaps.len());
+            if seen.is_empty() {
+                println!("   never seen");
+                continue;
+            }
+
+            // Changes happened some time between two snapshots, so both are shown.
+            let mut previous: Option<&GraphSnapshot> = None;
+            for snap in &snaps {
+                let was = previous.map_or(false, |p| seen.contains(&p.id));
+                let is = seen.contains(&snap.id);
+                if was != is {
+                    println!(
+                        "   {} {}{}",
+                        if is { "+ added by" } else { "- removed by" },
+                        snap.taken_at.to_rfc3339(),
+                        previous.map_or(" (first snapshot)".into(), |p| format!(
+                            ", after {}",
+                            p.taken_at.to_rfc3339()
+                        ))
+                    );
+                }
+
+                previous = Some(snap);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn find(conn: &DbConnection, snap: i32) -> Result<GraphSnapshot, OmeletteError> {
+    use crate::schema::graph_snapshots::dsl::*;
+
+    let snapshot: GraphSnapshot = graph_snapshots
+        .find(snap)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| OmeletteError::Data(format!("no snapshot #{}", snap)))?;
+
+    if !snapshot.complete {
+        return Err(OmeletteError::Data(format!("snapshot #{} is incomplete", snap)));
+    }
+
+    Ok(snapshot)
+}
+
+/// The complete snapshots of an account’s graph, oldest first.
+fn complete_snapshots(conn: &DbConnection, account: i32, graph: &str) -> Result<Vec<GraphSnapshot>, OmeletteError> {
+    use crate::schema::graph_snapshots::dsl::*;
+
+    Ok(graph_snapshots
+        .filter(account_id.eq(account))
+        .filter(kind.eq(graph))
+        .filter(complete.eq(true))
+        .order_by(taken_at.asc())
+        .load(conn)?)
+}
+
+fn members(conn: &DbConnection, snap: i32) -> Result<BTreeSet<i32>, OmeletteError> {
+    use crate::schema::graph_members::dsl::*;
+
+    Ok(graph_members
+        .filter(snapshot_id.eq(snap))
+        .select(user_id)
+        .load::<i32>(conn)?
+        .into_iter()
+        .collect())
+}
+
+fn users(conn: &DbConnection, ids: &[i32]) -> Result<Vec<TwitterUser>, OmeletteError> {
+    use crate::schema::twitter_users::dsl::*;
+
+    Ok(twitter_users
+        .filter(id.eq_any(ids))
+        .order_by(screen_name.asc())
+        .load(conn)?)
+}
+
+fn account_name(conn: &DbConnection, account: i32) -> Result<String, OmeletteError> {
+    use crate::schema::accounts::dsl::*;
+
+    Ok(accounts.find(account).select(name).first(conn)?)
+}
+
+/// Slim users only have their ID until hydrated.
+fn describe(user: &TwitterUser) -> String {
+    if user.screen_name == crate::SLIM_MARK {
+        format!("user {} (not hydrated yet)", user.source_id)
+    } else {
+        format!("@{} ({}, {})", user.screen_name, user.name, user.source_id)
+    }
+}
@@ -0,0 +1,309 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::sources::twitter::Twitter;
+use crate::{DbConnection, OmeletteError};
+use diesel::prelude::*;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only hydrate with this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+
+    /// Hydrate users
+    pub users: bool,
+
+    /// Hydrate tweets
+    pub tweets: bool,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-hydrate").or("account", &opt.account)?;
+
+    let (do_users, do_tweets) = if !opt.users && !opt.tweets {
+        println!("-- No hydration target provided, assuming all");
+        (true, true)
+    } else {
+        (opt.users, opt.tweets)
+    };
+
+    let db = crate::connect()?;
+    let accounts = Twitter::all(&db, config, &account)?;
+
+    // Each account looks up its own tweets, which may be protected. Tweets not
+    // linked to an account go with the first one, as do users.
+    if do_tweets {
+        for (n, tw) in accounts.iter().enumerate() {
+            use crate::schema::statuses::dsl::*;
+            use crate::types::Source;
+            let mut query = statuses.select(id)
+                .filter(source.eq(Source::Twitter))
+                .filter(source_author.eq(crate::slim()))
+                .into_boxed();
+
+            query = if n == 0 {
+                query.filter(account_id.eq(tw.account.id).or(account_id.is_null()))
+            } else {
+                query.filter(account_id.eq(tw.account.id))
+            };
+
+            let ids_left = query.load::<i32>(&db)?;
+
+            if !ids_left.is_empty() {
+                println!(
+                    "\n=> Hydrating {} slim tweets with {} (~{})",
+                    ids_left.len(), tw.account.name, hydrate_est(ids_left.len())
+                );
+                for (i, batch) in ids_left.chunks(100).enumerate() {
+                    println!("-> Batch {} of {} tweets", i + 1, batch.len());
+                    hydrate_batch_tweets(&db, tw, batch)?;
+                }
+            }
+        }
+    }
+
+    let tw = &accounts[0];
+
+    if do_users {
+        use crate::schema::twitter_users::dsl::*;
+        let ids_left = twitter_users.select(id)
+            .filter(screen_name.eq(crate::slim()))
+            .load::<i32>(&db)?;
+
+        if !ids_left.is_empty() {
+            println!("\n=> Hydrating {} slim users (~{})", ids_left.len(), hydrate_est(ids_left.len()));
+            for (i, batch) in ids_left.chunks(100).enumerate() {
+                println!("-> Batch {} of {} users", i + 1, batch.len());
+                hydrate_batch_users(&db, tw, batch)?;
+            }
+        }
+    }
+
+    println!("\n=> Done hydrating.");
+    Ok(())
+}
+
+/// Lookups are 100 at a time, and allowed 900 times per 15 minutes.
+fn hydrate_est(n: usize) -> String {
+    let mut seconds = n / 100;
+    let hours = seconds / 3600;
+    seconds = seconds % 3600;
+
+    let mut minutes = seconds / 60;
+    if seconds % 60 > 30 {
+        minutes += 1;
+    } else if minutes == 0 {
+        minutes = 1;
+    }
+
+    if hours == 0 {
+        format!("{} minutes", minutes)
+    } else {
+        format!("{} hours {} minutes", hours, minutes)
+    }
+}
+
+fn hydrate_batch_tweets(conn: &DbConnection, tw: &Twitter, ids: &[i32]) -> Result<(), OmeletteError> {
+    use chrono::Utc;
+    use egg_mode::tweet::lookup_map;
+    use crate::inserts::{NewEntity, NewEntityVariant, NewStatus, TextEntities};
+    use crate::models::Status;
+    use crate::types::{IntermediarySource, Source};
+    use std::collections::HashMap;
+
+    let source_ids: Vec<u64> = {
+        use crate::schema::statuses::dsl::*;
+        statuses.select(source_id)
+            .filter(id.eq_any(ids))
+            .load::<String>(conn)?
+            .iter()
+            .filter_map(|sid| sid.parse().ok())
+            .collect()
+    };
+
+    let tweets = match tw.call(conn, "statuses/lookup", || lookup_map(source_ids.clone(), &tw.token)) {
+        Ok(tws) => tws,
+        Err(OmeletteError::Twitter(err)) => {
+            println!("!! Cannot fetch tweets, skipping batch.\n{:?}", err);
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+
+    let statuses: Vec<Status> = {
+        use crate::schema::statuses::dsl::*;
+        statuses
+            .filter(id.eq_any(ids))
+            .load(conn)?
+    };
+
+    let authors = Twitter::store_authors(conn, tweets.values().filter_map(|tweet| tweet.as_ref()))?;
+
+    for status in &statuses {
+        use crate::schema::statuses::dsl::*;
+
+        let sid: u64 = match status.source_id.parse() {
+            Ok(sid) => sid,
+            Err(_) => {
+                println!("!! Cannot parse source ID {:?}, skipping.", status.source_id);
+                continue;
+            }
+        };
+
+        let tweet = match tweets.get(&sid) {
+            Some(tweet) => tweet,
+            None => {
+                println!("!! Tweet {} missing from lookup, skipping.", sid);
+                continue;
+            }
+        };
+
+        if let Some(tweet) = tweet {
+            let mut insert: NewStatus = tweet.into();
+            insert.fetched_via = Some(IntermediarySource::TwitterArchive);
+            insert.account_id = status.account_id;
+            insert.author_id = authors.get(&insert.source_id).cloned();
+
+            let (mut entitybag, variantbag) = if let Some(ref ents) = tweet.extended_entities {
+                (NewEntity::from_extended(&ents), NewEntityVariant::from_extended(&ents))
+            } else {
+                (Vec::new(), HashMap::new())
+            };
+
+            let otweet = tweet.retweeted_status.as_ref().map(|t| &**t).unwrap_or(tweet);
+            let mut textbag = HashMap::new();
+            textbag.insert(insert.source_id.clone(), TextEntities::from(otweet));
+
+            // Update and insert in a transaction so we don’t save an hydrated
+            // tweet without its entities if we’re interrupted in the middle.
+            conn.transaction::<_, diesel::result::Error, _>(|| {
+                let new_id = if status.source_id == insert.source_id {
+                    diesel::update(statuses.find(status.id))
+                        .set(insert)
+                        .execute(conn)?;
+
+                    status.id
+                } else {
+                    // Hydrating to a retweet.
+                    // We instead insert the hydrated tweet and delete the slim.
+
+                    crate::insert_or_ignore!(statuses, &insert, (source, source_id))
+                        .execute(conn)?;
+
+                    let nid = statuses.select(id)
+                        .filter(source.eq(Source::Twitter))
+                        .filter(source_id.eq(insert.source_id))
+                        .first::<i32>(conn)?;
+
+                    diesel::delete(statuses.find(status.id))
+                        .execute(conn)?;
+
+                    nid
+                };
+
+                // Add those late so it picks up if the ID has changed above
+                for ent in &mut entitybag {
+                    ent.status_id = new_id;
+                }
+
+                {
+                    use crate::schema::entities::dsl::*;
+                    crate::insert_or_ignore!(entities, &entitybag, (source, source_id))
+                        .execute(conn)?;
+                }
+                crate::db::insert_variants(conn, &Source::Twitter, &variantbag)?;
+                crate::db::insert_text_entities(conn, &textbag)?;
+
+                Ok(())
+            })?;
+        } else {
+            diesel::update(statuses.find(status.id))
+                .set((
+                    source_author.eq("".to_string()),
+                    deleted_at.eq(Utc::now())
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn hydrate_batch_users(conn: &DbConnection, tw: &Twitter, ids: &[i32]) -> Result<(), OmeletteError> {
+    use chrono::Utc;
+    use egg_mode::user::{lookup, TwitterUser as EggUser};
+    use crate::inserts::NewTwitterUser;
+    use crate::models::TwitterUser;
+    use std::collections::HashMap;
+
+    let source_ids: Vec<u64> = {
+        use crate::schema::twitter_users::dsl::*;
+        twitter_users.select(source_id)
+            .filter(id.eq_any(ids))
+            .load::<String>(conn)?
+            .iter()
+            .filter_map(|sid| sid.parse().ok())
+            .collect()
+    };
+
+    let twuv = match tw.call(conn, "users/lookup", || lookup(source_ids.clone(), &tw.token)) {
+        Ok(us) => us,
+        Err(OmeletteError::Twitter(err)) => {
+            println!("!! Cannot fetch users, skipping batch.\n{:?}", err);
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+
+    let mut twusers = HashMap::with_capacity(twuv.len());
+    for twu in twuv.into_iter() {
+        twusers.insert(twu.id, twu);
+    }
+    let twusers = twusers;
+
+    let dbusers: Vec<TwitterUser> = {
+        use crate::schema::twitter_users::dsl::*;
+        twitter_users
+            .filter(id.eq_any(ids))
+            .load(conn)?
+    };
+
+    for user in &dbusers {
+        use crate::schema::twitter_users::dsl::*;
+
+        let sid: u64 = match user.source_id.parse() {
+            Ok(sid) => sid,
+            Err(_) => {
+                println!("!! Cannot parse source ID {:?}, skipping.", user.source_id);
+                continue;
+            }
+        };
+
+        if let Some(twuser) = twusers.get(&sid) {
+            let twu: &EggUser = &*twuser;
+            let insert: NewTwitterUser = twu.into();
+
+            diesel::update(twitter_users.find(user.id))
+                .set(insert)
+                .execute(conn)?;
+        } else {
+            diesel::update(twitter_users.find(user.id))
+                .set((
+                    missing.eq(true),
+                    fetched_at.eq(Utc::now())
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
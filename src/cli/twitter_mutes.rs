@@ -0,0 +1,37 @@
+use crate::cli::Global;
+use crate::config::Config;
+use crate::sources::twitter::{Twitter, UserList};
+use crate::OmeletteError;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub struct Opt {
+    #[structopt(flatten)]
+    pub global: Global,
+
+    /// Only fetch the mutes of this account, by its name in the config
+    #[structopt(long = "account")]
+    pub account: Option<String>,
+}
+
+pub fn run(opt: &Opt) -> Result<(), OmeletteError> {
+    run_with(opt, &opt.global.load()?)
+}
+
+pub fn run_with(opt: &Opt, config: &Config) -> Result<(), OmeletteError> {
+    let account = config.tool("twitter-mutes").or("account", &opt.account)?;
+
+    let db = crate::connect()?;
+
+    println!("-- Twitter allows 15 pages of 5000 IDs per 15 minutes, so long lists can take a while.");
+    println!("-- If interrupted, the next run resumes where this one stopped.");
+
+    // Each account has its own mute list.
+    for tw in Twitter::all(&db, config, &account)? {
+        println!("\n=> Fetching IDs of users muted by {}", tw.account.name);
+        tw.sync_user_list(&db, UserList::Mutes)?;
+    }
+
+    Ok(())
+}
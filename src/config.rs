@@ -0,0 +1,234 @@
+//! The config file, which can stand in for environment variables and supply
+//! per-tool settings.
+//!
+//! Environment variables (including from .env) always win over the file, and
+//! flags on the command line win over per-tool settings.
+
+use crate::error::OmeletteError;
+use crate::types::Source;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+
+pub const DEFAULT_STORE: &str = "./omelette/store";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database_url: Option<String>,
+    pub store: Option<PathBuf>,
+    pub twitter: TwitterConfig,
+    pub mastodon: MastodonConfig,
+    /// Named accounts, for more than one per source. When there are none, the
+    /// environment defines one of each, named after their source.
+    pub accounts: Vec<AccountConfig>,
+    /// Settings for each tool, by name, keyed like their long flags.
+    pub tools: HashMap<String, Table>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwitterConfig {
+    pub consumer_key: Option<String>,
+    pub consumer_secret: Option<String>,
+    pub access_token_key: Option<String>,
+    pub access_token_secret: Option<String>,
+    pub user_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MastodonConfig {
+    pub instance: Option<String>,
+    pub access_token: Option<String>,
+    pub account_id: Option<String>,
+}
+
+/// One `[[accounts]]` entry. Which fields are needed depends on the source,
+/// the Twitter consumer keys default to those from the environment.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountConfig {
+    pub name: String,
+    pub source: Source,
+    /// The user ID on Twitter, or the account ID on Mastodon.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub consumer_key: Option<String>,
+    #[serde(default)]
+    pub consumer_secret: Option<String>,
+    #[serde(default)]
+    pub access_token_key: Option<String>,
+    #[serde(default)]
+    pub access_token_secret: Option<String>,
+    #[serde(default)]
+    pub instance: Option<String>,
+    #[serde(default)]
+    pub access_token: Option<String>,
+}
+
+impl AccountConfig {
+    fn from_env(name: &str, source: Source) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            id: None,
+            consumer_key: env::var("TWITTER_CONSUMER_KEY").ok(),
+            consumer_secret: env::var("TWITTER_CONSUMER_SECRET").ok(),
+            access_token_key: None,
+            access_token_secret: None,
+            instance: None,
+            access_token: None,
+        }
+    }
+}
+
+impl Config {
+    /// Where the config file is looked for when not given explicitly:
+    /// `$XDG_CONFIG_HOME/omelette/config.toml`, or `~/.config/omelette/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("omelette").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, OmeletteError> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            OmeletteError::Config(format!("cannot read config {}: {}", path.display(), err))
+        })?;
+
+        toml::from_str(&content)
+            .map_err(|err| OmeletteError::Config(format!("config {}: {}", path.display(), err)))
+    }
+
+    /// Sets the environment variables the file has values for, unless they’re
+    /// already set.
+    pub fn apply_env(&self) {
+        let vars = vec![
+            ("DATABASE_URL", &self.database_url),
+            ("TWITTER_CONSUMER_KEY", &self.twitter.consumer_key),
+            ("TWITTER_CONSUMER_SECRET", &self.twitter.consumer_secret),
+            ("TWITTER_ACCESS_TOKEN_KEY", &self.twitter.access_token_key),
+            ("TWITTER_ACCESS_TOKEN_SECRET", &self.twitter.access_token_secret),
+            ("TWITTER_USER_ID", &self.twitter.user_id),
+            ("MASTODON_INSTANCE", &self.mastodon.instance),
+            ("MASTODON_ACCESS_TOKEN", &self.mastodon.access_token),
+            ("MASTODON_ACCOUNT_ID", &self.mastodon.account_id),
+        ];
+
+        for (var, value) in vars {
+            if let Some(value) = value {
+                if env::var_os(var).is_none() {
+                    env::set_var(var, value);
+                }
+            }
+        }
+    }
+
+    /// The configured accounts, or if there are none, one per source that has
+    /// variables set in the environment.
+    ///
+    /// Call after `apply_env`, so the file’s `[twitter]` and `[mastodon]`
+    /// sections are taken into account.
+    pub fn accounts(&self) -> Vec<AccountConfig> {
+        if !self.accounts.is_empty() {
+            return self
+                .accounts
+                .iter()
+                .map(|account| {
+                    let defaults = AccountConfig::from_env(&account.name, account.source.clone());
+                    AccountConfig {
+                        consumer_key: account.consumer_key.clone().or(defaults.consumer_key),
+                        consumer_secret: account.consumer_secret.clone().or(defaults.consumer_secret),
+                        ..account.clone()
+                    }
+                })
+                .collect();
+        }
+
+        let mut accounts = Vec::new();
+
+        let twitter = AccountConfig {
+            id: env::var("TWITTER_USER_ID").ok(),
+            access_token_key: env::var("TWITTER_ACCESS_TOKEN_KEY").ok(),
+            access_token_secret: env::var("TWITTER_ACCESS_TOKEN_SECRET").ok(),
+            ..AccountConfig::from_env("twitter", Source::Twitter)
+        };
+        if twitter.id.is_some() || twitter.access_token_key.is_some() {
+            accounts.push(twitter);
+        }
+
+        let mastodon = AccountConfig {
+            id: env::var("MASTODON_ACCOUNT_ID").ok(),
+            instance: env::var("MASTODON_INSTANCE").ok(),
+            access_token: env::var("MASTODON_ACCESS_TOKEN").ok(),
+            ..AccountConfig::from_env("mastodon", Source::Mastodon)
+        };
+        if mastodon.id.is_some() || mastodon.instance.is_some() {
+            accounts.push(mastodon);
+        }
+
+        accounts
+    }
+
+    /// The blob store path: from the flag, the tool’s settings, the file, or
+    /// the default, in that order.
+    pub fn store(&self, tool: &Tool, flag: &Option<PathBuf>) -> Result<PathBuf, OmeletteError> {
+        if let Some(path) = flag {
+            return Ok(path.clone());
+        }
+
+        Ok(tool
+            .get::<PathBuf>("store")?
+            .or_else(|| self.store.clone())
+            .unwrap_or_else(|| DEFAULT_STORE.into()))
+    }
+
+    pub fn tool(&self, name: &'static str) -> Tool {
+        Tool {
+            name,
+            settings: self.tools.get(name).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Settings for one tool, from its `[tools.<name>]` table.
+#[derive(Clone, Debug)]
+pub struct Tool {
+    name: &'static str,
+    settings: Table,
+}
+
+impl Tool {
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, OmeletteError> {
+        match self.settings.get(key) {
+            None => Ok(None),
+            Some(value) => value.clone().try_into().map(Some).map_err(|err| {
+                OmeletteError::Config(format!("tools.{}.{}: {}", self.name, key, err))
+            }),
+        }
+    }
+
+    /// A flag is on if it was passed, or if the tool’s settings turn it on.
+    pub fn flag(&self, key: &str, passed: bool) -> Result<bool, OmeletteError> {
+        Ok(passed || self.get(key)?.unwrap_or(false))
+    }
+
+    /// An option’s value if it was passed, or from the tool’s settings.
+    pub fn or<T: Clone + DeserializeOwned>(
+        &self,
+        key: &str,
+        passed: &Option<T>,
+    ) -> Result<Option<T>, OmeletteError> {
+        match passed {
+            Some(value) => Ok(Some(value.clone())),
+            None => self.get(key),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! The scheduler behind `omelette-daemon`, which runs other tools in-process on
+//! intervals instead of leaving that to cron.
+//!
+//! The config is loaded once, at startup, and shared by every job: loading it
+//! sets environment variables, which isn’t safe to do from the job threads.
+//!
+//! ```toml
+//! [tools.daemon.jobs.sync]
+//! every = "5m"
+//! jitter = "30s"
+//!
+//! [tools.daemon.jobs.hydrate]
+//! every = "1d"
+//! args = ["twitter", "hydrate", "tweets"]
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use crate::cli::Command;
+use crate::config::Config;
+use crate::error::OmeletteError;
+use crate::policy::{duration, maybe_duration};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    panic, process,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+use structopt::StructOpt;
+
+pub const DEFAULT_STATUS_FILE: &str = "./omelette/daemon.json";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct JobConfig {
+    #[serde(deserialize_with = "duration")]
+    pub every: Duration,
+    /// Up to this much is added to each wait, so jobs don’t all fire at once.
+    /// Defaults to a tenth of `every`.
+    #[serde(default, deserialize_with = "maybe_duration")]
+    pub jitter: Option<Duration>,
+    /// Waits double after each failure in a row, up to this. Defaults to an hour.
+    #[serde(default, deserialize_with = "maybe_duration")]
+    pub max_backoff: Option<Duration>,
+    /// The tool and its arguments, as given to `omelette`. Defaults to the job
+    /// name.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl JobConfig {
+    fn every(every: Duration) -> Self {
+        Self {
+            every,
+            jitter: None,
+            max_backoff: None,
+            args: Vec::new(),
+        }
+    }
+}
+
+/// What runs when no jobs are configured: the usual cron setup.
+pub fn default_jobs() -> BTreeMap<String, JobConfig> {
+    let mut jobs = BTreeMap::new();
+    jobs.insert("sync".into(), JobConfig::every(Duration::minutes(5)));
+    jobs.insert("mediatise".into(), JobConfig::every(Duration::minutes(15)));
+    jobs.insert("cleanup".into(), JobConfig::every(Duration::hours(1)));
+    jobs.insert("delete".into(), JobConfig::every(Duration::minutes(5)));
+    jobs
+}
+
+#[derive(Debug)]
+pub struct Job {
+    pub name: String,
+    pub args: Vec<String>,
+    config: JobConfig,
+    command: Command,
+}
+
+impl Job {
+    /// Parses the job’s arguments up front, so a typo fails at startup rather
+    /// than at the first run.
+    pub fn new(name: &str, config: JobConfig) -> Result<Self, OmeletteError> {
+        let mut args = if config.args.is_empty() {
+            vec![name.to_string()]
+        } else {
+            config.args.clone()
+        };
+
+        if args[0] == "daemon" {
+            return Err(OmeletteError::Config(format!(
+                "job {}: the daemon cannot run itself",
+                name
+            )));
+        }
+
+        if let Some(arg) = args.iter().find(|arg| *arg == "--config" || *arg == "--dotenv") {
+            return Err(OmeletteError::Config(format!(
+                "job {}: jobs use the daemon’s config, {} can only be given to the daemon",
+                name, arg
+            )));
+        }
+
+        if config.every <= Duration::zero() {
+            return Err(OmeletteError::Config(format!("job {}: every must be positive", name)));
+        }
+
+        let argv = Some("omelette".to_string())
+            .into_iter()
+            .chain(args.iter().cloned());
+        let command = Command::from_iter_safe(argv)
+            .map_err(|err| OmeletteError::Config(format!("job {}: {}", name, err.message)))?;
+
+        args.insert(0, "omelette".into());
+        Ok(Self {
+            name: name.into(),
+            args,
+            config,
+            command,
+        })
+    }
+
+    pub fn every(&self) -> Duration {
+        self.config.every
+    }
+
+    fn jitter(&self) -> Duration {
+        self.config.jitter.unwrap_or_else(|| self.config.every / 10)
+    }
+
+    /// How long until the next run, after `failures` failed runs in a row.
+    fn wait(&self, failures: u32) -> Duration {
+        let every = self.config.every;
+        let max = self
+            .config
+            .max_backoff
+            .unwrap_or_else(|| Duration::hours(1))
+            .max(every);
+
+        let backoff = (0..failures.min(32)).fold(every, |wait, _| (wait * 2).min(max));
+        backoff + random_upto(self.jitter())
+    }
+
+    /// Runs the tool, catching panics so one bad run doesn’t take the daemon
+    /// down with it.
+    fn run(&self, config: &Config) -> Result<(), String> {
+        match panic::catch_unwind(panic::AssertUnwindSafe(|| self.command.run_with(config))) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => Err(format!("{}", err)),
+            Err(_) => Err("panicked".into()),
+        }
+    }
+}
+
+/// A random duration between zero and `max`. Spreading runs out doesn’t need
+/// good randomness, so this uses the hasher seeds from std.
+fn random_upto(max: Duration) -> Duration {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let ms = max.num_milliseconds();
+    if ms <= 0 {
+        return Duration::zero();
+    }
+
+    let random = RandomState::new().build_hasher().finish();
+    Duration::milliseconds((random % ms as u64) as i64)
+}
+
+/// Takes the job’s lock file, held until the file is dropped, or `None` if
+/// another process has it, like a second daemon on the same status file. Lock
+/// files go in a `.locks` directory next to the status file.
+fn lock(path: &Path, name: &str) -> Result<Option<File>, OmeletteError> {
+    let dir = path.with_extension("locks");
+    fs::create_dir_all(&dir)?;
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(dir.join(format!("{}.lock", name)))?;
+
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(Some(file)),
+        Err(ref err) if err.kind() == fs2::lock_contended_error().kind() => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct JobStatus {
+    pub args: Vec<String>,
+    pub running: bool,
+    pub runs: u64,
+    /// Failed runs in a row, which the wait backs off by.
+    pub failures: u32,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// The status file, rewritten whenever a job starts or finishes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub jobs: BTreeMap<String, JobStatus>,
+}
+
+impl DaemonStatus {
+    pub fn load(path: &Path) -> Result<Self, OmeletteError> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|err| {
+            OmeletteError::Data(format!("daemon status {}: {}", path.display(), err))
+        })
+    }
+
+    /// Writes to a temporary file first, so readers never see half a status.
+    fn save(&self, path: &Path) -> Result<(), OmeletteError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| OmeletteError::Data(format!("cannot write daemon status: {}", err)))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+type Shared = Arc<Mutex<DaemonStatus>>;
+
+fn update<F: FnOnce(&mut JobStatus)>(status: &Shared, path: &Path, name: &str, change: F) {
+    let mut status = status.lock().unwrap();
+    if let Some(job) = status.jobs.get_mut(name) {
+        change(job);
+    }
+
+    status.updated_at = Utc::now();
+    if let Err(err) = status.save(path) {
+        println!("!! Cannot write daemon status: {}", err);
+    }
+}
+
+/// Runs the jobs forever. Each job runs in its own thread, holding its lock
+/// file, and isn’t started again while it’s still running (here or in another
+/// daemon), however late that makes it.
+pub fn run(jobs: Vec<Job>, config: Config, path: &Path) -> Result<(), OmeletteError> {
+    let now = Utc::now();
+    let mut status = DaemonStatus {
+        pid: process::id(),
+        started_at: now,
+        updated_at: now,
+        jobs: BTreeMap::new(),
+    };
+
+    for job in &jobs {
+        status.jobs.insert(
+            job.name.clone(),
+            JobStatus {
+                args: job.args.clone(),
+                next_run_at: Some(now + random_upto(job.jitter())),
+                ..JobStatus::default()
+            },
+        );
+    }
+
+    status.save(path)?;
+    let status: Shared = Arc::new(Mutex::new(status));
+    let config = Arc::new(config);
+    let jobs: Vec<Arc<Job>> = jobs.into_iter().map(Arc::new).collect();
+
+    loop {
+        for job in &jobs {
+            let due = {
+                let status = status.lock().unwrap();
+                let state = &status.jobs[&job.name];
+                !state.running && state.next_run_at.map_or(true, |at| at <= Utc::now())
+            };
+
+            if !due {
+                continue;
+            }
+
+            match lock(path, &job.name) {
+                Ok(Some(lock)) => start(
+                    job.clone(),
+                    lock,
+                    config.clone(),
+                    status.clone(),
+                    path.to_path_buf(),
+                ),
+                Ok(None) => {
+                    println!("-- Job {} is already running elsewhere, skipping", job.name);
+                    update(&status, path, &job.name, |state| {
+                        state.next_run_at = Some(Utc::now() + job.wait(state.failures));
+                    });
+                }
+                Err(err) => {
+                    println!("!! Cannot lock job {}, skipping: {}", job.name, err);
+                    update(&status, path, &job.name, |state| {
+                        state.next_run_at = Some(Utc::now() + job.wait(state.failures));
+                    });
+                }
+            }
+        }
+
+        thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
+
+fn start(job: Arc<Job>, lock: File, config: Arc<Config>, status: Shared, path: PathBuf) {
+    update(&status, &path, &job.name, |state| {
+        state.running = true;
+        state.last_started_at = Some(Utc::now());
+    });
+    println!("\n=> Running job {}: {}", job.name, job.args.join(" "));
+
+    thread::spawn(move || {
+        let outcome = job.run(&config);
+
+        update(&status, &path, &job.name, |state| {
+            let now = Utc::now();
+            state.running = false;
+            state.runs += 1;
+            state.last_finished_at = Some(now);
+
+            match outcome {
+                Ok(()) => {
+                    state.failures = 0;
+                    state.last_success_at = Some(now);
+                    state.last_error = None;
+                }
+                Err(err) => {
+                    state.failures += 1;
+                    state.last_error = Some(err);
+                }
+            }
+
+            state.next_run_at = Some(now + job.wait(state.failures));
+
+            match state.last_error {
+                None => println!("=> Job {} done, next run at {}", job.name, state.next_run_at.unwrap()),
+                Some(ref err) => println!(
+                    "!! Job {} failed ({} in a row), retrying at {}: {}",
+                    job.name, state.failures, state.next_run_at.unwrap(), err
+                ),
+            }
+        });
+
+        drop(lock);
+    });
+}
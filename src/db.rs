@@ -0,0 +1,318 @@
+//! What differs between the Postgres and SQLite backends, so the rest of
+//! omelette can be written once for either.
+//!
+//! The backend is picked at build time with the `postgres` (default) or
+//! `sqlite` feature.
+
+use crate::error::OmeletteError;
+use crate::inserts::{
+    NewEntityVariant, NewHashtag, NewMention, NewStatus, NewTwitterUser, NewTwitterUserID, NewUrl,
+    TextEntities,
+};
+use crate::models::{Status, TwitterUser};
+use crate::types::Source;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use std::{
+    collections::{BTreeSet, HashMap},
+    env,
+};
+
+#[cfg(all(feature = "postgres", feature = "sqlite"))]
+compile_error!("the postgres and sqlite features are exclusive, use --no-default-features");
+
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("one of the postgres or sqlite features is required");
+
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::pg::PgConnection;
+
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::sqlite::SqliteConnection;
+
+/// Connects to `DATABASE_URL`, which is a file path for SQLite.
+pub fn connect() -> Result<DbConnection, OmeletteError> {
+    let database_url = env::var("DATABASE_URL")
+        .map_err(|err| OmeletteError::Env("DATABASE_URL", err))?;
+    let conn = DbConnection::establish(&database_url)?;
+
+    #[cfg(feature = "sqlite")]
+    conn.execute("PRAGMA foreign_keys = ON")?;
+
+    Ok(conn)
+}
+
+/// Inserts, skipping rows that conflict with existing ones.
+///
+/// That’s `ON CONFLICT (target) DO NOTHING` on Postgres, and `INSERT OR IGNORE`
+/// on SQLite, where the target isn’t used. Finish with `.execute(conn)`, which
+/// returns how many rows were inserted on both.
+#[cfg(feature = "postgres")]
+#[macro_export]
+macro_rules! insert_or_ignore {
+    ($table:expr, $values:expr, $target:expr) => {
+        diesel::insert_into($table)
+            .values($values)
+            .on_conflict($target)
+            .do_nothing()
+    };
+}
+
+#[cfg(feature = "sqlite")]
+#[macro_export]
+macro_rules! insert_or_ignore {
+    ($table:expr, $values:expr, $target:expr) => {
+        diesel::insert_or_ignore_into($table).values($values)
+    };
+}
+
+/// Inserts statuses, skipping those already stored, and returns the new ones.
+///
+/// SQLite has no `RETURNING`, so this looks up what’s there first instead.
+pub fn insert_new_statuses(
+    conn: &DbConnection,
+    bag: &[NewStatus],
+) -> Result<Vec<Status>, DieselError> {
+    use crate::schema::statuses::dsl::*;
+
+    if bag.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Source IDs are only unique within a source, so statuses are told apart
+    // by both.
+    let key = |s: &NewStatus| (s.source.clone(), s.source_id.clone());
+
+    conn.transaction(|| {
+        let existing: Vec<(Source, String)> = statuses
+            .select((source, source_id))
+            .filter(source_id.eq_any(bag.iter().map(|s| s.source_id.clone())))
+            .load(conn)?;
+
+        let new: Vec<NewStatus> = bag
+            .iter()
+            .filter(|s| !existing.contains(&key(*s)))
+            .cloned()
+            .collect();
+
+        if new.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        insert_or_ignore!(statuses, &new, (source, source_id)).execute(conn)?;
+
+        let keys: Vec<(Source, String)> = new.iter().map(key).collect();
+        Ok(statuses
+            .filter(source_id.eq_any(new.iter().map(|s| s.source_id.clone())))
+            .order_by(id)
+            .load::<Status>(conn)?
+            .into_iter()
+            .filter(|s| keys.contains(&(s.source.clone(), s.source_id.clone())))
+            .collect())
+    })
+}
+
+/// Inserts Twitter users, or refreshes the profiles of those already stored,
+/// and returns the internal IDs of all of them by source ID.
+pub fn upsert_twitter_users(
+    conn: &DbConnection,
+    bag: &[NewTwitterUser],
+) -> Result<HashMap<String, i32>, DieselError> {
+    use crate::schema::twitter_users::dsl::*;
+
+    if bag.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    conn.transaction(|| {
+        let existing: HashMap<String, TwitterUser> = twitter_users
+            .filter(source_id.eq_any(bag.iter().map(|u| u.source_id.clone())))
+            .load::<TwitterUser>(conn)?
+            .into_iter()
+            .map(|user| (user.source_id.clone(), user))
+            .collect();
+
+        let mut new = Vec::new();
+        for user in bag {
+            match existing.get(&user.source_id) {
+                Some(stored) => {
+                    diesel::update(twitter_users.find(stored.id))
+                        .set(user)
+                        .execute(conn)?;
+                }
+                None => new.push(user.clone()),
+            }
+        }
+
+        if !new.is_empty() {
+            insert_or_ignore!(twitter_users, &new, source_id).execute(conn)?;
+        }
+
+        Ok(twitter_users
+            .select((source_id, id))
+            .filter(source_id.eq_any(bag.iter().map(|u| u.source_id.clone())))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .collect())
+    })
+}
+
+/// Inserts the variants of entities from a source, keyed by the entities’
+/// source IDs, and returns how many were new. Variants of entities that aren’t
+/// stored are skipped, and those already stored are left alone.
+pub fn insert_variants(
+    conn: &DbConnection,
+    src: &Source,
+    bag: &HashMap<String, Vec<NewEntityVariant>>,
+) -> Result<usize, DieselError> {
+    use crate::schema::{entities, entity_variants::dsl::*};
+
+    if bag.is_empty() {
+        return Ok(0);
+    }
+
+    let stored: Vec<(i32, String)> = entities::table
+        .select((entities::id, entities::source_id))
+        .filter(entities::source.eq(src))
+        .filter(entities::source_id.eq_any(bag.keys().cloned()))
+        .load(conn)?;
+
+    let mut sack = Vec::new();
+    for (eid, esource_id) in stored {
+        for variant in &bag[&esource_id] {
+            sack.push(NewEntityVariant {
+                entity_id: eid,
+                ..variant.clone()
+            });
+        }
+    }
+
+    if sack.is_empty() {
+        return Ok(0);
+    }
+
+    insert_or_ignore!(entity_variants, &sack, (entity_id, url)).execute(conn)
+}
+
+/// Inserts the hashtags, mentions, and links of tweets, keyed by the
+/// statuses’ source IDs, and returns how many were new. Mentioned users are
+/// added as slim users if they aren’t known yet, to be hydrated later.
+pub fn insert_text_entities(
+    conn: &DbConnection,
+    bag: &HashMap<String, TextEntities>,
+) -> Result<usize, DieselError> {
+    use crate::schema::{hashtags, mentions, statuses, twitter_users, urls};
+
+    if bag.is_empty() {
+        return Ok(0);
+    }
+
+    let stored: Vec<(i32, String)> = statuses::table
+        .select((statuses::id, statuses::source_id))
+        .filter(statuses::source.eq(Source::Twitter))
+        .filter(statuses::source_id.eq_any(bag.keys().cloned()))
+        .load(conn)?;
+
+    let mentioned: Vec<NewTwitterUserID> = stored
+        .iter()
+        .flat_map(|(_, ssource_id)| bag[ssource_id].mentions.iter())
+        .filter_map(|(user, _)| user.parse::<u64>().ok())
+        .collect::<BTreeSet<u64>>()
+        .into_iter()
+        .map(NewTwitterUserID::from)
+        .collect();
+
+    let users: HashMap<String, i32> = if mentioned.is_empty() {
+        HashMap::new()
+    } else {
+        insert_or_ignore!(twitter_users::table, &mentioned, twitter_users::source_id)
+            .execute(conn)?;
+        twitter_users::table
+            .select((twitter_users::source_id, twitter_users::id))
+            .filter(twitter_users::source_id.eq_any(mentioned.iter().map(|u| u.source_id.clone())))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut tagsack = Vec::new();
+    let mut mentionsack = Vec::new();
+    let mut urlsack = Vec::new();
+    for (sid, ssource_id) in &stored {
+        let ents = &bag[ssource_id];
+        tagsack.extend(ents.hashtags.iter().map(|tag| NewHashtag {
+            status_id: *sid,
+            ..tag.clone()
+        }));
+        mentionsack.extend(ents.mentions.iter().filter_map(|(user, mention)| {
+            users.get(user).map(|uid| NewMention {
+                status_id: *sid,
+                user_id: *uid,
+                ..mention.clone()
+            })
+        }));
+        urlsack.extend(ents.urls.iter().map(|link| NewUrl {
+            status_id: *sid,
+            ..link.clone()
+        }));
+    }
+
+    let mut inserted = 0;
+    if !tagsack.is_empty() {
+        inserted += insert_or_ignore!(
+            hashtags::table,
+            &tagsack,
+            (hashtags::status_id, hashtags::start_index)
+        )
+        .execute(conn)?;
+    }
+    if !mentionsack.is_empty() {
+        inserted += insert_or_ignore!(
+            mentions::table,
+            &mentionsack,
+            (mentions::status_id, mentions::start_index)
+        )
+        .execute(conn)?;
+    }
+    if !urlsack.is_empty() {
+        inserted += insert_or_ignore!(
+            urls::table,
+            &urlsack,
+            (urls::status_id, urls::start_index)
+        )
+        .execute(conn)?;
+    }
+
+    Ok(inserted)
+}
+
+/// A migrated database for tests.
+///
+/// On SQLite that’s a fresh in-memory database. On Postgres it’s
+/// `DATABASE_URL` inside a transaction that is never committed, so tests
+/// leave nothing behind.
+#[cfg(test)]
+pub fn test_connection() -> DbConnection {
+    use std::{io, path::Path};
+
+    #[cfg(feature = "postgres")]
+    let (conn, dir) = (
+        connect().expect("tests on postgres need DATABASE_URL"),
+        "migrations",
+    );
+
+    #[cfg(feature = "sqlite")]
+    let (conn, dir) = {
+        let conn = DbConnection::establish(":memory:").unwrap();
+        conn.execute("PRAGMA foreign_keys = ON").unwrap();
+        (conn, "migrations-sqlite")
+    };
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    diesel_migrations::run_pending_migrations_in_directory(&conn, &dir, &mut io::sink()).unwrap();
+
+    #[cfg(feature = "postgres")]
+    conn.begin_test_transaction().unwrap();
+
+    conn
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use crate::inserts::{NewEntity, NewEntityVariant};
+use crate::models::{Deletion, Entity, EntityVariant};
+use crate::types::{MediaType, Source};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when a change to the records would trip up older importers.
+pub const FORMAT: u32 = 1;
+
+pub const MANIFEST: &str = "manifest.json";
+pub const STATUSES: &str = "statuses.jsonl";
+pub const ENTITIES: &str = "entities.jsonl";
+pub const ENTITY_VARIANTS: &str = "entity_variants.jsonl";
+pub const DELETIONS: &str = "deletions.jsonl";
+pub const TWITTER_USERS: &str = "twitter_users.jsonl";
+
+/// Describes a dump, written last so an interrupted export is obvious.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub omelette: String,
+    pub exported_at: DateTime<Utc>,
+    pub statuses: usize,
+    pub entities: usize,
+    /// Dumps from before variants were recorded don’t have any.
+    #[serde(default)]
+    pub entity_variants: usize,
+    pub deletions: usize,
+    pub twitter_users: usize,
+    pub media: bool,
+}
+
+// Statuses and Twitter users are dumped as their `New*` inserts. Internal IDs
+// aren’t portable between databases, so other records refer to statuses by
+// source and source ID instead, and variants to their entity likewise.
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EntityRecord {
+    /// Dumps from before source IDs were unique per source only don’t have it.
+    #[serde(default)]
+    pub status_source: Option<Source>,
+    pub status_source_id: String,
+    pub fetched_at: DateTime<Utc>,
+    pub ordering: Option<i32>,
+    pub media_type: MediaType,
+    pub source_id: String,
+    pub source_url: String,
+    pub original_status_source_id: Option<String>,
+    pub original_status_source_url: Option<String>,
+    pub blob_hash: Option<String>,
+    /// Dumps from before alt text was recorded don’t have it.
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    /// Path to the media file, relative to the dump, if it was included.
+    pub media_path: Option<String>,
+}
+
+impl EntityRecord {
+    pub fn new(entity: &Entity, status_source: &Source, status_source_id: &str) -> Self {
+        Self {
+            status_source: Some(status_source.clone()),
+            status_source_id: status_source_id.into(),
+            fetched_at: entity.fetched_at,
+            ordering: entity.ordering,
+            media_type: entity.media_type.clone(),
+            source_id: entity.source_id.clone(),
+            source_url: entity.source_url.clone(),
+            original_status_source_id: entity.original_status_source_id.clone(),
+            original_status_source_url: entity.original_status_source_url.clone(),
+            blob_hash: entity.blob_hash.clone(),
+            alt_text: entity.alt_text.clone(),
+            media_path: None,
+        }
+    }
+
+    pub fn to_insert(&self, status_id: i32, source: Source) -> NewEntity {
+        NewEntity {
+            fetched_at: self.fetched_at,
+            status_id,
+            ordering: self.ordering,
+            media_type: self.media_type.clone(),
+            source_id: self.source_id.clone(),
+            source_url: self.source_url.clone(),
+            original_status_source_id: self.original_status_source_id.clone(),
+            original_status_source_url: self.original_status_source_url.clone(),
+            alt_text: self.alt_text.clone(),
+            source,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VariantRecord {
+    /// Dumps from before media IDs were unique per source only don’t have it.
+    #[serde(default)]
+    pub entity_source: Option<Source>,
+    pub entity_source_id: String,
+    pub kind: String,
+    pub content_type: Option<String>,
+    pub bitrate: Option<i32>,
+    pub url: String,
+    pub blob_hash: Option<String>,
+    /// Path to the media file, relative to the dump, if it was included.
+    pub media_path: Option<String>,
+}
+
+impl VariantRecord {
+    pub fn new(variant: &EntityVariant, entity_source: &Source, entity_source_id: &str) -> Self {
+        Self {
+            entity_source: Some(entity_source.clone()),
+            entity_source_id: entity_source_id.into(),
+            kind: variant.kind.clone(),
+            content_type: variant.content_type.clone(),
+            bitrate: variant.bitrate,
+            url: variant.url.clone(),
+            blob_hash: variant.blob_hash.clone(),
+            media_path: None,
+        }
+    }
+
+    pub fn to_insert(&self, entity_id: i32) -> NewEntityVariant {
+        NewEntityVariant {
+            entity_id,
+            kind: self.kind.clone(),
+            content_type: self.content_type.clone(),
+            bitrate: self.bitrate,
+            url: self.url.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeletionRecord {
+    /// Dumps from before source IDs were unique per source only don’t have it.
+    #[serde(default)]
+    pub status_source: Option<Source>,
+    pub status_source_id: String,
+    pub created_at: DateTime<Utc>,
+    pub not_before: DateTime<Utc>,
+    pub executed_at: Option<DateTime<Utc>>,
+    pub sponsor: String,
+}
+
+impl DeletionRecord {
+    pub fn new(deletion: &Deletion, status_source: &Source, status_source_id: &str) -> Self {
+        Self {
+            status_source: Some(status_source.clone()),
+            status_source_id: status_source_id.into(),
+            created_at: deletion.created_at,
+            not_before: deletion.not_before,
+            executed_at: deletion.executed_at,
+            sponsor: deletion.sponsor.clone(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::sources::{DeleteError, LoadError};
+use diesel::{result::Error as DieselError, ConnectionError};
+use diesel_migrations::RunMigrationsError;
+use egg_mode::error::Error as EggError;
+use reqwest::Error as ReqwestError;
+use std::{env::VarError, fmt, io, process};
+
+#[derive(Debug)]
+pub enum OmeletteError {
+    Env(&'static str, VarError),
+    Config(String),
+    Connection(ConnectionError),
+    Database(DieselError),
+    Migration(RunMigrationsError),
+    Twitter(EggError),
+    Mastodon(ReqwestError),
+    Io(io::Error),
+    Data(String),
+    Delete(DeleteError),
+}
+
+impl OmeletteError {
+    /// Each class of failure exits with its own code, so timers and crons can
+    /// tell a broken config from a flaky API.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            OmeletteError::Env(_, _) | OmeletteError::Config(_) => 2,
+            OmeletteError::Connection(_) => 3,
+            OmeletteError::Database(_) | OmeletteError::Migration(_) => 4,
+            OmeletteError::Twitter(_) => 5,
+            OmeletteError::Mastodon(_) => 6,
+            OmeletteError::Io(_) => 7,
+            OmeletteError::Data(_) => 8,
+            OmeletteError::Delete(DeleteError::Database(_)) => 4,
+            OmeletteError::Delete(DeleteError::Twitter(_)) => 5,
+            OmeletteError::Delete(DeleteError::Mastodon(_)) => 6,
+            OmeletteError::Delete(DeleteError::Other(err)) => err.exit_code(),
+            OmeletteError::Delete(_) => 1,
+        }
+    }
+
+    /// Prints the error and exits with its code, for use in binaries.
+    pub fn exit(self) -> ! {
+        println!("!! {}", self);
+        process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for OmeletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OmeletteError::Env(var, err) => write!(f, "{} must be set: {}", var, err),
+            OmeletteError::Config(msg) => write!(f, "Bad configuration: {}", msg),
+            OmeletteError::Connection(err) => write!(f, "Cannot connect to database: {}", err),
+            OmeletteError::Database(err) => write!(f, "Database error: {}", err),
+            OmeletteError::Migration(err) => write!(f, "Cannot run migrations: {}", err),
+            OmeletteError::Twitter(err) => write!(f, "Twitter error: {}", err),
+            OmeletteError::Mastodon(err) => write!(f, "Mastodon error: {}", err),
+            OmeletteError::Io(err) => write!(f, "I/O error: {}", err),
+            OmeletteError::Data(msg) => write!(f, "Bad data: {}", msg),
+            OmeletteError::Delete(err) => write!(f, "Cannot delete: {:?}", err),
+        }
+    }
+}
+
+impl From<ConnectionError> for OmeletteError {
+    fn from(err: ConnectionError) -> OmeletteError {
+        OmeletteError::Connection(err)
+    }
+}
+
+impl From<DieselError> for OmeletteError {
+    fn from(err: DieselError) -> OmeletteError {
+        OmeletteError::Database(err)
+    }
+}
+
+impl From<RunMigrationsError> for OmeletteError {
+    fn from(err: RunMigrationsError) -> OmeletteError {
+        OmeletteError::Migration(err)
+    }
+}
+
+impl From<EggError> for OmeletteError {
+    fn from(err: EggError) -> OmeletteError {
+        OmeletteError::Twitter(err)
+    }
+}
+
+impl From<ReqwestError> for OmeletteError {
+    fn from(err: ReqwestError) -> OmeletteError {
+        OmeletteError::Mastodon(err)
+    }
+}
+
+impl From<io::Error> for OmeletteError {
+    fn from(err: io::Error) -> OmeletteError {
+        OmeletteError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for OmeletteError {
+    fn from(err: serde_json::Error) -> OmeletteError {
+        OmeletteError::Data(format!("{}", err))
+    }
+}
+
+impl From<DeleteError> for OmeletteError {
+    fn from(err: DeleteError) -> OmeletteError {
+        OmeletteError::Delete(err)
+    }
+}
+
+impl From<LoadError> for OmeletteError {
+    fn from(err: LoadError) -> OmeletteError {
+        match err {
+            LoadError::Missing(account, field) => {
+                OmeletteError::Config(format!("account {}: {} is missing", account, field))
+            }
+            LoadError::Parse(account, field) => {
+                OmeletteError::Config(format!("account {}: {} is malformed", account, field))
+            }
+        }
+    }
+}
+
+/// Parses an opaque source ID that should be numeric.
+pub fn parse_id(sid: &str) -> Result<u64, OmeletteError> {
+    sid.parse()
+        .map_err(|_| OmeletteError::Data(format!("source ID {:?} is not numeric", sid)))
+}
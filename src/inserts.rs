@@ -1,20 +1,27 @@
 #![allow(proc_macro_derive_resolution_fallback)]
 
 use chrono::prelude::*;
-use crate::models::Status;
+use crate::archive;
+use crate::models::{Status, TwitterUser};
 use crate::schema::*;
+use crate::sources::mastodon::{self, MastodonStatus};
 use crate::types::*;
 use egg_mode::{
     entities::MediaEntity,
     tweet::{ExtendedTweetEntities, Tweet},
     user::TwitterUser as EggUser,
 };
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(AsChangeset, Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[derive(AsChangeset, Clone, Debug, Deserialize, Insertable, PartialEq, PartialOrd, Serialize)]
 #[table_name = "statuses"]
 #[changeset_options(treat_none_as_null="true")]
 pub struct NewStatus {
     pub text: String,
+    /// Not dumped, as internal IDs aren’t portable: imported tweets are linked
+    /// to their author again from `source_author`.
+    #[serde(skip)]
     pub author_id: Option<i32>,
     pub geolocation_lat: Option<f64>,
     pub geolocation_lon: Option<f64>,
@@ -34,6 +41,10 @@ pub struct NewStatus {
     pub in_reply_to_user: Option<String>,
     pub quoting_status: Option<String>,
     pub public: bool,
+    /// Not dumped, as internal IDs aren’t portable: imported statuses are
+    /// claimed again with `omelette-migrate-db --claim`.
+    #[serde(skip)]
+    pub account_id: Option<i32>,
 }
 
 impl From<&Tweet> for NewStatus {
@@ -82,14 +93,9 @@ impl From<&Tweet> for NewStatus {
             } else {
                 None
             },
-            in_reply_to_user: if let Some(ref name) = otweet.in_reply_to_screen_name {
-                Some(format!(
-                    "{} <@{}>",
-                    name,
-                    otweet.in_reply_to_user_id.unwrap()
-                ))
-            } else {
-                None
+            in_reply_to_user: match (&otweet.in_reply_to_screen_name, otweet.in_reply_to_user_id) {
+                (Some(name), Some(uid)) => Some(format!("{} <@{}>", name, uid)),
+                _ => None,
             },
             quoting_status: if let Some(id) = otweet.quoted_status_id {
                 Some(format!("{}", id))
@@ -105,6 +111,132 @@ impl From<&Tweet> for NewStatus {
                 );
                 false
             },
+            account_id: None,
+        }
+    }
+}
+
+impl From<&Status> for NewStatus {
+    fn from(status: &Status) -> NewStatus {
+        NewStatus {
+            text: status.text.clone(),
+            author_id: status.author_id,
+            geolocation_lat: status.geolocation_lat,
+            geolocation_lon: status.geolocation_lon,
+            posted_at: status.posted_at,
+            fetched_at: status.fetched_at,
+            fetched_via: status.fetched_via.clone(),
+            deleted_at: status.deleted_at,
+            is_repost: status.is_repost,
+            reposted_at: status.reposted_at,
+            is_marked: status.is_marked,
+            marked_at: status.marked_at,
+            source: status.source.clone(),
+            source_id: status.source_id.clone(),
+            source_author: status.source_author.clone(),
+            source_app: status.source_app.clone(),
+            in_reply_to_status: status.in_reply_to_status.clone(),
+            in_reply_to_user: status.in_reply_to_user.clone(),
+            quoting_status: status.quoting_status.clone(),
+            public: status.public,
+            account_id: status.account_id,
+        }
+    }
+}
+
+impl NewStatus {
+    pub fn from_archive(tweet: &archive::Tweet, account: &archive::Account) -> NewStatus {
+        let (lat, lon) = match tweet.coordinates {
+            Some(ref c) if c.coordinates.len() == 2 => {
+                (c.coordinates[1].parse().ok(), c.coordinates[0].parse().ok())
+            }
+            _ => (None, None),
+        };
+
+        NewStatus {
+            text: tweet.full_text.clone(),
+            author_id: None,
+            geolocation_lat: lat,
+            geolocation_lon: lon,
+            posted_at: tweet.created_at,
+            fetched_at: Utc::now(),
+            fetched_via: Some(IntermediarySource::TwitterArchive),
+            deleted_at: None,
+            is_repost: tweet.is_retweet(),
+            // The archive only has the retweet’s own date, the original’s is
+            // filled in when hydrated.
+            reposted_at: if tweet.is_retweet() {
+                Some(tweet.created_at)
+            } else {
+                None
+            },
+            is_marked: tweet.favorited,
+            marked_at: None,
+            source: Source::Twitter,
+            source_id: tweet.id_str.clone(),
+            // Retweets only have their own ID and the truncated text in the
+            // archive, so leave them to be hydrated from the API.
+            source_author: if tweet.is_retweet() {
+                crate::SLIM_MARK.into()
+            } else {
+                account.source_author()
+            },
+            source_app: archive::parse_source_app(&tweet.source),
+            in_reply_to_status: tweet.in_reply_to_status_id_str.clone(),
+            in_reply_to_user: match (
+                &tweet.in_reply_to_screen_name,
+                &tweet.in_reply_to_user_id_str,
+            ) {
+                (Some(name), Some(uid)) => Some(format!("{} <@{}>", name, uid)),
+                _ => None,
+            },
+            quoting_status: tweet.quoted_status_id_str.clone(),
+            public: account.protected == Some(false),
+            account_id: None,
+        }
+    }
+}
+
+impl From<&MastodonStatus> for NewStatus {
+    fn from(status: &MastodonStatus) -> NewStatus {
+        let (is_repost, ostatus) = match status.reblog {
+            None => (false, status),
+            Some(ref st) => (true, &**st),
+        };
+
+        NewStatus {
+            text: mastodon::plain_text(&ostatus.content),
+            author_id: None,
+            geolocation_lat: None,
+            geolocation_lon: None,
+            posted_at: ostatus.created_at,
+            fetched_at: Utc::now(),
+            fetched_via: None,
+            deleted_at: None,
+            is_repost,
+            reposted_at: if is_repost {
+                Some(status.created_at)
+            } else {
+                None
+            },
+            is_marked: ostatus.favourited.unwrap_or(false),
+            marked_at: None,
+            source: Source::Mastodon,
+            source_id: ostatus.id.clone(),
+            source_author: format!(
+                "\"{}\" <@{}> ({})",
+                ostatus.account.display_name, ostatus.account.acct, ostatus.account.id
+            ),
+            source_app: if let Some(ref app) = ostatus.application {
+                format!("{} <{}>", app.name, app.website.clone().unwrap_or_default())
+            } else {
+                "".into()
+            },
+            in_reply_to_status: ostatus.in_reply_to_id.clone(),
+            in_reply_to_user: ostatus.in_reply_to_account_id.clone(),
+            quoting_status: None,
+            public: ostatus.visibility == "public" || ostatus.visibility == "unlisted",
+            account_id: None,
         }
     }
 }
@@ -120,34 +252,41 @@ pub struct NewEntity {
     pub source_url: String,
     pub original_status_source_id: Option<String>,
     pub original_status_source_url: Option<String>,
+    pub alt_text: Option<String>,
+    pub source: Source,
 }
 
-impl From<&MediaEntity> for NewEntity {
-    fn from(ent: &MediaEntity) -> NewEntity {
-        let media_type = (&ent.media_type).into();
+impl NewEntity {
+    /// The entity for a tweet’s media, or `None` (and a note) for videos and
+    /// gifs that come without their video info.
+    pub fn from_media(ent: &MediaEntity) -> Option<NewEntity> {
+        let media_type: MediaType = (&ent.media_type).into();
+        let variants = match (&media_type, &ent.video_info) {
+            (MediaType::Photo, _) => Vec::new(),
+            (_, Some(info)) => info.variants.clone(),
+            (_, None) => {
+                println!("~~ Skipping {:?} media {} without video info", media_type, ent.id);
+                return None;
+            }
+        };
+
         let source_url = match media_type {
             MediaType::Photo => ent.media_url_https.clone(),
-            MediaType::Gif => ent
-                .video_info
-                .clone()
-                .unwrap()
-                .variants
+            MediaType::Gif => match variants.iter().find(|v| v.bitrate == Some(0)).or(variants.first()) {
+                Some(v) => v.url.clone(),
+                None => {
+                    println!("~~ Skipping gif media {} without variants", ent.id);
+                    return None;
+                }
+            },
+            MediaType::Video => variants
                 .iter()
-                .find(|v| v.bitrate == Some(0))
-                .expect("!! GIF entity not in expected format")
-                .url
-                .clone(),
-            MediaType::Video => {
-                let mut variants = ent.video_info.clone().unwrap().variants;
-                variants.sort_unstable_by_key(|v| v.bitrate.unwrap_or(0));
-                variants
-                    .last()
-                    .map(|v| v.url.clone())
-                    .unwrap_or(ent.media_url_https.clone())
-            }
+                .max_by_key(|v| v.bitrate.unwrap_or(0))
+                .map(|v| v.url.clone())
+                .unwrap_or(ent.media_url_https.clone()),
         };
 
-        NewEntity {
+        Some(NewEntity {
             fetched_at: Utc::now(),
             status_id: 0,
             ordering: None,
@@ -156,24 +295,359 @@ impl From<&MediaEntity> for NewEntity {
             source_url,
             original_status_source_id: ent.source_status_id.map(|id| format!("{}", id)),
             original_status_source_url: Some(ent.url.clone()),
-        }
+            alt_text: ent.ext_alt_text.clone(),
+            source: Source::Twitter,
+        })
     }
-}
 
-impl NewEntity {
     pub fn from_extended(ents: &ExtendedTweetEntities) -> Vec<NewEntity> {
         ents.media
             .iter()
             .enumerate()
-            .map(|(i, ent)| {
-                let mut new_ent: NewEntity = ent.into();
+            .filter_map(|(i, ent)| {
+                let mut new_ent = NewEntity::from_media(ent)?;
                 new_ent.ordering = Some(i as i32);
-                new_ent
+                Some(new_ent)
+            })
+            .collect()
+    }
+
+    pub fn from_archive(ents: &archive::ExtendedEntities) -> Vec<NewEntity> {
+        ents.media
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ent)| {
+                let media_type = match ent.kind.as_str() {
+                    "photo" => MediaType::Photo,
+                    "video" => MediaType::Video,
+                    "animated_gif" => MediaType::Gif,
+                    _ => {
+                        println!("~~ Skipping unsupported {} media {}", ent.kind, ent.id_str);
+                        return None;
+                    }
+                };
+
+                let variants = ent
+                    .video_info
+                    .as_ref()
+                    .map(|v| v.variants.clone())
+                    .unwrap_or_default();
+
+                let source_url = match media_type {
+                    MediaType::Photo => ent.media_url_https.clone(),
+                    MediaType::Gif => variants
+                        .iter()
+                        .find(|v| v.bitrate() == Some(0))
+                        .or(variants.first())
+                        .map(|v| v.url.clone())
+                        .unwrap_or(ent.media_url_https.clone()),
+                    MediaType::Video => variants
+                        .iter()
+                        .max_by_key(|v| v.bitrate().unwrap_or(0))
+                        .map(|v| v.url.clone())
+                        .unwrap_or(ent.media_url_https.clone()),
+                };
+
+                Some(NewEntity {
+                    fetched_at: Utc::now(),
+                    status_id: 0,
+                    ordering: Some(i as i32),
+                    media_type,
+                    source_id: ent.id_str.clone(),
+                    source_url,
+                    original_status_source_id: ent.source_status_id_str.clone(),
+                    original_status_source_url: Some(ent.url.clone()),
+                    alt_text: ent.ext_alt_text.clone(),
+                    source: Source::Twitter,
+                })
+            })
+            .collect()
+    }
+
+    pub fn from_attachments(status: &MastodonStatus) -> Vec<NewEntity> {
+        let ostatus = match status.reblog {
+            None => status,
+            Some(ref st) => &**st,
+        };
+
+        ostatus
+            .media_attachments
+            .iter()
+            .enumerate()
+            .filter_map(|(i, att)| {
+                let media_type = match att.kind.as_str() {
+                    "image" => MediaType::Photo,
+                    "video" => MediaType::Video,
+                    "gifv" => MediaType::Gif,
+                    _ => {
+                        println!("~~ Skipping unsupported {} attachment {}", att.kind, att.id);
+                        return None;
+                    }
+                };
+
+                Some(NewEntity {
+                    fetched_at: Utc::now(),
+                    status_id: 0,
+                    ordering: Some(i as i32),
+                    media_type,
+                    source_id: att.id.clone(),
+                    source_url: att.remote_url.clone().unwrap_or(att.url.clone()),
+                    original_status_source_id: None,
+                    original_status_source_url: ostatus.url.clone(),
+                    alt_text: att.description.clone(),
+                    source: Source::Mastodon,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A variant of an entity’s media: one of the encodings of a video or gif, or
+/// the still image shown before it plays. Variants are made before their
+/// entity is stored, so they come keyed by its source ID, see
+/// `db::insert_variants`.
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "entity_variants"]
+pub struct NewEntityVariant {
+    pub entity_id: i32,
+    pub kind: String,
+    pub content_type: Option<String>,
+    pub bitrate: Option<i32>,
+    pub url: String,
+}
+
+impl NewEntityVariant {
+    fn video(content_type: Option<String>, bitrate: Option<i32>, url: &str) -> Self {
+        Self {
+            entity_id: 0,
+            kind: "video".into(),
+            content_type,
+            bitrate,
+            url: url.into(),
+        }
+    }
+
+    fn poster(url: &str) -> Self {
+        Self {
+            entity_id: 0,
+            kind: "poster".into(),
+            content_type: None,
+            bitrate: None,
+            url: url.into(),
+        }
+    }
+
+    /// Photos have no variants: they are their own poster.
+    pub fn from_extended(ents: &ExtendedTweetEntities) -> HashMap<String, Vec<Self>> {
+        ents.media
+            .iter()
+            .filter_map(|ent| {
+                let info = ent.video_info.as_ref()?;
+                let mut variants: Vec<Self> = info
+                    .variants
+                    .iter()
+                    .map(|v| Self::video(Some(v.content_type.to_string()), v.bitrate, &v.url))
+                    .collect();
+                variants.push(Self::poster(&ent.media_url_https));
+                Some((format!("{}", ent.id), variants))
+            })
+            .collect()
+    }
+
+    pub fn from_archive(ents: &archive::ExtendedEntities) -> HashMap<String, Vec<Self>> {
+        ents.media
+            .iter()
+            .filter_map(|ent| {
+                let info = ent.video_info.as_ref()?;
+                let mut variants: Vec<Self> = info
+                    .variants
+                    .iter()
+                    .map(|v| Self::video(Some(v.content_type.clone()), v.bitrate(), &v.url))
+                    .collect();
+                variants.push(Self::poster(&ent.media_url_https));
+                Some((ent.id_str.clone(), variants))
+            })
+            .collect()
+    }
+
+    /// Mastodon serves one encoding, and a preview for the poster.
+    pub fn from_attachments(status: &MastodonStatus) -> HashMap<String, Vec<Self>> {
+        let ostatus = match status.reblog {
+            None => status,
+            Some(ref st) => &**st,
+        };
+
+        ostatus
+            .media_attachments
+            .iter()
+            .filter(|att| att.kind == "video" || att.kind == "gifv")
+            .map(|att| {
+                let url = att.remote_url.as_ref().unwrap_or(&att.url);
+                let mut variants = vec![Self::video(None, None, url)];
+                if let Some(ref preview) = att.preview_url {
+                    variants.push(Self::poster(preview));
+                }
+
+                (att.id.clone(), variants)
             })
             .collect()
     }
 }
 
+/// What’s known of an entity’s media once it’s downloaded, see `media`.
+#[derive(AsChangeset, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[table_name = "entities"]
+#[changeset_options(treat_none_as_null="true")]
+pub struct MediaMetadata {
+    pub mime_type: Option<String>,
+    pub byte_size: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_ms: Option<i32>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "hashtags"]
+pub struct NewHashtag {
+    pub status_id: i32,
+    pub text: String,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "mentions"]
+pub struct NewMention {
+    pub status_id: i32,
+    pub user_id: i32,
+    pub screen_name: String,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "urls"]
+pub struct NewUrl {
+    pub status_id: i32,
+    pub url: String,
+    pub expanded_url: Option<String>,
+    pub display_url: String,
+    pub domain: Option<String>,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
+impl NewUrl {
+    fn new(
+        url: &str,
+        expanded_url: Option<String>,
+        display_url: &str,
+        (start, end): (i32, i32),
+    ) -> Self {
+        let domain = expanded_url
+            .as_ref()
+            .and_then(|link| url::Url::parse(link).ok())
+            .and_then(|link| link.host_str().map(|host| host.to_lowercase()))
+            .map(|host| host.trim_start_matches("www.").to_string());
+
+        Self {
+            status_id: 0,
+            url: url.into(),
+            expanded_url,
+            display_url: display_url.into(),
+            domain,
+            start_index: start,
+            end_index: end,
+        }
+    }
+}
+
+/// Hashtags, mentions, and links in a tweet’s text. They’re made before the
+/// status is stored, so its ID is filled in by `db::insert_text_entities`,
+/// and the mentioned users’ from the source IDs they come with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextEntities {
+    pub hashtags: Vec<NewHashtag>,
+    pub mentions: Vec<(String, NewMention)>,
+    pub urls: Vec<NewUrl>,
+}
+
+impl TextEntities {
+    fn hashtag(text: &str, (start, end): (i32, i32)) -> NewHashtag {
+        NewHashtag {
+            status_id: 0,
+            text: text.into(),
+            start_index: start,
+            end_index: end,
+        }
+    }
+
+    fn mention(user: &str, screen_name: &str, (start, end): (i32, i32)) -> (String, NewMention) {
+        let mention = NewMention {
+            status_id: 0,
+            user_id: 0,
+            screen_name: screen_name.into(),
+            start_index: start,
+            end_index: end,
+        };
+
+        (user.into(), mention)
+    }
+
+    pub fn from_archive(tweet: &archive::Tweet) -> Self {
+        let ents = &tweet.entities;
+        Self {
+            hashtags: ents
+                .hashtags
+                .iter()
+                .map(|tag| Self::hashtag(&tag.text, tag.indices))
+                .collect(),
+            mentions: ents
+                .user_mentions
+                .iter()
+                .map(|user| Self::mention(&user.id_str, &user.screen_name, user.indices))
+                .collect(),
+            urls: ents
+                .urls
+                .iter()
+                .map(|link| {
+                    NewUrl::new(&link.url, link.expanded_url.clone(), &link.display_url, link.indices)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// For retweets, pass the original: the retweet’s own text is cut short.
+impl From<&Tweet> for TextEntities {
+    fn from(tweet: &Tweet) -> Self {
+        let range = |(start, end): (usize, usize)| (start as i32, end as i32);
+        let ents = &tweet.entities;
+        Self {
+            hashtags: ents
+                .hashtags
+                .iter()
+                .map(|tag| Self::hashtag(&tag.text, range(tag.range)))
+                .collect(),
+            mentions: ents
+                .user_mentions
+                .iter()
+                .map(|user| {
+                    Self::mention(&format!("{}", user.id), &user.screen_name, range(user.range))
+                })
+                .collect(),
+            urls: ents
+                .urls
+                .iter()
+                .map(|link| {
+                    let indices = range(link.range);
+                    NewUrl::new(&link.url, link.expanded_url.clone(), &link.display_url, indices)
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
 #[table_name = "deletions"]
 pub struct NewDeletion {
@@ -190,9 +664,16 @@ impl NewDeletion {
             sponsor: "omelette".into(),
         }
     }
+
+    pub fn sponsored(status: &Status, not_before: DateTime<Utc>, sponsor: &str) -> Self {
+        Self {
+            sponsor: sponsor.into(),
+            ..Self::from_status(status, not_before)
+        }
+    }
 }
 
-#[derive(AsChangeset, Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[derive(AsChangeset, Clone, Debug, Deserialize, Insertable, PartialEq, PartialOrd, Serialize)]
 #[table_name = "twitter_users"]
 pub struct NewTwitterUser {
     pub source_id: String,
@@ -212,8 +693,6 @@ pub struct NewTwitterUser {
     pub listed_count: i32,
     pub created_at: DateTime<Utc>,
     pub fetched_at: DateTime<Utc>,
-    pub blocked_at: Option<DateTime<Utc>>,
-    pub muted_at: Option<DateTime<Utc>>,
     pub missing: bool,
     pub ui_language: Option<String>,
     pub ui_timezone: Option<String>,
@@ -241,8 +720,6 @@ impl From<&EggUser> for NewTwitterUser {
             listed_count: u.listed_count,
             created_at: u.created_at,
             fetched_at: Utc::now(),
-            blocked_at: None,
-            muted_at: None,
             missing: false,
             ui_language: u.lang.clone(),
             ui_timezone: u.time_zone.clone(),
@@ -252,6 +729,35 @@ impl From<&EggUser> for NewTwitterUser {
     }
 }
 
+impl From<&TwitterUser> for NewTwitterUser {
+    fn from(u: &TwitterUser) -> NewTwitterUser {
+        NewTwitterUser {
+            source_id: u.source_id.clone(),
+            screen_name: u.screen_name.clone(),
+            name: u.name.clone(),
+            description: u.description.clone(),
+            location: u.location.clone(),
+            url: u.url.clone(),
+            is_verified: u.is_verified,
+            is_protected: u.is_protected,
+            is_coauthored: u.is_coauthored,
+            is_translator: u.is_translator,
+            statuses_count: u.statuses_count,
+            following_count: u.following_count,
+            followers_count: u.followers_count,
+            likes_count: u.likes_count,
+            listed_count: u.listed_count,
+            created_at: u.created_at,
+            fetched_at: u.fetched_at,
+            missing: u.missing,
+            ui_language: u.ui_language.clone(),
+            ui_timezone: u.ui_timezone.clone(),
+            withheld_in: u.withheld_in.clone(),
+            withheld_scope: u.withheld_scope.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
 #[table_name = "twitter_users"]
 pub struct NewTwitterUserID {
@@ -273,3 +779,65 @@ impl From<u64> for NewTwitterUserID {
         }
     }
 }
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "sync_states"]
+pub struct NewSyncState {
+    pub source: Source,
+    pub kind: String,
+    pub account_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "accounts"]
+pub struct NewAccount {
+    pub name: String,
+    pub source: Source,
+    pub source_id: String,
+}
+
+#[derive(AsChangeset, Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "rate_limits"]
+pub struct NewRateLimit {
+    pub scope: String,
+    pub endpoint: String,
+    pub quota: i32,
+    pub remaining: i32,
+    pub reset_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "graph_snapshots"]
+pub struct NewGraphSnapshot {
+    pub account_id: i32,
+    pub kind: String,
+    pub taken_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "twitter_blocks"]
+pub struct NewTwitterBlock {
+    pub account_id: Option<i32>,
+    pub user_id: i32,
+    pub blocked_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "twitter_likes"]
+pub struct NewTwitterLike {
+    pub account_id: Option<i32>,
+    pub status_id: i32,
+    pub liked_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Insertable, PartialEq, PartialOrd)]
+#[table_name = "twitter_mutes"]
+pub struct NewTwitterMute {
+    pub account_id: Option<i32>,
+    pub user_id: i32,
+    pub muted_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+}
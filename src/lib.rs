@@ -4,23 +4,29 @@
 extern crate diesel;
 #[macro_use]
 extern crate diesel_derive_enum;
+#[macro_use]
+extern crate diesel_migrations;
 
-use diesel::pg::PgConnection;
-use diesel::prelude::*;
-use std::env;
-
+pub mod accounts;
+pub mod archive;
+pub mod cli;
+pub mod config;
+pub mod daemon;
+#[macro_use]
+pub mod db;
+pub mod dump;
+pub mod error;
 pub mod inserts;
+pub mod media;
 pub mod models;
+pub mod policy;
 pub mod schema;
 pub mod sources;
 pub mod store;
 pub mod types;
 
-pub fn connect() -> PgConnection {
-    let database_url = env::var("DATABASE_URL").expect("!! DATABASE_URL must be set");
-    PgConnection::establish(&database_url)
-        .expect(&format!("!! Error connecting to {}", database_url))
-}
+pub use crate::db::{connect, DbConnection};
+pub use crate::error::OmeletteError;
 
 pub const SLIM_MARK: &'static str = "~slim~";
 pub fn slim() -> String { SLIM_MARK.into() }
@@ -0,0 +1,240 @@
+//! What’s in a downloaded media file: its type, size, dimensions, and duration.
+//!
+//! Only the headers are read, for the formats Twitter and Mastodon serve:
+//! JPEG, PNG, and GIF images, and MP4 videos (which gifs are, too). Anything
+//! else gets its type and size only.
+
+use crate::inserts::MediaMetadata;
+
+pub fn inspect(content: &[u8]) -> MediaMetadata {
+    let mime_type = tree_magic::from_u8(content);
+
+    let (dimensions, duration) = match mime_type.as_str() {
+        "image/jpeg" => (jpeg_dimensions(content), None),
+        "image/png" => (png_dimensions(content), None),
+        "image/gif" => (gif_dimensions(content), None),
+        "video/mp4" | "video/quicktime" => mp4_info(content),
+        _ => (None, None),
+    };
+
+    MediaMetadata {
+        mime_type: Some(mime_type),
+        byte_size: Some(content.len() as i64),
+        width: dimensions.map(|(w, _)| w as i32),
+        height: dimensions.map(|(_, h)| h as i32),
+        duration_ms: duration.map(|d| d as i32),
+    }
+}
+
+fn be16(b: &[u8], at: usize) -> Option<u32> {
+    let b = b.get(at..at + 2)?;
+    Some(u32::from(b[0]) << 8 | u32::from(b[1]))
+}
+
+fn be32(b: &[u8], at: usize) -> Option<u32> {
+    let b = b.get(at..at + 4)?;
+    Some(u32::from(b[0]) << 24 | u32::from(b[1]) << 16 | u32::from(b[2]) << 8 | u32::from(b[3]))
+}
+
+fn be64(b: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from(be32(b, at)?) << 32 | u64::from(be32(b, at + 4)?))
+}
+
+fn le16(b: &[u8], at: usize) -> Option<u32> {
+    let b = b.get(at..at + 2)?;
+    Some(u32::from(b[1]) << 8 | u32::from(b[0]))
+}
+
+/// The IHDR chunk always comes first, right after the signature.
+fn png_dimensions(b: &[u8]) -> Option<(u32, u32)> {
+    if b.get(12..16)? != b"IHDR" {
+        return None;
+    }
+
+    Some((be32(b, 16)?, be32(b, 20)?))
+}
+
+/// The logical screen size, right after the signature.
+fn gif_dimensions(b: &[u8]) -> Option<(u32, u32)> {
+    Some((le16(b, 6)?, le16(b, 8)?))
+}
+
+/// Walks the segments up to the first start-of-frame.
+fn jpeg_dimensions(b: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *b.get(at)? != 0xFF {
+            return None;
+        }
+
+        let marker = *b.get(at + 1)?;
+        match marker {
+            // Padding
+            0xFF => at += 1,
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => at += 2,
+            // Start of frame, except DHT, JPG, and DAC which share the range
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                return Some((be16(b, at + 7)?, be16(b, at + 5)?));
+            }
+            _ => at += 2 + be16(b, at + 2)? as usize,
+        }
+    }
+}
+
+/// Reads the movie duration from `moov/mvhd` and the first visual track’s
+/// size from `moov/trak/tkhd`.
+fn mp4_info(b: &[u8]) -> (Option<(u32, u32)>, Option<u64>) {
+    let moov = match find_box(b, b"moov") {
+        Some(moov) => moov,
+        None => return (None, None),
+    };
+
+    let duration = find_box(moov, b"mvhd").and_then(|mvhd| {
+        let (timescale, duration) = match *mvhd.first()? {
+            1 => (be32(mvhd, 20)?, be64(mvhd, 24)?),
+            _ => (be32(mvhd, 12)?, u64::from(be32(mvhd, 16)?)),
+        };
+
+        if timescale == 0 {
+            return None;
+        }
+
+        Some(duration.checked_mul(1000)? / u64::from(timescale))
+    });
+
+    let dimensions = boxes(moov)
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| find_box(trak, b"tkhd"))
+        .filter_map(|tkhd| {
+            let at = if *tkhd.first()? == 1 { 88 } else { 76 };
+            // 16.16 fixed point
+            let (w, h) = (be32(tkhd, at)? >> 16, be32(tkhd, at + 4)? >> 16);
+            if w > 0 && h > 0 {
+                Some((w, h))
+            } else {
+                None
+            }
+        })
+        .next();
+
+    (dimensions, duration)
+}
+
+/// The boxes directly within `b`, as their type and contents.
+fn boxes(b: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        let size = be32(b, at)? as usize;
+        let kind = b.get(at + 4..at + 8)?;
+        let (header, size) = match size {
+            0 => (8, b.len() - at),
+            1 => (16, be64(b, at + 8)? as usize),
+            size => (8, size),
+        };
+
+        if size < header {
+            return None;
+        }
+
+        // Stop at sizes that run past the end, or past any address at all.
+        let end = at.checked_add(size)?;
+        let content = b.get(at + header..end)?;
+        at = end;
+
+        let mut k = [0; 4];
+        k.copy_from_slice(kind);
+        Some((k, content))
+    })
+}
+
+fn find_box<'a>(b: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(b).find(|(k, _)| k == kind).map(|(_, content)| content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut b = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(kind);
+        b.extend_from_slice(content);
+        b
+    }
+
+    fn mp4(timescale: u32, duration: u32, width: u32, height: u32) -> Vec<u8> {
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&timescale.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&duration.to_be_bytes());
+
+        let mut tkhd = vec![0; 84];
+        tkhd[76..80].copy_from_slice(&(width << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(height << 16).to_be_bytes());
+
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &[0; 84])));
+        moov.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd)));
+
+        let mut b = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        b.extend(mp4_box(b"moov", &moov));
+        b
+    }
+
+    #[test]
+    fn reads_jpeg_dimensions() {
+        let mut b = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xFF];
+        b.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x02]);
+        b.extend_from_slice(&[0xFF, 0xC2, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80]);
+
+        assert_eq!(jpeg_dimensions(&b), Some((640, 480)));
+        assert_eq!(jpeg_dimensions(&b[..b.len() - 1]), None);
+        assert_eq!(jpeg_dimensions(&b[..12]), None);
+        assert_eq!(jpeg_dimensions(&[0xFF, 0xD8, 0x00, 0xC0]), None);
+        assert_eq!(jpeg_dimensions(&[0xFF, 0xD8, 0xFF, 0xE0, 0xFF, 0xFF]), None);
+    }
+
+    #[test]
+    fn reads_png_dimensions() {
+        let mut b = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        b.extend_from_slice(&[0, 0, 0x04, 0x00, 0, 0, 0x03, 0x00, 8, 6, 0, 0, 0]);
+
+        assert_eq!(png_dimensions(&b), Some((1024, 768)));
+        assert_eq!(png_dimensions(&b[..23]), None);
+        b[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(png_dimensions(&b), None);
+    }
+
+    #[test]
+    fn reads_gif_dimensions() {
+        let b = b"GIF89a\x40\x01\xF0\x00\0\0\0";
+
+        assert_eq!(gif_dimensions(b), Some((320, 240)));
+        assert_eq!(gif_dimensions(&b[..9]), None);
+    }
+
+    #[test]
+    fn reads_mp4_info() {
+        let b = mp4(600, 1500, 1280, 720);
+
+        assert_eq!(mp4_info(&b), (Some((1280, 720)), Some(2500)));
+        assert_eq!(mp4_info(&b[..b.len() - 1]), (None, None));
+        assert_eq!(mp4_info(&mp4(0, 1500, 1280, 720)).1, None);
+        assert_eq!(mp4_info(b"\0\0\0\x04moov"), (None, None));
+    }
+
+    #[test]
+    fn stops_at_oversized_boxes() {
+        let mut b = mp4_box(b"free", b"");
+        b.extend_from_slice(&[0, 0, 0, 1]);
+        b.extend_from_slice(b"moov");
+        b.extend_from_slice(&u64::MAX.to_be_bytes());
+
+        let kinds: Vec<[u8; 4]> = boxes(&b).map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec![*b"free"]);
+
+        let mut b = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        b.extend_from_slice(b"moov");
+        assert_eq!(boxes(&b).count(), 0);
+    }
+}
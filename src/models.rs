@@ -5,6 +5,16 @@ use crate::schema::*;
 use crate::types::*;
 use diesel::sql_types::*;
 
+#[derive(Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
+#[table_name = "accounts"]
+pub struct Account {
+    pub id: i32,
+    pub name: String,
+    pub source: Source,
+    pub source_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
 #[table_name = "statuses"]
 pub struct Status {
@@ -29,6 +39,7 @@ pub struct Status {
     pub in_reply_to_user: Option<String>,
     pub quoting_status: Option<String>,
     pub public: bool,
+    pub account_id: Option<i32>,
 }
 
 #[derive(Associations, Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
@@ -45,6 +56,34 @@ pub struct Entity {
     pub original_status_source_id: Option<String>,
     pub original_status_source_url: Option<String>,
     pub blob_hash: Option<String>,
+    pub mime_type: Option<String>,
+    pub byte_size: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_ms: Option<i32>,
+    pub download_failures: i32,
+    pub download_error: Option<String>,
+    pub retry_at: Option<DateTime<Utc>>,
+    pub lost_at: Option<DateTime<Utc>>,
+    pub alt_text: Option<String>,
+    pub source: Source,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Entity, foreign_key = "entity_id")]
+#[table_name = "entity_variants"]
+pub struct EntityVariant {
+    pub id: i32,
+    pub entity_id: i32,
+    pub kind: String,
+    pub content_type: Option<String>,
+    pub bitrate: Option<i32>,
+    pub url: String,
+    pub blob_hash: Option<String>,
+    pub download_failures: i32,
+    pub download_error: Option<String>,
+    pub retry_at: Option<DateTime<Utc>>,
+    pub lost_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Associations, Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
@@ -59,6 +98,98 @@ pub struct Deletion {
     pub sponsor: String,
 }
 
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Account, foreign_key = "account_id")]
+#[table_name = "graph_snapshots"]
+pub struct GraphSnapshot {
+    pub id: i32,
+    pub account_id: i32,
+    pub kind: String,
+    pub taken_at: DateTime<Utc>,
+    pub complete: bool,
+    pub count: i32,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(GraphSnapshot, foreign_key = "snapshot_id")]
+#[belongs_to(TwitterUser, foreign_key = "user_id")]
+#[primary_key(snapshot_id, user_id)]
+#[table_name = "graph_members"]
+pub struct GraphMember {
+    pub snapshot_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
+#[table_name = "rate_limits"]
+pub struct RateLimit {
+    pub id: i32,
+    pub scope: String,
+    pub endpoint: String,
+    pub quota: i32,
+    pub remaining: i32,
+    pub reset_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
+#[table_name = "sync_states"]
+#[changeset_options(treat_none_as_null="true")]
+pub struct SyncState {
+    pub id: i32,
+    pub source: Source,
+    pub kind: String,
+    pub synced_to: Option<String>,
+    pub cursor: Option<String>,
+    pub pending_to: Option<String>,
+    pub done: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub fetched_count: i32,
+    pub inserted_count: i32,
+    pub account_id: Option<i32>,
+    pub pass_started_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Status, foreign_key = "status_id")]
+#[table_name = "hashtags"]
+pub struct Hashtag {
+    pub id: i32,
+    pub status_id: i32,
+    pub text: String,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Status, foreign_key = "status_id")]
+#[belongs_to(TwitterUser, foreign_key = "user_id")]
+#[table_name = "mentions"]
+pub struct Mention {
+    pub id: i32,
+    pub status_id: i32,
+    pub user_id: i32,
+    pub screen_name: String,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Status, foreign_key = "status_id")]
+#[table_name = "urls"]
+pub struct Url {
+    pub id: i32,
+    pub status_id: i32,
+    pub url: String,
+    pub expanded_url: Option<String>,
+    pub display_url: String,
+    pub domain: Option<String>,
+    pub start_index: i32,
+    pub end_index: i32,
+}
+
 #[derive(Clone, Debug, Identifiable, Insertable, PartialEq, PartialOrd, Queryable)]
 #[table_name = "twitter_users"]
 pub struct TwitterUser {
@@ -80,8 +211,6 @@ pub struct TwitterUser {
     pub listed_count: i32,
     pub created_at: DateTime<Utc>,
     pub fetched_at: DateTime<Utc>,
-    pub blocked_at: Option<DateTime<Utc>>,
-    pub muted_at: Option<DateTime<Utc>>,
     pub missing: bool,
     pub ui_language: Option<String>,
     pub ui_timezone: Option<String>,
@@ -89,5 +218,44 @@ pub struct TwitterUser {
     pub withheld_scope: Option<String>,
 }
 
-sql_function!(#[sql_name="repeat"] fn pg_repeat(t: Text, n: Int4) -> Text);
-sql_function!(#[sql_name="to_number"] fn pg_to_number(t: Text, f: Text) -> Numeric);
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Account, foreign_key = "account_id")]
+#[belongs_to(TwitterUser, foreign_key = "user_id")]
+#[table_name = "twitter_blocks"]
+pub struct TwitterBlock {
+    pub id: i32,
+    pub account_id: Option<i32>,
+    pub user_id: i32,
+    pub blocked_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+    pub unblocked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Account, foreign_key = "account_id")]
+#[belongs_to(Status, foreign_key = "status_id")]
+#[table_name = "twitter_likes"]
+pub struct TwitterLike {
+    pub id: i32,
+    pub account_id: Option<i32>,
+    pub status_id: i32,
+    pub liked_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+    pub unliked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Associations, Clone, Debug, Identifiable, PartialEq, PartialOrd, Queryable)]
+#[belongs_to(Account, foreign_key = "account_id")]
+#[belongs_to(TwitterUser, foreign_key = "user_id")]
+#[table_name = "twitter_mutes"]
+pub struct TwitterMute {
+    pub id: i32,
+    pub account_id: Option<i32>,
+    pub user_id: i32,
+    pub muted_at: DateTime<Utc>,
+    pub listed_at: Option<DateTime<Utc>>,
+    pub unmuted_at: Option<DateTime<Utc>>,
+}
+
+sql_function!(fn length(t: Text) -> Int4);
+sql_function!(fn lower(t: Text) -> Text);
@@ -0,0 +1,386 @@
+use chrono::{DateTime, Duration, Utc};
+use crate::error::OmeletteError;
+use crate::models::Status;
+use crate::types::Source;
+use egg_mode_text::{entities, EntityKind};
+use serde::{Deserialize, Deserializer};
+use std::{collections::HashSet, fs, path::Path};
+
+/// A deletion policy, as read from a TOML file by `omelette-policy`.
+///
+/// ```toml
+/// [keep]
+/// hashtags = ["#keep"]
+/// pinned = true
+///
+/// [[rule]]
+/// name = "old replies"
+/// kind = "reply"
+/// older_than = "90d"
+/// unless_marked = true
+///
+/// [[rule]]
+/// name = "unretweet"
+/// kind = "repost"
+/// older_than = "30d"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct Policy {
+    /// Recorded on the deletion requests, to tell them apart from `#cleanup`s.
+    #[serde(default = "default_sponsor")]
+    pub sponsor: String,
+    #[serde(default)]
+    pub keep: Keep,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+fn default_sponsor() -> String {
+    "omelette-policy".into()
+}
+
+/// Statuses that are never requested for deletion, whatever the rules say.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Keep {
+    #[serde(default)]
+    pub hashtags: Vec<String>,
+    /// Source IDs. Twitter doesn’t tell us which tweet is pinned, so put it here.
+    #[serde(default)]
+    pub ids: Vec<String>,
+    /// Keep statuses pinned on Mastodon.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Any,
+    Original,
+    Reply,
+    Repost,
+}
+
+impl Default for Kind {
+    fn default() -> Self {
+        Kind::Any
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default)]
+    pub kind: Kind,
+    /// Only apply to statuses from this source (`twitter` or `mastodon`).
+    pub source: Option<String>,
+    #[serde(deserialize_with = "duration")]
+    pub older_than: Duration,
+    /// Spare statuses that are liked/favourited by the account.
+    #[serde(default)]
+    pub unless_marked: bool,
+    /// Extra delay before the deletion can go ahead, to leave time to cancel.
+    #[serde(default, deserialize_with = "maybe_duration")]
+    pub grace: Option<Duration>,
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self, OmeletteError> {
+        let content = fs::read_to_string(path)?;
+        let policy: Policy = toml::from_str(&content)
+            .map_err(|err| OmeletteError::Config(format!("policy {}: {}", path.display(), err)))?;
+
+        for rule in &policy.rules {
+            if let Some(ref src) = rule.source {
+                if parse_source(src).is_none() {
+                    return Err(OmeletteError::Config(format!(
+                        "policy rule {:?}: unknown source {:?}",
+                        rule.name, src
+                    )));
+                }
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// The shortest age at which any rule applies, to narrow down the query.
+    pub fn min_age(&self) -> Option<Duration> {
+        self.rules.iter().map(|rule| rule.older_than).min()
+    }
+
+    /// Returns the first rule that wants this status gone, if any.
+    pub fn first_match(&self, status: &Status, now: DateTime<Utc>) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(status, now))
+    }
+}
+
+impl Keep {
+    pub fn keeps(&self, status: &Status, pinned: &HashSet<String>) -> bool {
+        if self.ids.contains(&status.source_id) {
+            return true;
+        }
+
+        if status.source == Source::Mastodon && pinned.contains(&status.source_id) {
+            return true;
+        }
+
+        entities(&status.text).into_iter().any(|ent| {
+            ent.kind == EntityKind::Hashtag && {
+                let tag = ent.substr(&status.text);
+                self.hashtags.iter().any(|keep| keep.eq_ignore_ascii_case(tag))
+            }
+        })
+    }
+}
+
+impl Rule {
+    pub fn matches(&self, status: &Status, now: DateTime<Utc>) -> bool {
+        if let Some(ref src) = self.source {
+            if parse_source(src) != Some(status.source.clone()) {
+                return false;
+            }
+        }
+
+        let kind = match self.kind {
+            Kind::Any => true,
+            Kind::Original => !status.is_repost && status.in_reply_to_status.is_none(),
+            Kind::Reply => !status.is_repost && status.in_reply_to_status.is_some(),
+            Kind::Repost => status.is_repost,
+        };
+
+        kind && !(self.unless_marked && status.is_marked) && anchor(status) + self.older_than <= now
+    }
+
+    /// When the deletion can go ahead: once the status is old enough, but no
+    /// sooner than the grace period from now.
+    pub fn not_before(&self, status: &Status, now: DateTime<Utc>) -> DateTime<Utc> {
+        let due = anchor(status) + self.older_than;
+        let earliest = now + self.grace.unwrap_or_else(Duration::zero);
+        if due > earliest {
+            due
+        } else {
+            earliest
+        }
+    }
+}
+
+/// Reposts age from when they were reposted, not from the original.
+fn anchor(status: &Status) -> DateTime<Utc> {
+    status.reposted_at.unwrap_or(status.posted_at)
+}
+
+fn parse_source(name: &str) -> Option<Source> {
+    match name {
+        "twitter" => Some(Source::Twitter),
+        "mastodon" => Some(Source::Mastodon),
+        _ => None,
+    }
+}
+
+/// Parses durations like `90d`, `2w`, `12h`, `30m`, or `45s`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (n, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+    let n: i64 = n.parse().ok()?;
+
+    match unit.trim() {
+        "w" => Some(Duration::weeks(n)),
+        "d" => Some(Duration::days(n)),
+        "h" => Some(Duration::hours(n)),
+        "m" => Some(Duration::minutes(n)),
+        "s" => Some(Duration::seconds(n)),
+        _ => None,
+    }
+}
+
+pub(crate) fn duration<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    use serde::de::Error;
+
+    let s = String::deserialize(d)?;
+    parse_duration(&s).ok_or_else(|| Error::custom(format!("bad duration {:?}", s)))
+}
+
+pub(crate) fn maybe_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    duration(d).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2019, 6, 1).and_hms(12, 0, 0)
+    }
+
+    fn status(text: &str, age: Duration) -> Status {
+        Status {
+            id: 1,
+            text: text.into(),
+            author_id: None,
+            geolocation_lat: None,
+            geolocation_lon: None,
+            posted_at: now() - age,
+            fetched_at: now(),
+            fetched_via: None,
+            deleted_at: None,
+            is_repost: false,
+            reposted_at: None,
+            is_marked: false,
+            marked_at: None,
+            source: Source::Twitter,
+            source_id: "1000".into(),
+            source_author: "\"Kat\" <@kat> (1)".into(),
+            source_app: "".into(),
+            in_reply_to_status: None,
+            in_reply_to_user: None,
+            quoting_status: None,
+            public: true,
+            account_id: None,
+        }
+    }
+
+    fn policy(toml: &str) -> Policy {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90d"), Some(Duration::days(90)));
+        assert_eq!(parse_duration("2w"), Some(Duration::weeks(2)));
+        assert_eq!(parse_duration("12h"), Some(Duration::hours(12)));
+        assert_eq!(parse_duration("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_duration("45s"), Some(Duration::seconds(45)));
+        assert_eq!(parse_duration(" 7 d "), Some(Duration::days(7)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("90"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("3y"), None);
+        assert_eq!(parse_duration("-3d"), None);
+        assert_eq!(parse_duration("1.5d"), None);
+    }
+
+    #[test]
+    fn rules_match_by_age_kind_and_source() {
+        let policy = policy(
+            r#"
+            [[rule]]
+            name = "old replies"
+            kind = "reply"
+            source = "twitter"
+            older_than = "90d"
+            "#,
+        );
+        let rule = &policy.rules[0];
+
+        let mut reply = status("@someone yes", Duration::days(91));
+        reply.in_reply_to_status = Some("999".into());
+        assert!(rule.matches(&reply, now()));
+
+        let original = status("Hello", Duration::days(91));
+        assert!(!rule.matches(&original, now()));
+
+        let mut young = reply.clone();
+        young.posted_at = now() - Duration::days(89);
+        assert!(!rule.matches(&young, now()));
+
+        let mut toot = reply.clone();
+        toot.source = Source::Mastodon;
+        assert!(!rule.matches(&toot, now()));
+    }
+
+    #[test]
+    fn rules_match_exactly_at_the_age() {
+        let rule = &policy("[[rule]]\nname = \"all\"\nolder_than = \"30d\"").rules[0];
+        assert!(rule.matches(&status("Hello", Duration::days(30)), now()));
+        assert!(!rule.matches(&status("Hello", Duration::days(30) - Duration::seconds(1)), now()));
+    }
+
+    #[test]
+    fn reposts_age_from_the_repost() {
+        let rule = &policy("[[rule]]\nname = \"rts\"\nkind = \"repost\"\nolder_than = \"30d\"").rules[0];
+
+        let mut repost = status("Hello", Duration::days(400));
+        repost.is_repost = true;
+        repost.reposted_at = Some(now() - Duration::days(10));
+        assert!(!rule.matches(&repost, now()));
+
+        repost.reposted_at = Some(now() - Duration::days(31));
+        assert!(rule.matches(&repost, now()));
+    }
+
+    #[test]
+    fn rules_spare_marked_statuses_if_asked() {
+        let rule = &policy("[[rule]]\nname = \"all\"\nolder_than = \"1d\"\nunless_marked = true").rules[0];
+
+        let mut liked = status("Hello", Duration::days(2));
+        assert!(rule.matches(&liked, now()));
+
+        liked.is_marked = true;
+        assert!(!rule.matches(&liked, now()));
+    }
+
+    #[test]
+    fn keeps_listed_ids_and_pinned_toots() {
+        let keep = policy("[keep]\nids = [\"1000\"]").keep;
+        let mut pinned = HashSet::new();
+        assert!(keep.keeps(&status("Hello", Duration::days(1)), &pinned));
+
+        let mut toot = status("Hello", Duration::days(1));
+        toot.source = Source::Mastodon;
+        toot.source_id = "2000".into();
+        assert!(!keep.keeps(&toot, &pinned));
+
+        pinned.insert("2000".into());
+        assert!(keep.keeps(&toot, &pinned));
+
+        // Only Mastodon has pins.
+        let mut tweet = toot.clone();
+        tweet.source = Source::Twitter;
+        assert!(!keep.keeps(&tweet, &pinned));
+    }
+
+    #[test]
+    fn keeps_hashtags_whatever_their_case() {
+        let keep = policy("[keep]\nhashtags = [\"#keep\"]").keep;
+        let none = HashSet::new();
+
+        assert!(keep.keeps(&status("Worth it #keep", Duration::days(1)), &none));
+        assert!(keep.keeps(&status("Worth it #KEEP", Duration::days(1)), &none));
+        assert!(!keep.keeps(&status("Not #keeper", Duration::days(1)), &none));
+        assert!(!keep.keeps(&status("Just keep", Duration::days(1)), &none));
+    }
+
+    #[test]
+    fn not_before_is_the_later_of_due_and_grace() {
+        let rule = &policy("[[rule]]\nname = \"all\"\nolder_than = \"30d\"\ngrace = \"1d\"").rules[0];
+
+        // Long overdue: the grace period decides.
+        let old = status("Hello", Duration::days(100));
+        assert_eq!(rule.not_before(&old, now()), now() + Duration::days(1));
+
+        // Only due in three days: that’s after the grace period.
+        let young = status("Hello", Duration::days(27));
+        assert_eq!(rule.not_before(&young, now()), now() + Duration::days(3));
+
+        // Due right as the grace period ends.
+        let edge = status("Hello", Duration::days(29));
+        assert_eq!(rule.not_before(&edge, now()), now() + Duration::days(1));
+    }
+
+    #[test]
+    fn not_before_without_grace_is_now_or_due() {
+        let rule = &policy("[[rule]]\nname = \"all\"\nolder_than = \"30d\"").rules[0];
+
+        assert_eq!(rule.not_before(&status("Hello", Duration::days(31)), now()), now());
+        assert_eq!(
+            rule.not_before(&status("Hello", Duration::days(20)), now()),
+            now() + Duration::days(10)
+        );
+    }
+}
@@ -3,6 +3,20 @@
 
 table! {
     use diesel::sql_types::*;
+    use crate::types::*;
+
+    accounts (id) {
+        id -> Int4,
+        name -> Text,
+        source -> Source_t,
+        source_id -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
 
     deletions (id) {
         id -> Int4,
@@ -29,6 +43,102 @@ table! {
         original_status_source_id -> Nullable<Text>,
         original_status_source_url -> Nullable<Text>,
         blob_hash -> Nullable<Text>,
+        mime_type -> Nullable<Text>,
+        byte_size -> Nullable<Int8>,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        duration_ms -> Nullable<Int4>,
+        download_failures -> Int4,
+        download_error -> Nullable<Text>,
+        retry_at -> Nullable<Timestamptz>,
+        lost_at -> Nullable<Timestamptz>,
+        alt_text -> Nullable<Text>,
+        source -> Source_t,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    entity_variants (id) {
+        id -> Int4,
+        entity_id -> Int4,
+        kind -> Text,
+        content_type -> Nullable<Text>,
+        bitrate -> Nullable<Int4>,
+        url -> Text,
+        blob_hash -> Nullable<Text>,
+        download_failures -> Int4,
+        download_error -> Nullable<Text>,
+        retry_at -> Nullable<Timestamptz>,
+        lost_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    graph_members (snapshot_id, user_id) {
+        snapshot_id -> Int4,
+        user_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    graph_snapshots (id) {
+        id -> Int4,
+        account_id -> Int4,
+        kind -> Text,
+        taken_at -> Timestamptz,
+        complete -> Bool,
+        count -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    hashtags (id) {
+        id -> Int4,
+        status_id -> Int4,
+        text -> Text,
+        start_index -> Int4,
+        end_index -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    mentions (id) {
+        id -> Int4,
+        status_id -> Int4,
+        user_id -> Int4,
+        screen_name -> Text,
+        start_index -> Int4,
+        end_index -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    rate_limits (id) {
+        id -> Int4,
+        scope -> Text,
+        endpoint -> Text,
+        quota -> Int4,
+        remaining -> Int4,
+        reset_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -58,6 +168,71 @@ table! {
         in_reply_to_user -> Nullable<Text>,
         quoting_status -> Nullable<Text>,
         public -> Bool,
+        account_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    sync_states (id) {
+        id -> Int4,
+        source -> Source_t,
+        kind -> Text,
+        synced_to -> Nullable<Text>,
+        cursor -> Nullable<Text>,
+        pending_to -> Nullable<Text>,
+        done -> Bool,
+        last_run_at -> Nullable<Timestamptz>,
+        last_success_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        fetched_count -> Int4,
+        inserted_count -> Int4,
+        account_id -> Nullable<Int4>,
+        pass_started_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    twitter_blocks (id) {
+        id -> Int4,
+        account_id -> Nullable<Int4>,
+        user_id -> Int4,
+        blocked_at -> Timestamptz,
+        listed_at -> Nullable<Timestamptz>,
+        unblocked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    twitter_likes (id) {
+        id -> Int4,
+        account_id -> Nullable<Int4>,
+        status_id -> Int4,
+        liked_at -> Timestamptz,
+        listed_at -> Nullable<Timestamptz>,
+        unliked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    twitter_mutes (id) {
+        id -> Int4,
+        account_id -> Nullable<Int4>,
+        user_id -> Int4,
+        muted_at -> Timestamptz,
+        listed_at -> Nullable<Timestamptz>,
+        unmuted_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -84,8 +259,6 @@ table! {
         listed_count -> Int4,
         created_at -> Timestamptz,
         fetched_at -> Timestamptz,
-        blocked_at -> Nullable<Timestamptz>,
-        muted_at -> Nullable<Timestamptz>,
         missing -> Bool,
         ui_language -> Nullable<Text>,
         ui_timezone -> Nullable<Text>,
@@ -94,12 +267,57 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use crate::types::*;
+
+    urls (id) {
+        id -> Int4,
+        status_id -> Int4,
+        url -> Text,
+        expanded_url -> Nullable<Text>,
+        display_url -> Text,
+        domain -> Nullable<Text>,
+        start_index -> Int4,
+        end_index -> Int4,
+    }
+}
+
 joinable!(deletions -> statuses (status_id));
 joinable!(entities -> statuses (status_id));
+joinable!(entity_variants -> entities (entity_id));
+joinable!(graph_members -> graph_snapshots (snapshot_id));
+joinable!(graph_members -> twitter_users (user_id));
+joinable!(graph_snapshots -> accounts (account_id));
+joinable!(hashtags -> statuses (status_id));
+joinable!(mentions -> statuses (status_id));
+joinable!(mentions -> twitter_users (user_id));
+joinable!(statuses -> accounts (account_id));
+joinable!(statuses -> twitter_users (author_id));
+joinable!(sync_states -> accounts (account_id));
+joinable!(twitter_blocks -> accounts (account_id));
+joinable!(twitter_blocks -> twitter_users (user_id));
+joinable!(twitter_likes -> accounts (account_id));
+joinable!(twitter_likes -> statuses (status_id));
+joinable!(twitter_mutes -> accounts (account_id));
+joinable!(twitter_mutes -> twitter_users (user_id));
+joinable!(urls -> statuses (status_id));
 
 allow_tables_to_appear_in_same_query!(
+    accounts,
     deletions,
     entities,
+    entity_variants,
+    graph_members,
+    graph_snapshots,
+    hashtags,
+    mentions,
+    rate_limits,
     statuses,
+    sync_states,
+    twitter_blocks,
+    twitter_likes,
+    twitter_mutes,
     twitter_users,
+    urls,
 );
@@ -0,0 +1,472 @@
+use crate::db::{insert_new_statuses, insert_variants, DbConnection};
+use crate::error::OmeletteError;
+use crate::inserts::{NewEntity, NewEntityVariant, NewStatus};
+use crate::config::AccountConfig;
+use crate::models::{self, Status};
+use crate::sources::{setting, DeleteError, LoadError, StatusSource};
+use crate::types::Source;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use reqwest::{Client, Error as ReqwestError, Method, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct Mastodon {
+    pub client: Client,
+    pub instance: String,
+    pub token: String,
+    pub id: String,
+    pub account: models::Account,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub username: String,
+    pub acct: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Application {
+    pub name: String,
+    pub website: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+    pub preview_url: Option<String>,
+    pub remote_url: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MastodonStatus {
+    pub id: String,
+    pub uri: String,
+    pub url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub content: String,
+    pub account: Account,
+    pub reblog: Option<Box<MastodonStatus>>,
+    pub in_reply_to_id: Option<String>,
+    pub in_reply_to_account_id: Option<String>,
+    pub visibility: String,
+    pub favourited: Option<bool>,
+    pub application: Option<Application>,
+    #[serde(default)]
+    pub media_attachments: Vec<Attachment>,
+}
+
+/// Mastodon statuses are HTML, this turns them into the plain text we store.
+pub fn plain_text(html: &str) -> String {
+    use regex::Regex;
+
+    let breaks = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    let paras = Regex::new(r"(?i)</p>\s*<p[^>]*>").unwrap();
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+
+    let text = breaks.replace_all(html, "\n");
+    let text = paras.replace_all(&text, "\n\n");
+    let text = tags.replace_all(&text, "");
+
+    // Keep the text as is if it has a stray & that isn’t an entity.
+    htmlescape::decode_html(&text).unwrap_or_else(|_| text.into_owned())
+}
+
+impl Mastodon {
+    pub fn source() -> Source {
+        Source::Mastodon
+    }
+
+    pub fn load(
+        settings: &AccountConfig,
+        account: models::Account,
+    ) -> Result<Box<StatusSource>, LoadError> {
+        let md = Self::load_unboxed(settings, account)?;
+        Ok(Box::new(md))
+    }
+
+    pub fn load_unboxed(settings: &AccountConfig, account: models::Account) -> Result<Self, LoadError> {
+        // The instance is a full base URL (e.g. https://mastodon.social) so it
+        // can just as well point to a local server for testing.
+        let instance = setting(settings, "instance", &settings.instance)?
+            .trim_end_matches('/')
+            .to_string();
+
+        Ok(Self {
+            client: Client::new(),
+            instance,
+            token: setting(settings, "access_token", &settings.access_token)?,
+            id: account.source_id.clone(),
+            account,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, &format!("{}/api/v1/{}", self.instance, path))
+            .bearer_auth(&self.token)
+    }
+
+    fn latest_id_in_db(&self, conn: &DbConnection) -> Result<Option<String>, OmeletteError> {
+        use crate::models::length;
+        use crate::schema::statuses::dsl::*;
+
+        Ok(statuses.select(source_id)
+            .filter(source.eq(Source::Mastodon))
+            .filter(account_id.eq(self.account.id))
+            .filter(is_repost.eq(false))
+            .filter(deleted_at.is_null())
+            // Mastodon IDs are numeric strings too, so same trick as Twitter:
+            .order_by((length(source_id).desc(), source_id.desc()))
+            .first::<String>(conn)
+            .optional()?)
+    }
+
+    fn account_statuses(
+        &self,
+        since: &Option<String>,
+        max: &Option<String>,
+    ) -> Result<Vec<MastodonStatus>, ReqwestError> {
+        let mut query = vec![("limit", "40".to_string())];
+        if let Some(ref id) = since {
+            query.push(("since_id", id.clone()));
+        }
+        if let Some(ref id) = max {
+            query.push(("max_id", id.clone()));
+        }
+
+        self.request(Method::GET, &format!("accounts/{}/statuses", self.id))
+            .query(&query)
+            .send()?
+            .error_for_status()?
+            .json()
+    }
+
+    /// IDs of the statuses pinned to the account’s profile.
+    pub fn pinned_ids(&self) -> Result<Vec<String>, ReqwestError> {
+        let pinned: Vec<MastodonStatus> = self
+            .request(Method::GET, &format!("accounts/{}/statuses", self.id))
+            .query(&[("pinned", "true")])
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(pinned.into_iter().map(|status| status.id).collect())
+    }
+}
+
+impl StatusSource for Mastodon {
+    fn account(&self) -> &models::Account {
+        &self.account
+    }
+
+    fn sync(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        let latest = self.latest_id_in_db(conn)?;
+        println!(
+            ":: Latest mastodon ID we have:\t\t{}",
+            latest.clone().unwrap_or("none".into())
+        );
+
+        let mut statusbag: Vec<NewStatus> = vec![];
+        let mut entitybag: HashMap<String, Vec<NewEntity>> = HashMap::new();
+        let mut variantbag = HashMap::new();
+        let mut max_id = None;
+        let mut batch = 0;
+
+        loop {
+            // Pages come newest first, so we walk down with max_id until
+            // there's nothing left that's newer than what we have.
+            let page = self.account_statuses(&latest, &max_id)?;
+
+            batch += 1;
+            println!("-> Batch {} ({} statuses)", batch, page.len());
+
+            if page.is_empty() {
+                break;
+            }
+
+            max_id = page.last().map(|s| s.id.clone());
+            for status in &page {
+                let mut new_status: NewStatus = status.into();
+                new_status.account_id = Some(self.account.id);
+                let ents = NewEntity::from_attachments(status);
+                if !ents.is_empty() {
+                    entitybag.insert(new_status.source_id.clone(), ents);
+                }
+                variantbag.extend(NewEntityVariant::from_attachments(status));
+
+                statusbag.push(new_status);
+            }
+        }
+
+        statusbag.reverse();
+
+        println!(
+            "=> Made {} calls to mastodon and retrieved {} statuses",
+            batch,
+            statusbag.len()
+        );
+
+        let inserted_statuses: Vec<Status> = insert_new_statuses(conn, &statusbag)?;
+
+        let mut entitysack = Vec::with_capacity(entitybag.len() * 4);
+        for inserted in &inserted_statuses {
+            if let Some(ents) = entitybag.remove(&inserted.source_id) {
+                for mut ent in ents.into_iter() {
+                    ent.status_id = inserted.id;
+                    entitysack.push(ent);
+                }
+            }
+        }
+
+        let entitied = {
+            use crate::schema::entities::dsl::*;
+            insert_or_ignore!(entities, &entitysack, (source, source_id)).execute(conn)?
+        };
+        insert_variants(conn, &Source::Mastodon, &variantbag)?;
+
+        println!(
+            "=> Inserted {} new statuses in DB and {} entities",
+            inserted_statuses.len(),
+            entitied
+        );
+
+        Ok(())
+    }
+
+    fn delete(&self, conn: &DbConnection, status: &Status) -> Result<(), DeleteError> {
+        if status.deleted_at.is_some() {
+            return Err(DeleteError::AlreadyDone);
+        }
+        if status.source != Source::Mastodon {
+            return Err(DeleteError::WrongSource);
+        }
+
+        if status.is_repost {
+            self.request(Method::POST, &format!("statuses/{}/unreblog", status.source_id))
+        } else {
+            self.request(Method::DELETE, &format!("statuses/{}", status.source_id))
+        }
+        .send()?
+        .error_for_status()?;
+
+        {
+            use crate::schema::statuses::dsl::*;
+            diesel::update(statuses.find(status.id))
+                .set(deleted_at.eq(Utc::now()))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts;
+    use crate::db::test_connection;
+    use mockito::{mock, Matcher};
+    use serde_json::{json, Value};
+
+    fn status_json(id: &str, reblog: Option<Value>) -> Value {
+        json!({
+            "id": id,
+            "uri": format!("https://example.org/users/kat/statuses/{}", id),
+            "url": null,
+            "created_at": "2019-06-01T12:00:00.000Z",
+            "content": format!("<p>Status {}</p>", id),
+            "account": {
+                "id": "101",
+                "username": "kat",
+                "acct": "kat",
+                "display_name": "Kat"
+            },
+            "reblog": reblog,
+            "in_reply_to_id": null,
+            "in_reply_to_account_id": null,
+            "visibility": "public",
+            "favourited": false,
+            "application": null
+        })
+    }
+
+    fn account(conn: &DbConnection, id: &str) -> Mastodon {
+        let settings = AccountConfig {
+            name: format!("masto{}", id),
+            source: Source::Mastodon,
+            id: Some(id.into()),
+            consumer_key: None,
+            consumer_secret: None,
+            access_token_key: None,
+            access_token_secret: None,
+            instance: Some(format!("{}/", mockito::server_url())),
+            access_token: Some("token".into()),
+        };
+
+        let account = accounts::register(conn, &settings).unwrap();
+        Mastodon::load_unboxed(&settings, account).unwrap()
+    }
+
+    #[test]
+    fn plain_text_decodes_entities() {
+        assert_eq!(
+            plain_text("<p>Fish &amp; chips &lt;3 &#8212; caf&eacute;</p><p>Two<br/>lines</p>"),
+            "Fish & chips <3 — café\n\nTwo\nlines"
+        );
+    }
+
+    #[test]
+    fn plain_text_keeps_stray_ampersands() {
+        assert_eq!(plain_text("<p>AT&T</p>"), "AT&T");
+    }
+
+    #[test]
+    fn reblogs_are_stored_as_the_original() {
+        let original = status_json("7", None);
+        let status: MastodonStatus =
+            serde_json::from_value(status_json("8", Some(original))).unwrap();
+        let new: NewStatus = (&status).into();
+
+        assert_eq!(new.source_id, "7");
+        assert!(new.is_repost);
+        assert_eq!(new.reposted_at, Some(status.created_at));
+    }
+
+    #[test]
+    fn pinned_ids_are_fetched_from_the_instance() {
+        let conn = test_connection();
+        let md = account(&conn, "102");
+
+        let _pinned = mock("GET", "/api/v1/accounts/102/statuses")
+            .match_query(Matcher::UrlEncoded("pinned".into(), "true".into()))
+            .match_header("authorization", "Bearer token")
+            .with_header("content-type", "application/json")
+            .with_body(json!([status_json("5", None)]).to_string())
+            .create();
+
+        assert_eq!(md.pinned_ids().unwrap(), vec!["5".to_string()]);
+    }
+
+    #[test]
+    fn sync_pages_down_and_keeps_sources_apart() {
+        let conn = test_connection();
+        let md = account(&conn, "101");
+
+        // A tweet with the same ID as one of the toots must not hide it.
+        let tweet: MastodonStatus = serde_json::from_value(status_json("3", None)).unwrap();
+        insert_new_statuses(
+            &conn,
+            &[NewStatus {
+                source: Source::Twitter,
+                ..(&tweet).into()
+            }],
+        )
+        .unwrap();
+
+        let _first = mock("GET", "/api/v1/accounts/101/statuses")
+            .match_query(Matcher::Exact("limit=40".into()))
+            .with_header("content-type", "application/json")
+            .with_body(json!([status_json("3", None), status_json("2", None)]).to_string())
+            .create();
+        let _last = mock("GET", "/api/v1/accounts/101/statuses")
+            .match_query(Matcher::Exact("limit=40&max_id=2".into()))
+            .with_header("content-type", "application/json")
+            .with_body("[]")
+            .create();
+
+        md.sync(&conn).unwrap();
+
+        use crate::schema::statuses::dsl::*;
+        let synced: Vec<(String, Option<i32>, String)> = statuses
+            .select((source_id, account_id, text))
+            .filter(source.eq(Source::Mastodon))
+            .order_by(source_id)
+            .load(&conn)
+            .unwrap();
+
+        assert_eq!(
+            synced,
+            vec![
+                ("2".into(), Some(md.account.id), "Status 2".into()),
+                ("3".into(), Some(md.account.id), "Status 3".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn media_ids_are_kept_apart_from_tweets() {
+        let conn = test_connection();
+        let md = account(&conn, "103");
+
+        let mut toot = status_json("4", None);
+        toot["media_attachments"] = json!([{
+            "id": "9",
+            "type": "image",
+            "url": "https://example.org/media/9.png",
+            "preview_url": null,
+            "remote_url": null,
+            "description": "A fish"
+        }]);
+
+        // A tweet with media of the same ID must not hide it.
+        let parsed: MastodonStatus = serde_json::from_value(toot.clone()).unwrap();
+        let tweet = insert_new_statuses(
+            &conn,
+            &[NewStatus {
+                source: Source::Twitter,
+                ..(&parsed).into()
+            }],
+        )
+        .unwrap();
+        let media: Vec<NewEntity> = NewEntity::from_attachments(&parsed)
+            .into_iter()
+            .map(|ent| NewEntity {
+                status_id: tweet[0].id,
+                source: Source::Twitter,
+                alt_text: None,
+                ..ent
+            })
+            .collect();
+        {
+            use crate::schema::entities::dsl::*;
+            insert_or_ignore!(entities, &media, (source, source_id))
+                .execute(&conn)
+                .unwrap();
+        }
+
+        let _first = mock("GET", "/api/v1/accounts/103/statuses")
+            .match_query(Matcher::Exact("limit=40".into()))
+            .with_header("content-type", "application/json")
+            .with_body(json!([toot]).to_string())
+            .create();
+        let _last = mock("GET", "/api/v1/accounts/103/statuses")
+            .match_query(Matcher::Exact("limit=40&max_id=4".into()))
+            .with_header("content-type", "application/json")
+            .with_body("[]")
+            .create();
+
+        md.sync(&conn).unwrap();
+
+        use crate::schema::entities::dsl::*;
+        let alts = |src: Source| -> Vec<(String, Option<String>)> {
+            entities
+                .select((source_id, alt_text))
+                .filter(source.eq(src))
+                .load(&conn)
+                .unwrap()
+        };
+
+        assert_eq!(alts(Source::Twitter), vec![("9".into(), None)]);
+        assert_eq!(alts(Source::Mastodon), vec![("9".into(), Some("A fish".into()))]);
+    }
+}
@@ -1,59 +1,91 @@
-use crate::models::{Deletion, Status};
+use crate::accounts;
+use crate::config::{AccountConfig, Config};
+use crate::db::DbConnection;
+use crate::error::OmeletteError;
+use crate::models::{Account, Deletion, Status};
 use crate::types::Source;
-use diesel::{pg::PgConnection, result::Error as DieselError};
+use diesel::result::Error as DieselError;
 use egg_mode::error::Error as EggError;
+use reqwest::Error as ReqwestError;
 use std::{
-    collections::HashMap, env::VarError, io::{self, Write},
+    collections::BTreeMap, io::{self, Write},
 };
 
+pub mod mastodon;
+pub mod state;
 pub mod twitter;
 
-pub type Sources = HashMap<Source, Box<StatusSource>>;
+/// Loaded sources, by account name.
+pub type Sources = BTreeMap<String, Box<StatusSource>>;
+
+/// Loads a source for each configured account, or only the one named.
+///
+/// Accounts that can’t be loaded are reported and left out.
+pub fn all_available(
+    conn: &DbConnection,
+    config: &Config,
+    only: &Option<String>,
+) -> Result<Sources, OmeletteError> {
+    use self::mastodon::Mastodon;
+    use self::twitter::Twitter;
 
-pub fn all_available() -> Sources {
-    let mut sources: Sources = HashMap::new();
+    let mut sources: Sources = BTreeMap::new();
+    for (settings, account) in accounts::load(conn, config, only)? {
+        let loaded = match settings.source {
+            Source::Twitter => Twitter::load(&settings, account),
+            Source::Mastodon => Mastodon::load(&settings, account),
+        };
 
-    macro_rules! load_source {
-        ($struct:ident) => {
-            match $struct::load() {
-                Err(err) => println!("!! Error loading {}: {:?}", stringify!($struct), err),
-                Ok(source) => {
-                    sources.insert($struct::source(), source);
-                }
-            };
+        match loaded {
+            Err(err) => println!("!! Error loading account {}: {:?}", settings.name, err),
+            Ok(source) => {
+                sources.insert(settings.name, source);
+            }
         };
     }
 
-    use self::twitter::Twitter;
-    load_source!(Twitter);
-
-    sources
+    Ok(sources)
 }
 
-pub fn run_deletes(sources: &Sources, conn: &PgConnection, mode: ActionMode) {
+pub fn run_deletes(sources: &Sources, conn: &DbConnection, mode: ActionMode) -> Result<usize, OmeletteError> {
     use chrono::Utc;
     use crate::schema::deletions::dsl::*;
     use crate::schema::statuses;
     use diesel::prelude::*;
 
+    // Only the requests for the loaded accounts, or those that may be theirs.
+    let loaded: Vec<i32> = sources.values().map(|source| source.account().id).collect();
+    let mut kinds: Vec<Source> = sources
+        .values()
+        .map(|source| source.account().source.clone())
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+
     let deletes: Vec<(Deletion, Status)> = deletions
         .inner_join(statuses::table)
         .filter(executed_at.is_null())
         .filter(not_before.lt(Utc::now()))
+        .filter(
+            statuses::account_id
+                .eq_any(loaded)
+                .or(statuses::account_id.is_null().and(statuses::source.eq_any(kinds))),
+        )
         .order_by(not_before)
-        .load(conn)
-        .expect("!! Cannot load deletions from DB");
+        .load(conn)?;
 
     if deletes.is_empty() {
         println!("!! No deletion requests ready, skip.");
-        return;
+        return Ok(0);
     }
 
     println!("=> {} deletion requests ready for action", deletes.len());
 
+    let registered = accounts::all(conn)?;
+
     let mut successes = 0;
     for (delete, status) in &deletes {
-        if let Some(source) = sources.get(&status.source) {
+        if let Some(source) = source_for(sources, &registered, status) {
             match mode {
                 ActionMode::DryRun => println!("\n-> DRY RUN: would delete status: {:?}", status),
                 ActionMode::Interactive => {
@@ -71,33 +103,29 @@ To exit, use Ctrl-C.
 
 Delete status? ");
 
-                    io::stdout().flush().unwrap();
+                    io::stdout().flush()?;
 
                     let mut nline = String::with_capacity(7);
-                    io::stdin()
-                        .read_line(&mut nline)
-                        .expect("!! Could not grab input");
+                    io::stdin().read_line(&mut nline)?;
                     let mut command = nline.trim();
 
                     if command == "show" || command == "?" {
                         print!("\n{:?}\n\nDelete status? ", status);
-                        io::stdout().flush().unwrap();
+                        io::stdout().flush()?;
 
                         nline.truncate(0);
-                        io::stdin()
-                            .read_line(&mut nline)
-                            .expect("!! Could not grab input");
+                        io::stdin().read_line(&mut nline)?;
                         command = nline.trim();
                     }
 
                     if command == "delete" || command == "d" {
-                        successes += one_delete(source, conn, status, delete);
+                        successes += one_delete(source, conn, status, delete)?;
                     }
 
                     println!("");
                 }
                 ActionMode::Auto => {
-                    successes += one_delete(source, conn, status, delete);
+                    successes += one_delete(source, conn, status, delete)?;
                 }
             };
         } else {
@@ -106,14 +134,34 @@ Delete status? ");
     }
 
     println!("\n=> {} successful deletes performed", successes);
+    Ok(successes)
+}
+
+/// The source for the account a status belongs to. Statuses that aren’t linked
+/// to an account can only be attributed if there’s a single one on their source.
+fn source_for<'a>(
+    sources: &'a Sources,
+    registered: &[Account],
+    status: &Status,
+) -> Option<&'a Box<StatusSource>> {
+    match status.account_id {
+        Some(aid) => sources.values().find(|source| source.account().id == aid),
+        None => {
+            let mut candidates = registered.iter().filter(|a| a.source == status.source);
+            match (candidates.next(), candidates.next()) {
+                (Some(only), None) => sources.get(&only.name),
+                _ => None,
+            }
+        }
+    }
 }
 
 fn one_delete(
     source: &Box<StatusSource>,
-    conn: &PgConnection,
+    conn: &DbConnection,
     status: &Status,
     delete: &Deletion,
-) -> usize {
+) -> Result<usize, OmeletteError> {
     use chrono::Utc;
     use crate::schema::deletions::dsl::*;
     use diesel::prelude::*;
@@ -125,24 +173,19 @@ fn one_delete(
 
     let record = diesel::update(deletions.find(delete.id)).set(executed_at.eq(Utc::now()));
 
+    // A failed delete is reported but doesn’t stop the other requests, the
+    // request stays pending and will be retried on the next run.
     if let Err(err) = source.delete(conn, &status) {
         println!("!! Could not delete status: {:?}", err);
 
         if let DeleteError::AlreadyDone = err {
-            record.execute(conn).expect(&format!(
-                "!! Failed to record deletion status for #{}",
-                delete.id
-            ));
+            record.execute(conn)?;
         }
 
-        0
+        Ok(0)
     } else {
-        record.execute(conn).expect(&format!(
-            "!! Failed to record deletion success for #{}",
-            delete.id
-        ));
-
-        1
+        record.execute(conn)?;
+        Ok(1)
     }
 }
 
@@ -160,28 +203,40 @@ impl Default for ActionMode {
 }
 
 pub trait StatusSource {
-    fn sync(&self, conn: &PgConnection) -> bool;
-    fn delete(&self, conn: &PgConnection, status: &Status) -> Result<(), DeleteError>;
+    fn account(&self) -> &Account;
+    fn sync(&self, conn: &DbConnection) -> Result<(), OmeletteError>;
+    fn delete(&self, conn: &DbConnection, status: &Status) -> Result<(), DeleteError>;
 }
 
 #[derive(Debug)]
 pub enum LoadError {
-    Env(VarError),
+    Missing(String, &'static str),
+    Parse(String, &'static str),
 }
 
-impl From<VarError> for LoadError {
-    fn from(err: VarError) -> LoadError {
-        LoadError::Env(err)
-    }
+/// Reads a setting a source needs from its account’s configuration.
+pub fn setting(
+    account: &AccountConfig,
+    field: &'static str,
+    value: &Option<String>,
+) -> Result<String, LoadError> {
+    value
+        .clone()
+        .ok_or_else(|| LoadError::Missing(account.name.clone(), field))
 }
 
 #[derive(Debug)]
 pub enum DeleteError {
     AlreadyDone,
     WrongSource,
+    InvalidId(String),
     Unimplemented,
     Database(DieselError),
     Twitter(EggError),
+    Mastodon(ReqwestError),
+    /// Anything else from a call that isn’t specific to deleting, like waiting
+    /// out a rate limit.
+    Other(Box<OmeletteError>),
 }
 
 impl From<DieselError> for DeleteError {
@@ -195,3 +250,21 @@ impl From<EggError> for DeleteError {
         DeleteError::Twitter(err)
     }
 }
+
+impl From<ReqwestError> for DeleteError {
+    fn from(err: ReqwestError) -> DeleteError {
+        DeleteError::Mastodon(err)
+    }
+}
+
+impl From<OmeletteError> for DeleteError {
+    fn from(err: OmeletteError) -> DeleteError {
+        match err {
+            OmeletteError::Database(err) => DeleteError::Database(err),
+            OmeletteError::Twitter(err) => DeleteError::Twitter(err),
+            OmeletteError::Mastodon(err) => DeleteError::Mastodon(err),
+            OmeletteError::Delete(err) => err,
+            err => DeleteError::Other(Box::new(err)),
+        }
+    }
+}
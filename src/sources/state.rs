@@ -0,0 +1,101 @@
+use chrono::Utc;
+use crate::db::DbConnection;
+use crate::error::OmeletteError;
+use crate::inserts::NewSyncState;
+use crate::models::{Account, SyncState};
+use diesel::prelude::*;
+
+/// Loads the state for a sync pass, creating it if it’s the first run, and
+/// records that the pass has started.
+///
+/// Counts are kept when resuming an interrupted pass, reset otherwise.
+pub fn start(conn: &DbConnection, account: &Account, pass: &str) -> Result<SyncState, OmeletteError> {
+    use crate::schema::sync_states::dsl::*;
+
+    insert_or_ignore!(
+        sync_states,
+        &NewSyncState {
+            source: account.source.clone(),
+            kind: pass.into(),
+            account_id: Some(account.id),
+        },
+        (account_id, kind)
+    )
+    .execute(conn)?;
+
+    let mut state: SyncState = sync_states
+        .filter(account_id.eq(account.id))
+        .filter(kind.eq(pass))
+        .first(conn)?;
+
+    if state.cursor.is_none() {
+        state.fetched_count = 0;
+        state.inserted_count = 0;
+    }
+
+    state.last_run_at = Some(Utc::now());
+    state.last_error = None;
+    state.save(conn)?;
+    Ok(state)
+}
+
+/// Every sync state, with the account it’s for if it has been linked to one.
+pub fn all(conn: &DbConnection) -> Result<Vec<(SyncState, Option<Account>)>, OmeletteError> {
+    use crate::schema::accounts;
+    use crate::schema::sync_states::dsl::*;
+
+    Ok(sync_states
+        .left_join(accounts::table)
+        .order_by((source, accounts::name, kind))
+        .load(conn)?)
+}
+
+impl SyncState {
+    pub fn save(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        use crate::schema::sync_states::dsl::*;
+
+        diesel::update(sync_states.find(self.id))
+            .set(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Records a batch as saved, and where to resume from if we stop here.
+    pub fn progress(
+        &mut self,
+        conn: &DbConnection,
+        cursor: Option<String>,
+        fetched: usize,
+        inserted: usize,
+    ) -> Result<(), OmeletteError> {
+        self.cursor = cursor;
+        self.fetched_count += fetched as i32;
+        self.inserted_count += inserted as i32;
+        self.save(conn)
+    }
+
+    /// Records the outcome of a pass, passing it through.
+    ///
+    /// On success the cursor is cleared, so the next run starts afresh. On
+    /// failure it’s kept so the next run resumes, and the error is stored.
+    pub fn record(
+        &mut self,
+        conn: &DbConnection,
+        res: Result<(), OmeletteError>,
+    ) -> Result<(), OmeletteError> {
+        match res {
+            Ok(()) => {
+                self.cursor = None;
+                self.last_success_at = Some(Utc::now());
+            }
+            Err(ref err) => {
+                println!("!! {}", err);
+                self.last_error = Some(format!("{}", err));
+            }
+        }
+
+        self.save(conn)?;
+        res
+    }
+}
@@ -0,0 +1,215 @@
+//! Calls to the Twitter API that keep within its rate limits.
+//!
+//! Twitter allows each token so many calls per endpoint in a 15-minute window,
+//! and says how many are left with every response. That’s recorded in the
+//! database, so tools running at the same time share the budget: each call
+//! takes one from it first, and waits for the window to reset when it’s empty.
+
+use chrono::{DateTime, TimeZone, Utc};
+use crate::db::DbConnection;
+use crate::error::OmeletteError;
+use crate::inserts::NewRateLimit;
+use crate::models::RateLimit;
+use diesel::prelude::*;
+use egg_mode::{error::Error as EggError, Response};
+use futures::Future;
+use std::{thread::sleep, time::Duration};
+use tokio::runtime::current_thread::block_on_all;
+
+/// Retries for failures that aren’t ours, after which the error is returned.
+/// Waiting out a rate limit isn’t one: the call wasn’t made.
+const MAX_RETRIES: u32 = 5;
+
+/// Makes a call to `endpoint` within the budget of `scope` (the token’s user),
+/// retrying when rate-limited or when Twitter has trouble of its own.
+///
+/// `request` is called again for each attempt, as futures can’t be retried.
+pub fn call<T, F, R>(
+    conn: &DbConnection,
+    scope: &str,
+    endpoint: &str,
+    request: F,
+) -> Result<Response<T>, OmeletteError>
+where
+    F: Fn() -> R,
+    R: Future<Item = Response<T>, Error = EggError>,
+{
+    attempt(conn, scope, endpoint, true, request)
+}
+
+/// Like `call`, but only retries when rate-limited. For calls that change
+/// something and can’t be made twice, like deleting or un-retweeting, which
+/// may have gone through even when Twitter has trouble answering.
+pub fn call_once<T, F, R>(
+    conn: &DbConnection,
+    scope: &str,
+    endpoint: &str,
+    request: F,
+) -> Result<Response<T>, OmeletteError>
+where
+    F: Fn() -> R,
+    R: Future<Item = Response<T>, Error = EggError>,
+{
+    attempt(conn, scope, endpoint, false, request)
+}
+
+fn attempt<T, F, R>(
+    conn: &DbConnection,
+    scope: &str,
+    endpoint: &str,
+    retry_trouble: bool,
+    request: F,
+) -> Result<Response<T>, OmeletteError>
+where
+    F: Fn() -> R,
+    R: Future<Item = Response<T>, Error = EggError>,
+{
+    let mut failures = 0;
+    loop {
+        if let Some(reset) = take(conn, scope, endpoint)? {
+            wait_until(endpoint, reset);
+        }
+
+        let err = match block_on_all(request()) {
+            Ok(response) => {
+                record(
+                    conn,
+                    scope,
+                    endpoint,
+                    response.rate_limit,
+                    response.rate_limit_remaining,
+                    response.rate_limit_reset,
+                )?;
+                return Ok(response);
+            }
+            Err(err) => err,
+        };
+
+        match err {
+            EggError::RateLimit(reset) => {
+                record(conn, scope, endpoint, 0, 0, reset)?;
+                wait_until(endpoint, Utc.timestamp(reset as i64, 0));
+            }
+            ref err if retry_trouble && failures < MAX_RETRIES && is_trouble(err) => {
+                failures += 1;
+                backoff(endpoint, failures, err);
+            }
+            err => return Err(err.into()),
+        }
+    }
+}
+
+/// Whether Twitter had trouble of its own: a server error, being over
+/// capacity, or an internal error.
+fn is_trouble(err: &EggError) -> bool {
+    match err {
+        EggError::BadStatus(code) => code.is_server_error(),
+        EggError::TwitterError(errs) => errs.errors.iter().any(|e| e.code == 130 || e.code == 131),
+        _ => false,
+    }
+}
+
+/// Whether Twitter says what was asked for doesn’t exist (anymore).
+pub fn is_not_found(err: &OmeletteError) -> bool {
+    match err {
+        OmeletteError::Twitter(EggError::BadStatus(code)) => code.as_u16() == 404,
+        // No such page, or no such status
+        OmeletteError::Twitter(EggError::TwitterError(errs)) => {
+            errs.errors.iter().any(|e| e.code == 34 || e.code == 144)
+        }
+        _ => false,
+    }
+}
+
+/// Takes a call from the budget. If there’s none left, returns when the
+/// window resets. Unknown or expired budgets let the call through, and its
+/// response says what the budget is.
+fn take(
+    conn: &DbConnection,
+    s: &str,
+    e: &str,
+) -> Result<Option<DateTime<Utc>>, OmeletteError> {
+    use crate::schema::rate_limits::dsl::*;
+
+    let now = Utc::now();
+    let taken = diesel::update(
+        rate_limits
+            .filter(scope.eq(s))
+            .filter(endpoint.eq(e))
+            .filter(remaining.gt(0))
+            .filter(reset_at.gt(now)),
+    )
+    .set(remaining.eq(remaining - 1))
+    .execute(conn)?;
+
+    if taken > 0 {
+        return Ok(None);
+    }
+
+    let state: Option<RateLimit> = rate_limits
+        .filter(scope.eq(s))
+        .filter(endpoint.eq(e))
+        .first(conn)
+        .optional()?;
+
+    Ok(match state {
+        Some(ref state) if state.reset_at > now => Some(state.reset_at),
+        _ => None,
+    })
+}
+
+/// Records the budget as given by Twitter, which has the last word over what
+/// other tools took from it in the meantime.
+fn record(
+    conn: &DbConnection,
+    s: &str,
+    e: &str,
+    limit: i32,
+    left: i32,
+    reset: i32,
+) -> Result<(), OmeletteError> {
+    use crate::schema::rate_limits::dsl::*;
+
+    // Some responses come without rate-limit headers, leave those be.
+    if reset <= 0 {
+        return Ok(());
+    }
+
+    let state = NewRateLimit {
+        scope: s.into(),
+        endpoint: e.into(),
+        quota: limit,
+        remaining: left,
+        reset_at: Utc.timestamp(reset as i64, 0),
+        updated_at: Utc::now(),
+    };
+
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let inserted = insert_or_ignore!(rate_limits, &state, (scope, endpoint)).execute(conn)?;
+        if inserted == 0 {
+            diesel::update(rate_limits.filter(scope.eq(s)).filter(endpoint.eq(e)))
+                .set(&state)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+fn wait_until(endpoint: &str, reset: DateTime<Utc>) {
+    // A second more, as clocks and Twitter’s idea of the window don’t quite agree.
+    let seconds = (reset - Utc::now()).num_seconds().max(0) as u64 + 1;
+    println!("~~ Rate limit reached for {}, waiting {}s", endpoint, seconds);
+    sleep(Duration::from_secs(seconds));
+}
+
+fn backoff(endpoint: &str, attempt: u32, err: &EggError) {
+    let seconds = 2u64.pow(attempt);
+    println!(
+        "~~ Twitter error on {}, retrying in {}s: {}",
+        endpoint, seconds, err
+    );
+    sleep(Duration::from_secs(seconds));
+}
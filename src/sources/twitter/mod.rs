@@ -0,0 +1,1093 @@
+use chrono::{DateTime, Utc};
+use crate::db::{
+    insert_new_statuses, insert_text_entities, insert_variants, upsert_twitter_users, DbConnection,
+};
+use crate::error::{parse_id, OmeletteError};
+use crate::inserts::{
+    NewEntity, NewEntityVariant, NewGraphSnapshot, NewStatus, NewTwitterBlock, NewTwitterLike,
+    NewTwitterMute, NewTwitterUser, NewTwitterUserID, TextEntities,
+};
+use crate::accounts;
+use crate::config::{AccountConfig, Config};
+use crate::models::{Account, GraphMember, GraphSnapshot, Status, SyncState};
+use crate::sources::{setting, state as sync_state, DeleteError, LoadError, StatusSource};
+use crate::types::Source;
+use diesel::prelude::*;
+use egg_mode::error::Error as EggError;
+use egg_mode::tweet::{delete, liked_by, unretweet, user_timeline, Tweet};
+use egg_mode::{
+    user::{UserID, blocks_ids, followers_ids, friends_ids, mutes_ids},
+    KeyPair, Response, Token,
+};
+use futures::Future;
+use std::{collections::HashMap, str::FromStr};
+
+pub mod client;
+
+/// Hours between two checks for un-likes, see `Twitter::sync_unlikes`.
+const UNLIKES_EVERY_HOURS: i64 = 24;
+
+/// The lists of users an account keeps, which are fetched as IDs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UserList {
+    Blocks,
+    Mutes,
+}
+
+impl UserList {
+    /// Also the kind of sync pass.
+    pub fn name(self) -> &'static str {
+        match self {
+            UserList::Blocks => "blocks",
+            UserList::Mutes => "mutes",
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            UserList::Blocks => "blocks/ids",
+            UserList::Mutes => "mutes/users/ids",
+        }
+    }
+}
+
+/// The two sides of an account’s follow graph, which are snapshotted over time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GraphKind {
+    /// Users following the account.
+    Followers,
+    /// Users the account follows.
+    Following,
+}
+
+impl GraphKind {
+    pub fn all() -> [GraphKind; 2] {
+        [GraphKind::Followers, GraphKind::Following]
+    }
+
+    /// Also the kind stored with snapshots.
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphKind::Followers => "followers",
+            GraphKind::Following => "following",
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            GraphKind::Followers => "followers/ids",
+            GraphKind::Following => "friends/ids",
+        }
+    }
+}
+
+impl FromStr for GraphKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "followers" => Ok(GraphKind::Followers),
+            "following" => Ok(GraphKind::Following),
+            _ => Err(format!("unknown graph kind {:?}, expected followers or following", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Twitter {
+    pub token: Token,
+    pub id: UserID<'static>,
+    pub account: Account,
+}
+
+impl Twitter {
+    pub fn source() -> Source {
+        Source::Twitter
+    }
+
+    pub fn load(settings: &AccountConfig, account: Account) -> Result<Box<StatusSource>, LoadError> {
+        let tw = Self::load_unboxed(settings, account)?;
+        Ok(Box::new(tw))
+    }
+
+    pub fn load_unboxed(settings: &AccountConfig, account: Account) -> Result<Self, LoadError> {
+        let con_token = KeyPair::new(
+            setting(settings, "consumer_key", &settings.consumer_key)?,
+            setting(settings, "consumer_secret", &settings.consumer_secret)?,
+        );
+        let access_token = KeyPair::new(
+            setting(settings, "access_token_key", &settings.access_token_key)?,
+            setting(settings, "access_token_secret", &settings.access_token_secret)?,
+        );
+        let token = Token::Access {
+            consumer: con_token,
+            access: access_token,
+        };
+
+        let uid: u64 = account
+            .source_id
+            .parse()
+            .map_err(|_| LoadError::Parse(settings.name.clone(), "id"))?;
+
+        Ok(Self {
+            token,
+            id: uid.into(),
+            account,
+        })
+    }
+
+    /// Loads the configured Twitter accounts, or only the one named, for the
+    /// tools that work on Twitter alone.
+    pub fn all(
+        conn: &DbConnection,
+        config: &Config,
+        only: &Option<String>,
+    ) -> Result<Vec<Self>, OmeletteError> {
+        let mut loaded = Vec::new();
+        for (settings, account) in accounts::load(conn, config, only)? {
+            if account.source == Source::Twitter {
+                loaded.push(Self::load_unboxed(&settings, account)?);
+            }
+        }
+
+        if loaded.is_empty() {
+            return Err(OmeletteError::Config("no twitter account configured".into()));
+        }
+
+        Ok(loaded)
+    }
+
+    pub fn user_id(&self) -> u64 {
+        match self.id {
+            UserID::ID(uid) => uid,
+            UserID::ScreenName(_) => unreachable!("Twitter source is always loaded with a numeric ID"),
+        }
+    }
+
+    /// Makes a call to the API within this account’s rate limits, see `client`.
+    pub fn call<T, F, R>(
+        &self,
+        conn: &DbConnection,
+        endpoint: &str,
+        request: F,
+    ) -> Result<Response<T>, OmeletteError>
+    where
+        F: Fn() -> R,
+        R: Future<Item = Response<T>, Error = EggError>,
+    {
+        client::call(conn, &format!("{}", self.user_id()), endpoint, request)
+    }
+
+    /// Makes a call that can’t be made twice, see `client::call_once`.
+    pub fn call_once<T, F, R>(
+        &self,
+        conn: &DbConnection,
+        endpoint: &str,
+        request: F,
+    ) -> Result<Response<T>, OmeletteError>
+    where
+        F: Fn() -> R,
+        R: Future<Item = Response<T>, Error = EggError>,
+    {
+        client::call_once(conn, &format!("{}", self.user_id()), endpoint, request)
+    }
+
+    /// Fetches the block or mute list, saving the cursor after each page so
+    /// that an interrupted import resumes where it stopped. Once the whole
+    /// list has been seen, users listed before the pass started but no longer
+    /// in the list are marked as unblocked or unmuted.
+    pub fn sync_user_list(&self, conn: &DbConnection, list: UserList) -> Result<(), OmeletteError> {
+        let mut state = sync_state::start(conn, &self.account, list.name())?;
+        let res = self.user_list_pass(conn, &mut state, list);
+        state.record(conn, res)
+    }
+
+    fn user_list_pass(
+        &self,
+        conn: &DbConnection,
+        state: &mut SyncState,
+        list: UserList,
+    ) -> Result<(), OmeletteError> {
+        let mut cursor = match list {
+            UserList::Blocks => blocks_ids(&self.token),
+            UserList::Mutes => mutes_ids(&self.token),
+        }
+        .with_page_size(5000);
+
+        // The pass start is kept in the state, as a resumed pass must only
+        // clear users that weren’t seen since the very start.
+        let started = match (state.cursor.clone(), state.pass_started_at) {
+            (Some(next), Some(at)) => {
+                cursor.next_cursor = next.parse().map_err(|_| {
+                    OmeletteError::Data(format!("bad {} cursor {:?}", list.name(), next))
+                })?;
+                println!(":: Resuming {} import from cursor {}", list.name(), next);
+                at
+            }
+            _ => {
+                let now = Utc::now();
+                state.pass_started_at = Some(now);
+                now
+            }
+        };
+
+        loop {
+            let page = self.call(conn, list.endpoint(), || cursor.call())?;
+            let inserted = self.store_listed(conn, list, &page.ids)?;
+
+            let next = match page.next_cursor {
+                0 => None,
+                n => Some(n),
+            };
+
+            state.progress(conn, next.map(|n| format!("{}", n)), page.ids.len(), inserted)?;
+            println!(
+                "-> Fetched {} {} IDs, {} new users so far",
+                state.fetched_count, list.name(), state.inserted_count
+            );
+
+            match next {
+                Some(n) => cursor.next_cursor = n,
+                None => break,
+            }
+        }
+
+        // Only this account’s list was fetched, so only its rows are cleared.
+        // They’re kept, with when the user was noticed gone from the list.
+        let account = self.account.id;
+        let now = Utc::now();
+        macro_rules! clear_unlisted {
+            ($table:ident, $flag:ident, $cleared:ident) => {{
+                use crate::schema::$table::dsl::*;
+                diesel::update(
+                    $table
+                        .filter(account_id.eq(account))
+                        .filter($cleared.is_null())
+                        .filter($flag.lt(started))
+                        .filter(listed_at.is_null().or(listed_at.lt(started))),
+                )
+                .set($cleared.eq(now))
+                .execute(conn)?
+            }};
+        }
+
+        let cleared = match list {
+            UserList::Blocks => clear_unlisted!(twitter_blocks, blocked_at, unblocked_at),
+            UserList::Mutes => clear_unlisted!(twitter_mutes, muted_at, unmuted_at),
+        };
+
+        state.pass_started_at = None;
+        state.synced_to = Some(Utc::now().to_rfc3339());
+
+        println!(
+            "=> Fetched {} {}, inserted {} new user IDs, and noticed {} removed from the list",
+            state.fetched_count, list.name(), state.inserted_count, cleared
+        );
+        Ok(())
+    }
+
+    /// Stores a page of listed user IDs, returning how many users were new.
+    /// They’re recorded as listed for this account only.
+    fn store_listed(&self, conn: &DbConnection, list: UserList, ids: &[u64]) -> Result<usize, OmeletteError> {
+        let now = Utc::now();
+        let account = self.account.id;
+        let userbag: Vec<NewTwitterUserID> = ids.iter().map(|uid| (*uid).into()).collect();
+        let source_ids: Vec<String> = userbag.iter().map(|u| u.source_id.clone()).collect();
+
+        // Users listed again after being noticed gone were re-blocked or
+        // re-muted since, so that starts afresh.
+        macro_rules! mark_listed {
+            ($table:ident, $row:ident, $flag:ident, $cleared:ident, $users:expr) => {{
+                use crate::schema::$table::dsl::*;
+
+                let rows: Vec<$row> = $users
+                    .iter()
+                    .map(|uid| $row {
+                        account_id: Some(account),
+                        user_id: *uid,
+                        $flag: now,
+                        listed_at: Some(now),
+                    })
+                    .collect();
+                insert_or_ignore!($table, &rows, (account_id, user_id)).execute(conn)?;
+
+                diesel::update(
+                    $table
+                        .filter(account_id.eq(account))
+                        .filter(user_id.eq_any($users))
+                        .filter($cleared.is_not_null()),
+                )
+                .set(($flag.eq(now), $cleared.eq(None::<DateTime<Utc>>)))
+                .execute(conn)?;
+
+                diesel::update(
+                    $table
+                        .filter(account_id.eq(account))
+                        .filter(user_id.eq_any($users)),
+                )
+                .set(listed_at.eq(now))
+                .execute(conn)?;
+            }};
+        }
+
+        Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+            let (inserted, users) = {
+                use crate::schema::twitter_users::dsl::*;
+                let inserted = insert_or_ignore!(twitter_users, &userbag, source_id).execute(conn)?;
+                let users: Vec<i32> = twitter_users
+                    .filter(source_id.eq_any(&source_ids))
+                    .select(id)
+                    .load(conn)?;
+                (inserted, users)
+            };
+
+            match list {
+                UserList::Blocks => {
+                    mark_listed!(twitter_blocks, NewTwitterBlock, blocked_at, unblocked_at, &users)
+                }
+                UserList::Mutes => {
+                    mark_listed!(twitter_mutes, NewTwitterMute, muted_at, unmuted_at, &users)
+                }
+            }
+
+            Ok(inserted)
+        })?)
+    }
+
+    /// Takes a snapshot of the followers or followed users of the account.
+    ///
+    /// Users not yet known are inserted with their ID only, for hydrate to fill
+    /// in. The snapshot is only marked complete once the whole list has been
+    /// fetched, and is dropped if that fails, so diffs never compare against a
+    /// partial list.
+    pub fn snapshot_graph(
+        &self,
+        conn: &DbConnection,
+        graph: GraphKind,
+    ) -> Result<GraphSnapshot, OmeletteError> {
+        use crate::schema::graph_snapshots::dsl::*;
+
+        diesel::insert_into(graph_snapshots)
+            .values(&NewGraphSnapshot {
+                account_id: self.account.id,
+                kind: graph.name().into(),
+                taken_at: Utc::now(),
+            })
+            .execute(conn)?;
+
+        let snapshot: GraphSnapshot = graph_snapshots
+            .filter(account_id.eq(self.account.id))
+            .filter(kind.eq(graph.name()))
+            .order_by(id.desc())
+            .first(conn)?;
+
+        match self.graph_pass(conn, &snapshot, graph) {
+            Ok(total) => {
+                diesel::update(graph_snapshots.find(snapshot.id))
+                    .set((complete.eq(true), count.eq(total as i32)))
+                    .execute(conn)?;
+
+                Ok(GraphSnapshot {
+                    complete: true,
+                    count: total as i32,
+                    ..snapshot
+                })
+            }
+            Err(err) => {
+                diesel::delete(graph_snapshots.find(snapshot.id)).execute(conn)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn graph_pass(
+        &self,
+        conn: &DbConnection,
+        snapshot: &GraphSnapshot,
+        kind: GraphKind,
+    ) -> Result<usize, OmeletteError> {
+        let mut cursor = match kind {
+            GraphKind::Followers => followers_ids(self.id, &self.token),
+            GraphKind::Following => friends_ids(self.id, &self.token),
+        }
+        .with_page_size(5000);
+
+        let mut total = 0;
+        let mut inserted = 0;
+        loop {
+            let page = self.call(conn, kind.endpoint(), || cursor.call())?;
+            inserted += Self::store_graph_page(conn, snapshot, &page.ids)?;
+            total += page.ids.len();
+
+            println!("-> Fetched {} {} IDs, {} new users so far", total, kind.name(), inserted);
+
+            match page.next_cursor {
+                0 => break,
+                n => cursor.next_cursor = n,
+            }
+        }
+
+        println!(
+            "=> Snapshot #{} of {} {}: {} users, {} new to the database",
+            snapshot.id, self.account.name, kind.name(), total, inserted
+        );
+        Ok(total)
+    }
+
+    /// Stores a page of user IDs into a snapshot, returning how many users were new.
+    fn store_graph_page(
+        conn: &DbConnection,
+        snapshot: &GraphSnapshot,
+        ids: &[u64],
+    ) -> Result<usize, OmeletteError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let userbag: Vec<NewTwitterUserID> = ids.iter().map(|uid| (*uid).into()).collect();
+        let source_ids: Vec<String> = userbag.iter().map(|u| u.source_id.clone()).collect();
+
+        Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+            let (inserted, users) = {
+                use crate::schema::twitter_users::dsl::*;
+                let inserted = insert_or_ignore!(twitter_users, &userbag, source_id).execute(conn)?;
+                let users: Vec<i32> = twitter_users
+                    .filter(source_id.eq_any(&source_ids))
+                    .select(id)
+                    .load(conn)?;
+                (inserted, users)
+            };
+
+            let members: Vec<GraphMember> = users
+                .into_iter()
+                .map(|uid| GraphMember {
+                    snapshot_id: snapshot.id,
+                    user_id: uid,
+                })
+                .collect();
+
+            {
+                use crate::schema::graph_members::dsl::*;
+                insert_or_ignore!(graph_members, &members, (snapshot_id, user_id)).execute(conn)?;
+            }
+
+            Ok(inserted)
+        })?)
+    }
+
+    /// Stores or refreshes the authors of tweets as users, returning their
+    /// internal IDs by the source ID of the statuses made from those tweets.
+    pub fn store_authors<'a, I>(conn: &DbConnection, tweets: I) -> Result<HashMap<String, i32>, OmeletteError>
+    where
+        I: IntoIterator<Item = &'a Tweet>,
+    {
+        let mut users: HashMap<u64, NewTwitterUser> = HashMap::new();
+        let mut written_by: Vec<(String, String)> = Vec::new();
+        for tweet in tweets {
+            let otweet = tweet.retweeted_status.as_ref().map(|t| &**t).unwrap_or(tweet);
+            if let Some(ref user) = otweet.user {
+                users.entry(user.id).or_insert_with(|| (&**user).into());
+                written_by.push((format!("{}", otweet.id), format!("{}", user.id)));
+            }
+        }
+
+        let userbag: Vec<NewTwitterUser> = users.into_iter().map(|(_, user)| user).collect();
+        let ids = upsert_twitter_users(conn, &userbag)?;
+
+        Ok(written_by
+            .into_iter()
+            .filter_map(|(status, user)| ids.get(&user).map(|uid| (status, *uid)))
+            .collect())
+    }
+
+    /// Links Twitter statuses without an author, such as those imported from a
+    /// dump, using the ID at the end of their `source_author`. Authors not yet
+    /// known are inserted slim, for hydrate to fill in.
+    pub fn link_authors(conn: &DbConnection) -> Result<usize, OmeletteError> {
+        use crate::schema::statuses::dsl::*;
+
+        let unlinked: Vec<(i32, String)> = statuses
+            .select((id, source_author))
+            .filter(source.eq(Source::Twitter))
+            .filter(author_id.is_null())
+            .load(conn)?;
+
+        let mut by_author: HashMap<u64, Vec<i32>> = HashMap::new();
+        for (sid, author) in &unlinked {
+            if let Some(uid) = author_source_id(author) {
+                by_author.entry(uid).or_insert_with(Vec::new).push(*sid);
+            }
+        }
+
+        if by_author.is_empty() {
+            return Ok(0);
+        }
+
+        Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+            let users: HashMap<String, i32> = {
+                use crate::schema::twitter_users::dsl::*;
+                let userbag: Vec<NewTwitterUserID> = by_author.keys().map(|uid| (*uid).into()).collect();
+                insert_or_ignore!(twitter_users, &userbag, source_id).execute(conn)?;
+
+                twitter_users
+                    .select((source_id, id))
+                    .filter(source_id.eq_any(userbag.iter().map(|u| u.source_id.clone())))
+                    .load::<(String, i32)>(conn)?
+                    .into_iter()
+                    .collect()
+            };
+
+            let mut linked = 0;
+            for (uid, sids) in &by_author {
+                if let Some(user) = users.get(&format!("{}", uid)) {
+                    linked += diesel::update(statuses.filter(id.eq_any(sids)))
+                        .set(author_id.eq(*user))
+                        .execute(conn)?;
+                }
+            }
+
+            Ok(linked)
+        })?)
+    }
+
+    /// Stores a page of tweets and their entities, returning those that were new.
+    fn store_tweets(
+        conn: &DbConnection,
+        mut statusbag: Vec<NewStatus>,
+        tweets: &[Tweet],
+    ) -> Result<Vec<Status>, OmeletteError> {
+        let authors = Self::store_authors(conn, tweets)?;
+        for status in &mut statusbag {
+            status.author_id = authors.get(&status.source_id).cloned();
+        }
+
+        let mut entitybag: HashMap<String, Vec<NewEntity>> = HashMap::new();
+        let mut variantbag = HashMap::new();
+        let mut textbag = HashMap::new();
+        for tweet in tweets {
+            let otweet = tweet.retweeted_status.as_ref().map(|t| &**t).unwrap_or(tweet);
+            if let Some(ref ents) = tweet.extended_entities {
+                entitybag.insert(format!("{}", otweet.id), NewEntity::from_extended(ents));
+                variantbag.extend(NewEntityVariant::from_extended(ents));
+            }
+            textbag.insert(format!("{}", otweet.id), TextEntities::from(otweet));
+        }
+
+        let inserted_tweets: Vec<Status> = insert_new_statuses(conn, &statusbag)?;
+
+        let mut entitysack = Vec::with_capacity(entitybag.len() * 4);
+        for inserted in &inserted_tweets {
+            if let Some(ents) = entitybag.remove(&inserted.source_id) {
+                for mut ent in ents.into_iter() {
+                    ent.status_id = inserted.id;
+                    entitysack.push(ent);
+                }
+            }
+        }
+
+        {
+            use crate::schema::entities::dsl::*;
+            insert_or_ignore!(entities, &entitysack, (source, source_id)).execute(conn)?
+        };
+        insert_variants(conn, &Source::Twitter, &variantbag)?;
+        insert_text_entities(conn, &textbag)?;
+
+        Ok(inserted_tweets)
+    }
+
+    /// Pages down the timeline from `max` (or the top) to `since`, saving each
+    /// page as it comes and recording where to resume from in the sync state.
+    ///
+    /// Returns the highest tweet ID seen, or the error that stopped the pass.
+    fn sync_pages(
+        &self,
+        conn: &DbConnection,
+        state: &mut SyncState,
+        since: Option<u64>,
+        max: Option<u64>,
+        single: bool,
+    ) -> Result<Option<u64>, OmeletteError> {
+        let timeline = user_timeline(self.id, true, true, &self.token).with_page_size(200);
+        let mut max = max;
+        let mut top = None;
+        let mut batch = 0;
+
+        loop {
+            let feed = self.call(conn, "statuses/user_timeline", || timeline.call(since, max))?;
+            batch += 1;
+
+            let ids: Vec<u64> = feed.iter().map(|t| t.id).collect();
+            let (highest, lowest) = match (ids.iter().max(), ids.iter().min()) {
+                (Some(h), Some(l)) => (*h, *l),
+                _ => {
+                    println!("-> Batch {} is empty, end of the line", batch);
+                    break;
+                }
+            };
+
+            let statusbag: Vec<NewStatus> = feed
+                .iter()
+                .map(|tweet| {
+                    let mut status: NewStatus = tweet.into();
+                    status.account_id = Some(self.account.id);
+                    status
+                })
+                .collect();
+            let inserted = Self::store_tweets(conn, statusbag, &feed)?;
+
+            println!(
+                "-> Batch {} ({} tweets, {} new) from {} down to {}",
+                batch, ids.len(), inserted.len(), highest, lowest
+            );
+
+            top = top.or(Some(highest));
+            max = Some(lowest - 1);
+
+            // Remember where this pass will have synced to once complete, as
+            // the top page won’t be seen again if it’s interrupted and resumed.
+            if state.pending_to.is_none() {
+                state.pending_to = top.map(|id| format!("{}", id));
+            }
+
+            state.progress(conn, max.map(|m| format!("{}", m)), ids.len(), inserted.len())?;
+
+            if single {
+                break;
+            }
+        }
+
+        Ok(top)
+    }
+
+    fn latest_own_id_in_db(&self, conn: &DbConnection) -> Result<Option<u64>, OmeletteError> {
+        use crate::models::length;
+        use crate::schema::statuses::dsl::*;
+
+        statuses.select(source_id)
+            .filter(source.eq(Source::Twitter))
+            .filter(is_repost.eq(false))
+            // Liked tweets from others are in there too
+            .filter(source_author.like(format!("% ({})", self.user_id())))
+            // IDs are numeric strings without leading zeroes, so ordering by
+            // length then text is numeric ordering, on any backend:
+            .order_by((length(source_id).desc(), source_id.desc()))
+            .first::<String>(conn)
+            .optional()?
+            .map(|sid| parse_id(&sid))
+            .transpose()
+    }
+
+    /// Fetches tweets newer than the last forward sync.
+    ///
+    /// Tweets come newest first, so an interrupted pass resumes from the
+    /// oldest page it saved, and only moves the synced-to mark once it has
+    /// caught up with the previous one. On the very first run (nothing synced
+    /// and nothing in the DB) only the latest page is fetched, and the rest is
+    /// left to the backfill.
+    pub fn sync_forward(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        let mut state = sync_state::start(conn, &self.account, "forward")?;
+        let res = self.forward_pass(conn, &mut state);
+        state.record(conn, res)
+    }
+
+    fn forward_pass(&self, conn: &DbConnection, state: &mut SyncState) -> Result<(), OmeletteError> {
+        let since = match state.synced_to {
+            Some(ref id) => Some(parse_id(id)?),
+            None => self.latest_own_id_in_db(conn)?,
+        };
+        let max = state.cursor.as_ref().map(|id| parse_id(id)).transpose()?;
+
+        println!(":: Latest twitter ID synced:\t\t{}", since.map(|id| format!("{}", id)).unwrap_or("none".into()));
+        if let Some(m) = max {
+            println!(":: Resuming interrupted sync from:\t{}", m);
+        }
+
+        let top = self.sync_pages(conn, state, since, max, since.is_none())?;
+
+        let pending = state.pending_to.as_ref().map(|id| parse_id(id)).transpose()?;
+        let synced = pending.or(top).or(since);
+        state.synced_to = synced.map(|id| format!("{}", id));
+        state.pending_to = None;
+
+        println!(
+            "=> Retrieved {} tweets and inserted {} new in DB",
+            state.fetched_count, state.inserted_count
+        );
+        Ok(())
+    }
+
+    /// Fetches the whole timeline from the top down, until the API stops
+    /// giving any more (it only goes back ~3200 tweets). This is done once,
+    /// and can be interrupted and resumed.
+    pub fn sync_backfill(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        let mut state = sync_state::start(conn, &self.account, "backfill")?;
+        if state.done {
+            println!("-- Backfill already complete, skip.");
+            return Ok(());
+        }
+
+        let res = self.backfill_pass(conn, &mut state);
+        state.record(conn, res)
+    }
+
+    fn backfill_pass(&self, conn: &DbConnection, state: &mut SyncState) -> Result<(), OmeletteError> {
+        let max = state.cursor.as_ref().map(|id| parse_id(id)).transpose()?;
+
+        println!(
+            ":: Backfilling twitter from:\t\t{}",
+            max.map(|id| format!("{}", id)).unwrap_or("the top".into())
+        );
+
+        self.sync_pages(conn, state, None, max, false)?;
+
+        state.done = true;
+        state.synced_to = state.cursor.clone();
+        state.pending_to = None;
+
+        println!(
+            "=> Backfill complete: retrieved {} tweets and inserted {} new in DB",
+            state.fetched_count, state.inserted_count
+        );
+        Ok(())
+    }
+
+    /// Likes come back in the order they were made, newest first, so this
+    /// pages down from the top until a page brings no like the account didn’t
+    /// already have. An interrupted pass resumes from the last page it saved.
+    pub fn sync_likes(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        let mut state = sync_state::start(conn, &self.account, "likes")?;
+        let res = self.likes_pass(conn, &mut state);
+        state.record(conn, res)
+    }
+
+    fn likes_pass(&self, conn: &DbConnection, state: &mut SyncState) -> Result<(), OmeletteError> {
+        let max = state.cursor.as_ref().map(|id| parse_id(id)).transpose()?;
+
+        if let Some(m) = max {
+            println!(":: Resuming interrupted likes sync from:\t{}", m);
+        }
+
+        let seen = self.like_pages(conn, state, max, true)?;
+        if max.is_none() {
+            if let Some(top) = seen.first() {
+                state.synced_to = Some(format!("{}", top));
+            }
+        }
+
+        println!(
+            "=> Retrieved {} likes and inserted {} new tweets in DB",
+            state.fetched_count, state.inserted_count
+        );
+        Ok(())
+    }
+
+    /// Goes through the whole likes list to notice un-likes, which is a lot
+    /// more calls than the likes pass, so it’s only done once a day.
+    pub fn sync_unlikes(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        let mut state = sync_state::start(conn, &self.account, "unlikes")?;
+        if let Some(at) = state.last_success_at {
+            if Utc::now().signed_duration_since(at) < chrono::Duration::hours(UNLIKES_EVERY_HOURS) {
+                println!("-- Un-likes checked less than {} hours ago, skip.", UNLIKES_EVERY_HOURS);
+                return Ok(());
+            }
+        }
+
+        let res = self.unlikes_pass(conn, &mut state);
+        state.record(conn, res)
+    }
+
+    fn unlikes_pass(&self, conn: &DbConnection, state: &mut SyncState) -> Result<(), OmeletteError> {
+        use crate::schema::{statuses, twitter_likes};
+        use egg_mode::tweet::lookup_map;
+
+        // No resuming: un-likes are only known once the whole list is seen.
+        state.cursor = None;
+        state.fetched_count = 0;
+        state.inserted_count = 0;
+        let started = Utc::now();
+        let seen = self.like_pages(conn, state, None, false)?;
+
+        // The list pages by tweet ID although it’s in the order of the likes,
+        // so it can skip some, and it only goes back so far. So likes it
+        // didn’t have aren’t taken as gone: the tweets are looked up, which
+        // says whether the account still likes them.
+        let unlisted: Vec<(i32, String)> = twitter_likes::table
+            .inner_join(statuses::table)
+            .select((twitter_likes::id, statuses::source_id))
+            .filter(twitter_likes::account_id.eq(self.account.id))
+            .filter(twitter_likes::unliked_at.is_null())
+            .filter(
+                twitter_likes::listed_at
+                    .is_null()
+                    .or(twitter_likes::listed_at.lt(started)),
+            )
+            .load(conn)?;
+
+        let mut unliked = Vec::new();
+        for chunk in unlisted.chunks(100) {
+            let ids: Vec<u64> = chunk.iter().filter_map(|(_, sid)| sid.parse().ok()).collect();
+            let tweets = self.call(conn, "statuses/lookup", || lookup_map(ids.clone(), &self.token))?;
+
+            for (like, sid) in chunk {
+                let tweet = sid.parse().ok().and_then(|sid: u64| tweets.get(&sid));
+                if let Some(Some(tweet)) = tweet {
+                    if tweet.favorited == Some(false) {
+                        unliked.push(*like);
+                    }
+                }
+            }
+        }
+
+        Self::store_unliked(conn, &unliked)?;
+
+        println!(
+            "=> Checked {} likes, looked up {} not in the list, and noticed {} un-likes",
+            seen.len(), unlisted.len(), unliked.len()
+        );
+        Ok(())
+    }
+
+    /// Pages down the likes list from `max` (or the top), saving each page as
+    /// it comes, to the end, or with `until_known` until a page that brings
+    /// no new like.
+    ///
+    /// Returns the IDs of the liked tweets seen, newest like first.
+    fn like_pages(
+        &self,
+        conn: &DbConnection,
+        state: &mut SyncState,
+        max: Option<u64>,
+        until_known: bool,
+    ) -> Result<Vec<u64>, OmeletteError> {
+        let likes = liked_by(self.id, &self.token).with_page_size(200);
+        let mut max = max;
+        let mut seen = Vec::new();
+        let mut batch = 0;
+
+        loop {
+            let feed = self.call(conn, "favorites/list", || likes.call(None, max))?;
+            batch += 1;
+
+            let ids: Vec<u64> = feed.iter().map(|t| t.id).collect();
+            let lowest = match ids.iter().min() {
+                Some(l) => *l,
+                None => {
+                    println!("-> Likes batch {} is empty, end of the line", batch);
+                    break;
+                }
+            };
+
+            let (inserted, liked) = self.store_likes(conn, &feed)?;
+            println!(
+                "-> Likes batch {} ({} tweets, {} new, {} new likes)",
+                batch, ids.len(), inserted, liked
+            );
+
+            // Pages go by tweet ID, like for the timeline, even though likes
+            // aren’t in that order.
+            max = Some(lowest - 1);
+            state.progress(conn, max.map(|m| format!("{}", m)), ids.len(), inserted)?;
+            seen.extend(ids);
+
+            if until_known && liked == 0 {
+                println!("-> Caught up with the likes already stored");
+                break;
+            }
+        }
+
+        Ok(seen)
+    }
+
+    /// Stores a page of liked tweets, and records them as liked by the
+    /// account. Returns how many tweets were new, and how many likes.
+    fn store_likes(&self, conn: &DbConnection, feed: &[Tweet]) -> Result<(usize, usize), OmeletteError> {
+        let now = Utc::now();
+        let statusbag: Vec<NewStatus> = feed
+            .iter()
+            .map(|tweet| {
+                let mut status: NewStatus = tweet.into();
+                status.is_marked = true;
+                status.marked_at = Some(now);
+                status.account_id = Some(self.account.id);
+                status
+            })
+            .collect();
+
+        let liked_ids: Vec<String> = statusbag.iter().map(|s| s.source_id.clone()).collect();
+        let inserted = Self::store_tweets(conn, statusbag, feed)?;
+
+        // Tweets we already had too (our own, or liked by another account)
+        let stored: Vec<i32> = {
+            use crate::schema::statuses::dsl::*;
+            statuses
+                .select(id)
+                .filter(source.eq(Source::Twitter))
+                .filter(source_id.eq_any(&liked_ids))
+                .load(conn)?
+        };
+
+        let liked = Self::store_liked(conn, self.account.id, &stored)?;
+        Ok((inserted.len(), liked))
+    }
+
+    /// Records statuses as liked by the account, and as seen in its likes
+    /// just now, and marks them. Likes noticed gone before start afresh.
+    /// Returns how many likes were new.
+    pub fn store_liked(conn: &DbConnection, account: i32, liked: &[i32]) -> Result<usize, OmeletteError> {
+        let now = Utc::now();
+        let rows: Vec<NewTwitterLike> = liked
+            .iter()
+            .map(|sid| NewTwitterLike {
+                account_id: Some(account),
+                status_id: *sid,
+                liked_at: now,
+                listed_at: Some(now),
+            })
+            .collect();
+
+        Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+            let new = {
+                use crate::schema::twitter_likes::dsl::*;
+
+                let inserted = insert_or_ignore!(twitter_likes, &rows, (account_id, status_id))
+                    .execute(conn)?;
+
+                let reliked = diesel::update(
+                    twitter_likes
+                        .filter(account_id.eq(account))
+                        .filter(status_id.eq_any(liked))
+                        .filter(unliked_at.is_not_null()),
+                )
+                .set((liked_at.eq(now), unliked_at.eq(None::<DateTime<Utc>>)))
+                .execute(conn)?;
+
+                diesel::update(
+                    twitter_likes
+                        .filter(account_id.eq(account))
+                        .filter(status_id.eq_any(liked)),
+                )
+                .set(listed_at.eq(now))
+                .execute(conn)?;
+
+                inserted + reliked
+            };
+
+            {
+                use crate::schema::statuses::dsl::*;
+                diesel::update(statuses.filter(id.eq_any(liked)).filter(marked_at.is_null()))
+                    .set((is_marked.eq(true), marked_at.eq(now)))
+                    .execute(conn)?;
+            }
+
+            Ok(new)
+        })?)
+    }
+
+    /// Records likes as noticed gone, and unmarks the statuses no account
+    /// likes anymore.
+    pub fn store_unliked(conn: &DbConnection, unliked: &[i32]) -> Result<(), OmeletteError> {
+        use crate::schema::{statuses, twitter_likes};
+
+        if unliked.is_empty() {
+            return Ok(());
+        }
+
+        Ok(conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::update(twitter_likes::table.filter(twitter_likes::id.eq_any(unliked)))
+                .set(twitter_likes::unliked_at.eq(Utc::now()))
+                .execute(conn)?;
+
+            let unliked_statuses = twitter_likes::table
+                .select(twitter_likes::status_id)
+                .filter(twitter_likes::id.eq_any(unliked));
+            let still_liked = twitter_likes::table
+                .select(twitter_likes::status_id)
+                .filter(twitter_likes::unliked_at.is_null());
+
+            diesel::update(
+                statuses::table
+                    .filter(statuses::id.eq_any(unliked_statuses))
+                    .filter(statuses::id.ne_all(still_liked)),
+            )
+            .set((
+                statuses::is_marked.eq(false),
+                statuses::marked_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)?;
+
+            Ok(())
+        })?)
+    }
+}
+
+/// The Twitter user ID at the end of a `source_author`, as written by
+/// `NewStatus::from(&Tweet)`: `"Name" <@handle> (id)`.
+pub fn author_source_id(source_author: &str) -> Option<u64> {
+    if !source_author.ends_with(')') {
+        return None;
+    }
+
+    let start = source_author.rfind(" (")? + 2;
+    source_author[start..source_author.len() - 1].parse().ok()
+}
+
+impl StatusSource for Twitter {
+    fn account(&self) -> &Account {
+        &self.account
+    }
+
+    /// Runs every pass even if one fails, returning the first error.
+    fn sync(&self, conn: &DbConnection) -> Result<(), OmeletteError> {
+        println!("-> Forward sync");
+        let forward = self.sync_forward(conn);
+        println!("-> Backfill");
+        let backfill = self.sync_backfill(conn);
+        println!("-> Likes");
+        let likes = self.sync_likes(conn);
+        println!("-> Un-likes");
+        let unlikes = self.sync_unlikes(conn);
+        forward.and(backfill).and(likes).and(unlikes)
+    }
+
+    fn delete(&self, conn: &DbConnection, status: &Status) -> Result<(), DeleteError> {
+        if status.deleted_at.is_some() {
+            return Err(DeleteError::AlreadyDone);
+        }
+        if status.source != Source::Twitter {
+            return Err(DeleteError::WrongSource);
+        }
+
+        let id: u64 = status
+            .source_id
+            .parse()
+            .map_err(|_| DeleteError::InvalidId(status.source_id.clone()))?;
+
+        let done = if status.is_repost {
+            self.call_once(conn, "statuses/unretweet/:id", || unretweet(id, &self.token))
+                .map(|_| ())
+        } else {
+            self.call_once(conn, "statuses/destroy/:id", || delete(id, &self.token))
+                .map(|_| ())
+        };
+
+        // Gone already, deleted elsewhere or by an earlier attempt.
+        let gone = match done {
+            Ok(()) => false,
+            Err(ref err) if client::is_not_found(err) => true,
+            Err(err) => return Err(err.into()),
+        };
+
+        {
+            use crate::schema::statuses::dsl::*;
+            diesel::update(statuses.find(status.id))
+                .set(deleted_at.eq(Utc::now()))
+                .execute(conn)?;
+        }
+
+        if gone {
+            Err(DeleteError::AlreadyDone)
+        } else {
+            Ok(())
+        }
+    }
+}
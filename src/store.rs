@@ -1,65 +1,796 @@
 use blobstore::{BlobStore, Store};
-use crate::models::{Entity, Status};
+use chrono::{DateTime, Duration, Utc};
+use crate::db::DbConnection;
+use crate::error::OmeletteError;
+use crate::inserts::MediaMetadata;
+use crate::media;
+use crate::models::{Entity, EntityVariant, Status};
 use diesel::{prelude::*, result::Error as DieselError};
-use reqwest::get;
-use std::path::Path;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
 
-pub fn sync(conn: &PgConnection, path: &Path) {
-    use crate::schema::entities::dsl::*;
-    use crate::schema::statuses;
+/// How downloads are run, see `sync`.
+#[derive(Clone, Copy, Debug)]
+pub struct Downloads {
+    /// How many to run at once.
+    pub parallel: usize,
+    /// Failures in a row after which the media is given up as lost.
+    pub give_up_after: i32,
+    /// Which variants of videos and gifs to download.
+    pub variants: Variants,
+}
+
+impl Default for Downloads {
+    fn default() -> Self {
+        Self {
+            parallel: 4,
+            give_up_after: 8,
+            variants: Variants::Best,
+        }
+    }
+}
+
+/// Which variants of videos and gifs to download. The best one is what the
+/// entity itself points to, so it always is.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Variants {
+    /// Only the highest bitrate.
+    Best,
+    /// The highest bitrate, and the poster image as a small preview.
+    BestPlusPoster,
+    /// Every encoding, and the poster image.
+    All,
+}
+
+impl FromStr for Variants {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best" => Ok(Variants::Best),
+            "best-plus-poster" => Ok(Variants::BestPlusPoster),
+            "all" => Ok(Variants::All),
+            _ => Err(format!("unknown variants {:?}, expected best, best-plus-poster, or all", s)),
+        }
+    }
+}
+
+/// What a download is for.
+#[derive(Clone, Copy, Debug)]
+enum Target {
+    Entity(i32),
+    Variant(i32),
+}
+
+/// A download to make, with what’s needed to record how it went.
+struct Job {
+    target: Target,
+    url: String,
+    failures: i32,
+    what: String,
+}
+
+/// Why a download failed. Permanent failures are those the server says won’t
+/// go away, like a 404, but they’re retried like the others until the media is
+/// given up on, as Twitter sometimes serves those for a while.
+#[derive(Debug)]
+enum Failure {
+    Transient(String),
+    Permanent(String),
+}
+
+impl Failure {
+    fn message(&self) -> &str {
+        match self {
+            Failure::Transient(msg) | Failure::Permanent(msg) => msg,
+        }
+    }
+}
 
-    let todo: Vec<(Entity, Status)> = entities
+/// The wait before retrying after `failures` in a row: an hour, doubling each
+/// time, up to a week.
+fn backoff(failures: i32) -> Duration {
+    let hours = 1i64 << (failures - 1).max(0).min(8);
+    Duration::hours(hours).min(Duration::weeks(1))
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    stored: usize,
+    transient: usize,
+    permanent: usize,
+    lost: usize,
+}
+
+/// Downloads pending entities and the variants asked for, returning how many
+/// were stored, then records what’s in blobs stored without it (by an import,
+/// or before it was).
+///
+/// Downloads run in parallel, and each is recorded as soon as it’s done, so an
+/// interrupted run loses nothing. Failures are recorded on the entity or
+/// variant and retried on a later run, backing off each time, until the media
+/// is given up as lost.
+pub fn sync(conn: &DbConnection, path: &Path, opts: Downloads) -> Result<usize, OmeletteError> {
+    use crate::schema::{entities, entity_variants, statuses};
+
+    let bs = BlobStore::new(path.to_string_lossy().into());
+
+    let todo: Vec<(Entity, Status)> = entities::table
         .inner_join(statuses::table)
         .filter(statuses::deleted_at.is_null())
-        .filter(blob_hash.is_null())
-        .order_by(fetched_at)
-        .load(conn)
-        .expect("!! Cannot load entities from DB");
+        .filter(entities::blob_hash.is_null())
+        .filter(entities::lost_at.is_null())
+        .filter(entities::retry_at.is_null().or(entities::retry_at.le(Utc::now())))
+        .order_by(entities::fetched_at)
+        .load(conn)?;
 
-    if todo.is_empty() {
+    // The best variant is the entity’s own media, see `share_best`.
+    let variants: Vec<(EntityVariant, (Entity, Status))> = match opts.variants {
+        Variants::Best => Vec::new(),
+        wanted => entity_variants::table
+            .inner_join(entities::table.inner_join(statuses::table))
+            .filter(statuses::deleted_at.is_null())
+            .filter(entity_variants::blob_hash.is_null())
+            .filter(entity_variants::lost_at.is_null())
+            .filter(
+                entity_variants::retry_at
+                    .is_null()
+                    .or(entity_variants::retry_at.le(Utc::now())),
+            )
+            .order_by(entity_variants::id)
+            .load::<(EntityVariant, (Entity, Status))>(conn)?
+            .into_iter()
+            .filter(|(variant, (entity, _))| {
+                variant.url != entity.source_url
+                    && (wanted == Variants::All || variant.kind == "poster")
+            })
+            .collect(),
+    };
+
+    let mut jobs: Vec<Job> = Vec::with_capacity(todo.len() + variants.len());
+    for (entity, status) in &todo {
+        jobs.push(Job {
+            target: Target::Entity(entity.id),
+            url: entity.source_url.clone(),
+            failures: entity.download_failures,
+            what: format!(
+                "Entity #{} for {:?} {} (#{})",
+                entity.id, status.source, status.source_id, status.id
+            ),
+        });
+    }
+    for (variant, (entity, status)) in &variants {
+        jobs.push(Job {
+            target: Target::Variant(variant.id),
+            url: variant.url.clone(),
+            failures: variant.download_failures,
+            what: format!(
+                "{} variant #{} of entity #{} for {:?} {} (#{})",
+                variant.kind, variant.id, entity.id, status.source, status.source_id, status.id
+            ),
+        });
+    }
+
+    if jobs.is_empty() {
         println!("=> All entities in DB are already local, skip.");
-        return;
+        share_best(conn)?;
+        inspect_stored(conn, &bs)?;
+        return Ok(0);
     }
 
-    println!("\n=> Downloading content for {} entities", todo.len());
+    let parallel = opts.parallel.max(1);
+    println!(
+        "\n=> Downloading content for {} entities and {} variants, {} at a time",
+        todo.len(),
+        variants.len(),
+        parallel
+    );
 
-    let bs = BlobStore::new(path.to_string_lossy().into());
+    let queue: Vec<(usize, String)> = jobs
+        .iter()
+        .enumerate()
+        .map(|(n, job)| (n, job.url.clone()))
+        .collect();
+    let queue = Arc::new(Mutex::new(queue.into_iter()));
+    let (done, results) = mpsc::channel();
+    let client = Client::new();
 
-    let mut successes = 0;
-    for (entity, status) in &todo {
-        println!(
-            "\n-> Downloading entity #{} for {:?} {} (#{})\n:: {}",
-            entity.id, status.source, status.source_id, status.id, entity.source_url
-        );
+    // Workers only download and store, as the connection can’t be shared:
+    // results are recorded here as they come in.
+    let workers: Vec<_> = (0..parallel)
+        .map(|_| {
+            let queue = queue.clone();
+            let done = done.clone();
+            let client = client.clone();
+            let bs = BlobStore::new(path.to_string_lossy().into());
 
-        match get(&entity.source_url) {
-            Err(err) => println!("!! Error downloading: {:?}", err),
-            Ok(ref mut resp) => match bs.put(resp) {
-                Err(err) => println!("!! Error storing: {:?}", err),
-                Ok(hash) => match write_hash(conn, &entity, &hash) {
-                    Err(err) => println!("!! Error recording: {:?}", err),
-                    Ok(_) => {
-                        successes += 1;
-                        println!("== Stored at hash {}.", hash);
-                    }
-                },
+            thread::spawn(move || loop {
+                let next = queue.lock().unwrap().next();
+                let (n, url) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                if done.send((n, download(&client, &bs, &url))).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect();
+    drop(done);
+
+    let mut summary = Summary::default();
+    for (count, (n, result)) in results.iter().enumerate() {
+        let job = &jobs[n];
+        let what = format!("[{}/{}] {}", count + 1, jobs.len(), job.what);
+
+        match result {
+            Ok((hash, meta)) => match write_hash(conn, job.target, &hash, &meta) {
+                Err(err) => println!("!! {}: error recording: {:?}", what, err),
+                Ok(_) => {
+                    summary.stored += 1;
+                    println!(
+                        "-> {}: stored at hash {} ({}, {} bytes)",
+                        what,
+                        hash,
+                        meta.mime_type.as_ref().map_or("unknown type", |t| t.as_str()),
+                        meta.byte_size.unwrap_or(0)
+                    );
+                }
             },
-        };
+            Err(failure) => {
+                match failure {
+                    Failure::Transient(_) => summary.transient += 1,
+                    Failure::Permanent(_) => summary.permanent += 1,
+                }
+
+                match record_failure(conn, job, &failure, opts) {
+                    Err(err) => println!("!! {}: error recording failure: {:?}", what, err),
+                    Ok(true) => {
+                        summary.lost += 1;
+                        println!(
+                            "!! {}: {}, giving up after {} failures\n:: {}",
+                            what,
+                            failure.message(),
+                            job.failures + 1,
+                            job.url
+                        );
+                    }
+                    Ok(false) => println!(
+                        "!! {}: {}, will retry\n:: {}",
+                        what,
+                        failure.message(),
+                        job.url
+                    ),
+                }
+            }
+        }
+    }
+
+    for worker in workers {
+        let _ = worker.join();
     }
 
     println!(
-        "\n=> Successfully downloaded {} (out of {}) entities",
-        successes,
-        todo.len()
+        "\n=> Downloaded {} (out of {}) entities and variants",
+        summary.stored,
+        jobs.len()
     );
+    if summary.transient + summary.permanent > 0 {
+        println!(
+            "=> Failed: {} transient (network or server errors), {} permanent (gone or forbidden), {} given up as lost",
+            summary.transient, summary.permanent, summary.lost
+        );
+    }
+
+    share_best(conn)?;
+    inspect_stored(conn, &bs)?;
+    Ok(summary.stored)
+}
+
+/// Downloads into the store, returning the hash and what’s in the content.
+fn download(client: &Client, bs: &BlobStore, url: &str) -> Result<(String, MediaMetadata), Failure> {
+    let mut resp = client
+        .get(url)
+        .send()
+        .map_err(|err| Failure::Transient(format!("error downloading: {}", err)))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let msg = format!("server said {}", status);
+        let retryable = status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS;
+        return Err(if status.is_client_error() && !retryable {
+            Failure::Permanent(msg)
+        } else {
+            Failure::Transient(msg)
+        });
+    }
+
+    let mut content = Vec::new();
+    resp.copy_to(&mut content)
+        .map_err(|err| Failure::Transient(format!("error downloading: {}", err)))?;
+
+    let hash = bs
+        .put(&mut &content[..])
+        .map_err(|err| Failure::Transient(format!("error storing: {:?}", err)))?;
+
+    Ok((hash, media::inspect(&content)))
+}
+
+fn write_hash(
+    conn: &DbConnection,
+    target: Target,
+    hash: &str,
+    meta: &MediaMetadata,
+) -> Result<(), DieselError> {
+    match target {
+        Target::Entity(eid) => {
+            use crate::schema::entities::dsl::*;
+            diesel::update(entities.find(eid))
+                .set((
+                    blob_hash.eq(hash),
+                    meta,
+                    download_failures.eq(0),
+                    download_error.eq(None::<String>),
+                    retry_at.eq(None::<DateTime<Utc>>),
+                ))
+                .execute(conn)?;
+        }
+        Target::Variant(vid) => {
+            use crate::schema::entity_variants::dsl::*;
+            diesel::update(entity_variants.find(vid))
+                .set((
+                    blob_hash.eq(hash),
+                    download_failures.eq(0),
+                    download_error.eq(None::<String>),
+                    retry_at.eq(None::<DateTime<Utc>>),
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Records a failed download, returning whether the media is now given up as lost.
+fn record_failure(
+    conn: &DbConnection,
+    job: &Job,
+    failure: &Failure,
+    opts: Downloads,
+) -> Result<bool, DieselError> {
+    let now = Utc::now();
+    let failures = job.failures + 1;
+    let lost = failures >= opts.give_up_after;
+    let retry = if lost { None } else { Some(now + backoff(failures)) };
+    let lost_on = if lost { Some(now) } else { None };
+
+    match job.target {
+        Target::Entity(eid) => {
+            use crate::schema::entities::dsl::*;
+            diesel::update(entities.find(eid))
+                .set((
+                    download_failures.eq(failures),
+                    download_error.eq(failure.message()),
+                    retry_at.eq(retry),
+                    lost_at.eq(lost_on),
+                ))
+                .execute(conn)?;
+        }
+        Target::Variant(vid) => {
+            use crate::schema::entity_variants::dsl::*;
+            diesel::update(entity_variants.find(vid))
+                .set((
+                    download_failures.eq(failures),
+                    download_error.eq(failure.message()),
+                    retry_at.eq(retry),
+                    lost_at.eq(lost_on),
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(lost)
+}
+
+/// Gives media given up as lost another chance, returning how many.
+pub fn retry_lost(conn: &DbConnection) -> Result<usize, OmeletteError> {
+    let ents = {
+        use crate::schema::entities::dsl::*;
+        diesel::update(entities.filter(lost_at.is_not_null()))
+            .set((
+                download_failures.eq(0),
+                retry_at.eq(None::<DateTime<Utc>>),
+                lost_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)?
+    };
+
+    let variants = {
+        use crate::schema::entity_variants::dsl::*;
+        diesel::update(entity_variants.filter(lost_at.is_not_null()))
+            .set((
+                download_failures.eq(0),
+                retry_at.eq(None::<DateTime<Utc>>),
+                lost_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)?
+    };
+
+    Ok(ents + variants)
+}
+
+/// The best variant of a video is the entity’s own media, so rather than
+/// being downloaded twice it’s given the entity’s blob once that’s stored.
+fn share_best(conn: &DbConnection) -> Result<usize, OmeletteError> {
+    use crate::schema::{entities, entity_variants::dsl::*};
+
+    let best: Vec<(i32, Option<String>)> = entity_variants
+        .inner_join(entities::table)
+        .filter(blob_hash.is_null())
+        .filter(entities::blob_hash.is_not_null())
+        .filter(url.eq(entities::source_url))
+        .select((id, entities::blob_hash))
+        .load(conn)?;
+
+    let shared = best.len();
+    for (vid, hash) in best {
+        diesel::update(entity_variants.find(vid))
+            .set((
+                blob_hash.eq(hash),
+                retry_at.eq(None::<DateTime<Utc>>),
+                lost_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(shared)
 }
 
-fn write_hash(conn: &PgConnection, entity: &Entity, hash: &String) -> Result<(), DieselError> {
+/// Records type, size, and dimensions for stored blobs that don’t have them.
+fn inspect_stored(conn: &DbConnection, bs: &BlobStore) -> Result<usize, OmeletteError> {
     use crate::schema::entities::dsl::*;
 
-    diesel::update(entities.find(entity.id))
-        .set(blob_hash.eq(hash))
-        .execute(conn)?;
+    let hashes: BTreeSet<String> = entities
+        .select(blob_hash)
+        .filter(blob_hash.is_not_null())
+        .filter(mime_type.is_null())
+        .load::<Option<String>>(conn)?
+        .into_iter()
+        .filter_map(|hash| hash)
+        .collect();
+
+    if hashes.is_empty() {
+        return Ok(0);
+    }
+
+    println!("\n=> Recording what’s in {} stored blobs", hashes.len());
+
+    let mut done = 0;
+    let mut unreadable = 0;
+    for hash in &hashes {
+        let mut content = Vec::new();
+        match bs.get(hash).and_then(|mut blob| blob.read_to_end(&mut content)) {
+            Err(_) => unreadable += 1,
+            Ok(_) => {
+                diesel::update(entities.filter(blob_hash.eq(hash)))
+                    .set(&media::inspect(&content))
+                    .execute(conn)?;
+                done += 1;
+            }
+        }
+    }
+
+    if unreadable > 0 {
+        println!(
+            "!! {} blobs could not be read, run `omelette mediatise verify` to check the store",
+            unreadable
+        );
+    }
+
+    println!("=> Recorded {} blobs", done);
+    Ok(done)
+}
+
+/// The blobs in the store, by hash. Blobs are files named after the rest of
+/// their hash, in folders named after its first two characters; dotfiles are
+/// the store’s temporary files.
+fn blobs(path: &Path) -> Result<HashMap<String, PathBuf>, OmeletteError> {
+    let mut found = HashMap::new();
+    if !path.exists() {
+        return Ok(found);
+    }
+
+    for dir in fs::read_dir(path)? {
+        let dir = dir?;
+        if !dir.file_type()?.is_dir() {
+            continue;
+        }
+
+        let prefix = dir.file_name().to_string_lossy().into_owned();
+        for entry in fs::read_dir(dir.path())? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_file() && !name.starts_with('.') {
+                found.insert(format!("{}{}", prefix, name), entry.path());
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Hashes content the way the store does.
+fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// The hashes entities and their variants refer to.
+fn referenced(conn: &DbConnection) -> Result<BTreeSet<String>, OmeletteError> {
+    use crate::schema::{entities, entity_variants};
+
+    let ents = entities::table
+        .select(entities::blob_hash)
+        .filter(entities::blob_hash.is_not_null())
+        .distinct()
+        .load::<Option<String>>(conn)?;
+
+    let variants = entity_variants::table
+        .select(entity_variants::blob_hash)
+        .filter(entity_variants::blob_hash.is_not_null())
+        .distinct()
+        .load::<Option<String>>(conn)?;
+
+    Ok(ents.into_iter().chain(variants).filter_map(|hash| hash).collect())
+}
+
+/// If none of the referenced blobs are found, the store is elsewhere or isn’t
+/// laid out as expected, and everything would look missing or unreferenced.
+fn check_layout(
+    hashes: &BTreeSet<String>,
+    stored: &HashMap<String, PathBuf>,
+    path: &Path,
+) -> Result<(), OmeletteError> {
+    if !hashes.is_empty() && !hashes.iter().any(|hash| stored.contains_key(hash)) {
+        return Err(OmeletteError::Data(format!(
+            "none of the {} referenced blobs are in {}, is that the right store?",
+            hashes.len(),
+            path.display()
+        )));
+    }
 
     Ok(())
 }
+
+/// Checks every blob entities and variants refer to. Missing blobs, and those whose content
+/// doesn’t match their hash, have their hash cleared so the next sync
+/// downloads them again; corrupt blobs are removed first, so the download
+/// doesn’t find them in its place.
+///
+/// Returns how many blobs were fine, and how many were cleared.
+pub fn verify(conn: &DbConnection, path: &Path) -> Result<(usize, usize), OmeletteError> {
+    use crate::schema::{entities, entity_variants};
+
+    let hashes = referenced(conn)?;
+    let stored = blobs(path)?;
+    check_layout(&hashes, &stored, path)?;
+
+    println!("=> Verifying {} blobs", hashes.len());
+
+    let mut good = 0;
+    let mut cleared = 0;
+    for hash in &hashes {
+        let problem = match stored.get(hash) {
+            None => Some("missing"),
+            Some(file) => {
+                let content = fs::read(file)?;
+                if content_hash(&content) == *hash {
+                    None
+                } else {
+                    fs::remove_file(file)?;
+                    Some("corrupt")
+                }
+            }
+        };
+
+        match problem {
+            None => good += 1,
+            Some(problem) => {
+                let ents = diesel::update(entities::table.filter(entities::blob_hash.eq(hash)))
+                    .set(entities::blob_hash.eq(None::<String>))
+                    .execute(conn)?;
+                let variants =
+                    diesel::update(entity_variants::table.filter(entity_variants::blob_hash.eq(hash)))
+                        .set(entity_variants::blob_hash.eq(None::<String>))
+                        .execute(conn)?;
+                cleared += 1;
+                println!(
+                    "!! Blob {} is {}, cleared from {} entities and {} variants",
+                    hash, problem, ents, variants
+                );
+            }
+        }
+    }
+
+    println!(
+        "=> {} blobs are fine, {} will be downloaded again on the next sync",
+        good, cleared
+    );
+    Ok((good, cleared))
+}
+
+/// Finds blobs no entity or variant refers to, and removes them unless `dry_run`.
+/// Returns how many there are.
+///
+/// Blobs written less than `grace` ago are left alone: a sync running at the
+/// same time stores each download before recording its hash.
+pub fn gc(
+    conn: &DbConnection,
+    path: &Path,
+    grace: Duration,
+    dry_run: bool,
+) -> Result<usize, OmeletteError> {
+    let hashes = referenced(conn)?;
+    let stored = blobs(path)?;
+
+    check_layout(&hashes, &stored, path)?;
+
+    let mut recent = 0;
+    let mut unreferenced: Vec<(&String, &PathBuf)> = Vec::new();
+    for (hash, file) in &stored {
+        if hashes.contains(hash) {
+            continue;
+        }
+
+        let age = fs::metadata(file)?.modified()?.elapsed().unwrap_or_default();
+        if Duration::from_std(age).unwrap_or_else(|_| Duration::max_value()) < grace {
+            recent += 1;
+        } else {
+            unreferenced.push((hash, file));
+        }
+    }
+    unreferenced.sort();
+
+    let mut bytes = 0;
+    for (hash, file) in &unreferenced {
+        bytes += fs::metadata(file)?.len();
+        if dry_run {
+            println!("-> Would remove {}", hash);
+        } else {
+            fs::remove_file(file)?;
+            println!("-> Removed {}", hash);
+        }
+    }
+
+    println!(
+        "=> {} of {} blobs unreferenced ({} bytes){}",
+        unreferenced.len(),
+        stored.len(),
+        bytes,
+        if dry_run { ", nothing removed (dry run)" } else { ", removed" }
+    );
+    if recent > 0 {
+        println!("=> {} more written in the last {} hours, left alone", recent, grace.num_hours());
+    }
+    Ok(unreferenced.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+    use crate::types::{MediaType, Source};
+
+    fn store(name: &str) -> (PathBuf, BlobStore) {
+        let path = std::env::temp_dir().join(format!("omelette-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let bs = BlobStore::new(path.to_string_lossy().into());
+        (path, bs)
+    }
+
+    fn put(bs: &BlobStore, content: &str) -> String {
+        bs.put(&mut content.as_bytes()).unwrap()
+    }
+
+    fn entity(conn: &DbConnection, sid: &str, hash: &str) {
+        use crate::schema::{entities, statuses};
+
+        let now = Utc::now();
+        diesel::insert_into(statuses::table)
+            .values((
+                statuses::text.eq(""),
+                statuses::posted_at.eq(now),
+                statuses::fetched_at.eq(now),
+                statuses::source.eq(Source::Twitter),
+                statuses::source_id.eq(sid),
+                statuses::source_author.eq(""),
+                statuses::source_app.eq(""),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        let status: i32 = statuses::table
+            .select(statuses::id)
+            .filter(statuses::source_id.eq(sid))
+            .first(conn)
+            .unwrap();
+
+        diesel::insert_into(entities::table)
+            .values((
+                entities::fetched_at.eq(now),
+                entities::status_id.eq(status),
+                entities::media_type.eq(MediaType::Photo),
+                entities::source_id.eq(sid),
+                entities::source_url.eq(format!("https://example.org/{}.jpg", sid)),
+                entities::blob_hash.eq(hash),
+                entities::source.eq(Source::Twitter),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn hash_of(conn: &DbConnection, sid: &str) -> Option<String> {
+        use crate::schema::entities::dsl::*;
+        entities
+            .select(blob_hash)
+            .filter(source_id.eq(sid))
+            .first(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn clears_and_removes_corrupt_blobs() {
+        let conn = test_connection();
+        let (path, bs) = store("verify");
+
+        let good = put(&bs, "good");
+        let bad = put(&bs, "bad");
+        entity(&conn, "1", &good);
+        entity(&conn, "2", &bad);
+
+        let file = blobs(&path).unwrap()[&bad].clone();
+        fs::write(&file, "tampered").unwrap();
+
+        assert_eq!(verify(&conn, &path).unwrap(), (1, 1));
+        assert_eq!(hash_of(&conn, "1"), Some(good.clone()));
+        assert_eq!(hash_of(&conn, "2"), None);
+        assert!(!file.exists());
+        assert!(blobs(&path).unwrap().contains_key(&good));
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn removes_orphan_blobs_once_past_grace() {
+        let conn = test_connection();
+        let (path, bs) = store("gc");
+
+        let kept = put(&bs, "kept");
+        let orphan = put(&bs, "orphan");
+        entity(&conn, "1", &kept);
+
+        assert_eq!(gc(&conn, &path, Duration::hours(1), false).unwrap(), 0);
+        assert!(blobs(&path).unwrap().contains_key(&orphan));
+
+        assert_eq!(gc(&conn, &path, Duration::zero(), true).unwrap(), 1);
+        assert!(blobs(&path).unwrap().contains_key(&orphan));
+
+        assert_eq!(gc(&conn, &path, Duration::zero(), false).unwrap(), 1);
+        let left = blobs(&path).unwrap();
+        assert!(left.contains_key(&kept));
+        assert!(!left.contains_key(&orphan));
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
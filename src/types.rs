@@ -1,32 +1,52 @@
 use egg_mode::entities::MediaType as EggMediaType;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Clone, Debug, DbEnum, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, DbEnum, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[PgType = "source_t"]
 #[DieselType = "Source_t"]
 pub enum Source {
     #[db_rename = "twitter"]
+    #[serde(rename = "twitter")]
     Twitter,
     #[db_rename = "mastodon"]
+    #[serde(rename = "mastodon")]
     Mastodon,
 }
 
-#[derive(Clone, Debug, DbEnum, Hash, PartialEq, Eq, PartialOrd, Ord)]
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "twitter" => Ok(Source::Twitter),
+            "mastodon" => Ok(Source::Mastodon),
+            _ => Err(format!("unknown source {:?}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, DbEnum, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[PgType = "intermediary_source_t"]
 #[DieselType = "Intermediary_source_t"]
 pub enum IntermediarySource {
     #[db_rename = "twitter archive"]
+    #[serde(rename = "twitter archive")]
     TwitterArchive,
 }
 
-#[derive(Clone, Debug, DbEnum, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, DbEnum, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[PgType = "media_type_t"]
 #[DieselType = "Media_type_t"]
 pub enum MediaType {
     #[db_rename = "photo"]
+    #[serde(rename = "photo")]
     Photo,
     #[db_rename = "video"]
+    #[serde(rename = "video")]
     Video,
     #[db_rename = "gif"]
+    #[serde(rename = "gif")]
     Gif,
 }
 
@@ -39,3 +59,79 @@ impl From<&EggMediaType> for MediaType {
         }
     }
 }
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::Timestamptz;
+
+/// SQLite has no timezone-aware timestamp type, so this stores UTC timestamps
+/// as RFC 3339 text with millisecond precision. That has a fixed width, so
+/// timestamps still sort and compare correctly as text.
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+    use diesel::backend::Backend;
+    use diesel::deserialize::{self, FromSql};
+    use diesel::expression::{bound::Bound, AsExpression};
+    use diesel::serialize::{self, Output, ToSql};
+    use diesel::sql_types::{Nullable, Text};
+    use diesel::sqlite::Sqlite;
+    use std::io::Write;
+
+    #[derive(Clone, Copy, Debug, Default, QueryId, SqlType)]
+    #[sqlite_type = "Text"]
+    pub struct Timestamptz;
+
+    impl ToSql<Timestamptz, Sqlite> for DateTime<Utc> {
+        fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+            let text = self.to_rfc3339_opts(SecondsFormat::Millis, true);
+            ToSql::<Text, Sqlite>::to_sql(&text, out)
+        }
+    }
+
+    impl FromSql<Timestamptz, Sqlite> for DateTime<Utc> {
+        fn from_sql(value: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
+            let text = <String as FromSql<Text, Sqlite>>::from_sql(value)?;
+
+            // Also accept SQLite’s own CURRENT_TIMESTAMP format, just in case.
+            DateTime::parse_from_rfc3339(&text)
+                .map(|date| date.with_timezone(&Utc))
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f")
+                        .map(|date| DateTime::from_utc(date, Utc))
+                })
+                .map_err(|err| format!("bad timestamp {:?}: {}", text, err).into())
+        }
+    }
+
+    impl AsExpression<Timestamptz> for DateTime<Utc> {
+        type Expression = Bound<Timestamptz, Self>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
+
+    impl<'a> AsExpression<Timestamptz> for &'a DateTime<Utc> {
+        type Expression = Bound<Timestamptz, Self>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
+
+    impl AsExpression<Nullable<Timestamptz>> for DateTime<Utc> {
+        type Expression = Bound<Nullable<Timestamptz>, Self>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
+
+    impl<'a> AsExpression<Nullable<Timestamptz>> for &'a DateTime<Utc> {
+        type Expression = Bound<Nullable<Timestamptz>, Self>;
+
+        fn as_expression(self) -> Self::Expression {
+            Bound::new(self)
+        }
+    }
+}